@@ -10,3 +10,8 @@ SCRIPT_DIR=$( cd -P "$( dirname "$SCRIPT_SOURCE" )" >/dev/null 2>&1 && pwd )
 
 glslc "${SCRIPT_DIR}/shader.vert" -o "${SCRIPT_DIR}/shader.vert.spv"
 glslc "${SCRIPT_DIR}/shader.frag" -o "${SCRIPT_DIR}/shader.frag.spv"
+glslc "${SCRIPT_DIR}/skinned.vert" -o "${SCRIPT_DIR}/skinned.vert.spv"
+glslc "${SCRIPT_DIR}/points.vert" -o "${SCRIPT_DIR}/points.vert.spv"
+glslc "${SCRIPT_DIR}/quantized.vert" -o "${SCRIPT_DIR}/quantized.vert.spv"
+glslc "${SCRIPT_DIR}/position.vert" -o "${SCRIPT_DIR}/position.vert.spv"
+glslc "${SCRIPT_DIR}/lines.vert" -o "${SCRIPT_DIR}/lines.vert.spv"
@@ -1,2 +1,7 @@
 glslc "${PSScriptRoot}/shader.vert" -o "${PSScriptRoot}/shader.vert.spv"
 glslc "${PSScriptRoot}/shader.frag" -o "${PSScriptRoot}/shader.frag.spv"
+glslc "${PSScriptRoot}/skinned.vert" -o "${PSScriptRoot}/skinned.vert.spv"
+glslc "${PSScriptRoot}/points.vert" -o "${PSScriptRoot}/points.vert.spv"
+glslc "${PSScriptRoot}/quantized.vert" -o "${PSScriptRoot}/quantized.vert.spv"
+glslc "${PSScriptRoot}/position.vert" -o "${PSScriptRoot}/position.vert.spv"
+glslc "${PSScriptRoot}/lines.vert" -o "${PSScriptRoot}/lines.vert.spv"
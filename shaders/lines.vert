@@ -0,0 +1,27 @@
+#version 450
+
+layout(binding = 0) uniform MvpMatUBO {
+    mat4 view;
+    mat4 projection;
+} mvpMat;
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inColor;
+
+// Per-instance data
+layout(location = 2) in mat4 inModel;
+layout(location = 6) in vec4 inTint;
+
+layout(location = 0) out vec3 fragColor;
+layout(location = 1) out vec2 fragTexCoord;
+layout(location = 2) out vec4 fragTint;
+
+void main() {
+    gl_Position = mvpMat.projection
+        * mvpMat.view
+        * inModel
+        * vec4(inPosition, 1.0);
+    fragColor = inColor;
+    fragTexCoord = vec2(0.0);
+    fragTint = inTint;
+}
@@ -0,0 +1,30 @@
+#version 450
+
+layout(binding = 0) uniform MvpMatUBO {
+    mat4 view;
+    mat4 projection;
+} mvpMat;
+
+layout(location = 0) in vec3 inPosition; // 0 to 1 within the mesh's bounds
+layout(location = 1) in vec3 inColor;
+layout(location = 2) in vec2 inTexCoord;
+layout(location = 3) in vec2 inNormal; // octahedral, not used for shading yet
+layout(location = 4) in vec3 inTangent; // octahedral and bitangent sign, not used for shading yet
+
+// Per-instance data
+layout(location = 5) in mat4 inModel; // also scales positions back out of the mesh's bounds
+layout(location = 9) in vec4 inTint;
+
+layout(location = 0) out vec3 fragColor;
+layout(location = 1) out vec2 fragTexCoord;
+layout(location = 2) out vec4 fragTint;
+
+void main() {
+    gl_Position = mvpMat.projection
+        * mvpMat.view
+        * inModel
+        * vec4(inPosition, 1.0);
+    fragColor = inColor;
+    fragTexCoord = inTexCoord;
+    fragTint = inTint;
+}
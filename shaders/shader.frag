@@ -1,16 +1,17 @@
 #version 450
 
-layout(binding = 1) uniform sampler2D texSampler;
+layout(set = 1, binding = 0) uniform sampler2D texSampler;
 
 layout(push_constant) uniform PushConstants {
-    layout(offset = 64) float opacity;
+    vec4 baseColor;
 } pcs;
 
 layout(location = 0) in vec3 fragColor;
 layout(location = 1) in vec2 fragTexCoord;
+layout(location = 2) in vec4 fragTint; // the instance's tint, with its opacity in alpha
 
 layout(location = 0) out vec4 outColor;
 
 void main() {
-    outColor = vec4(texture(texSampler, fragTexCoord).rgb, pcs.opacity);
+    outColor = texture(texSampler, fragTexCoord) * vec4(fragColor, 1.0) * pcs.baseColor * fragTint;
 }
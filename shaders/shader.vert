@@ -5,22 +5,26 @@ layout(binding = 0) uniform MvpMatUBO {
     mat4 projection;
 } mvpMat;
 
-layout(push_constant) uniform PushConstants {
-    mat4 model;
-} pcs;
-
 layout(location = 0) in vec3 inPosition;
 layout(location = 1) in vec3 inColor;
 layout(location = 2) in vec2 inTexCoord;
+layout(location = 3) in vec3 inNormal; // not used for shading yet
+layout(location = 4) in vec4 inTangent; // not used for shading yet
+
+// Per-instance data
+layout(location = 5) in mat4 inModel;
+layout(location = 9) in vec4 inTint;
 
 layout(location = 0) out vec3 fragColor;
 layout(location = 1) out vec2 fragTexCoord;
+layout(location = 2) out vec4 fragTint;
 
 void main() {
     gl_Position = mvpMat.projection
         * mvpMat.view
-        * pcs.model
+        * inModel
         * vec4(inPosition, 1.0);
     fragColor = inColor;
     fragTexCoord = inTexCoord;
+    fragTint = inTint;
 }
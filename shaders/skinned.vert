@@ -0,0 +1,51 @@
+#version 450
+
+layout(binding = 0) uniform MvpMatUBO {
+    mat4 view;
+    mat4 projection;
+} mvpMat;
+
+// The joint matrices of every skinned object being drawn, one after another
+layout(set = 2, binding = 0) readonly buffer JointPalette {
+    mat4 joints[];
+} palette;
+
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inColor;
+layout(location = 2) in vec2 inTexCoord;
+layout(location = 3) in vec3 inNormal; // not used for shading yet
+layout(location = 4) in vec4 inTangent; // not used for shading yet
+layout(location = 5) in uvec4 inJoints;
+layout(location = 6) in vec4 inWeights;
+
+// Per-instance data
+layout(location = 7) in mat4 inModel;
+layout(location = 11) in vec4 inTint;
+layout(location = 12) in uint inFirstJoint; // where the instance's joint matrices start in the palette
+
+layout(location = 0) out vec3 fragColor;
+layout(location = 1) out vec2 fragTexCoord;
+layout(location = 2) out vec4 fragTint;
+
+void main() {
+    mat4 skin = mat4(0.0);
+    for (int i = 0; i < 4; i++) {
+        skin += inWeights[i] * palette.joints[inFirstJoint + inJoints[i]];
+    }
+
+    // Vertices without any weights aren't attached to the skeleton
+    float totalWeight = inWeights.x + inWeights.y + inWeights.z + inWeights.w;
+    if (totalWeight == 0.0) {
+        skin = mat4(1.0);
+    }
+
+    gl_Position = mvpMat.projection
+        * mvpMat.view
+        * inModel
+        * skin
+        * vec4(inPosition, 1.0);
+    fragColor = inColor;
+    fragTexCoord = inTexCoord;
+    fragTint = inTint;
+}
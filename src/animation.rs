@@ -0,0 +1,448 @@
+//! Skeletal animation: skeletons, the animation clips that move them, and
+//! evaluating both into the joint matrices that skinned vertices are
+//! transformed by.
+//!
+//! A [`Skin`] is a hierarchy of joints. Each frame, an [`AnimationPlayer`]
+//! samples its clips into a pose, which is the local [`Transform`] of every
+//! joint, and [`Skin::joint_matrices()`] turns the pose into a palette of
+//! matrices for the skinning vertex shader.
+
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+
+/// The translation, rotation, and scale of a joint relative to its parent.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Transform {
+    pub translation: glm::Vec3,
+    pub rotation: glm::Quat,
+    pub scale: glm::Vec3,
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            translation: glm::Vec3::zeros(),
+            rotation: glm::Quat::identity(),
+            scale: glm::vec3(1.0, 1.0, 1.0),
+        }
+    }
+
+    /// Scale, then rotate, then translate.
+    pub fn matrix(&self) -> glm::Mat4 {
+        glm::translation(&self.translation)
+            * glm::quat_to_mat4(&self.rotation)
+            * glm::scaling(&self.scale)
+    }
+
+    /// Interpolate between two transforms, where `t` is 0 for `self` and 1 for
+    /// `other`. Rotations take the shortest path.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            translation: glm::lerp(&self.translation, &other.translation, t),
+            rotation: slerp(&self.rotation, &other.rotation, t),
+            scale: glm::lerp(&self.scale, &other.scale, t),
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// The skeleton that a skinned mesh is attached to.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Skin {
+    /// Every joint comes after its parent.
+    pub joints: Vec<Joint>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Joint {
+    pub name: Option<String>,
+    /// Index into [`Skin::joints`].
+    pub parent: Option<usize>,
+    /// The transform of anything between the joint and its parent joint, or
+    /// between the joint and the model if it has no parent. Usually identity.
+    pub parent_transform: glm::Mat4,
+    /// The joint's transform when it isn't animated.
+    pub rest_pose: Transform,
+    /// Moves vertices from the model into the joint's space, as it was when
+    /// the mesh was bound to the skeleton.
+    pub inverse_bind_matrix: glm::Mat4,
+}
+
+impl Skin {
+    /// The pose of the skeleton when it isn't animated.
+    pub fn rest_pose(&self) -> Vec<Transform> {
+        self.joints.iter().map(|joint| joint.rest_pose).collect()
+    }
+
+    /// Turn a pose, with one transform per joint, into the matrices that move
+    /// each joint's vertices from where they were bound to where the pose puts
+    /// them.
+    pub fn joint_matrices(&self, pose: &[Transform]) -> Vec<glm::Mat4> {
+        let mut world_transforms: Vec<glm::Mat4> = Vec::with_capacity(self.joints.len());
+        for (joint, transform) in self.joints.iter().zip(pose) {
+            let parent = match joint.parent {
+                Some(parent) => world_transforms[parent],
+                None => glm::identity(),
+            };
+            world_transforms.push(parent * joint.parent_transform * transform.matrix());
+        }
+
+        world_transforms
+            .iter()
+            .zip(&self.joints)
+            .map(|(world, joint)| world * joint.inverse_bind_matrix)
+            .collect()
+    }
+}
+
+/// An animation that moves some of a skin's joints. Clips loop.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct AnimationClip {
+    pub name: Option<String>,
+    /// How long the clip is, in seconds.
+    pub duration: f32,
+    pub channels: Vec<Channel>,
+}
+
+impl AnimationClip {
+    /// Set the animated parts of `pose` to where they are `time` seconds into
+    /// the clip. The parts of the pose that the clip doesn't animate are left
+    /// alone.
+    pub fn sample(&self, time: f32, pose: &mut [Transform]) {
+        let time = if self.duration > 0.0 {
+            time.rem_euclid(self.duration)
+        } else {
+            0.0
+        };
+
+        for channel in &self.channels {
+            let Some(transform) = pose.get_mut(channel.joint) else {
+                continue;
+            };
+            let Some(value) = channel.sample(time) else {
+                continue;
+            };
+
+            match channel.property {
+                Property::Translation => transform.translation = value.xyz(),
+                Property::Rotation => transform.rotation = glm::Quat::from(value).normalize(),
+                Property::Scale => transform.scale = value.xyz(),
+            }
+        }
+    }
+}
+
+/// Keyframes for one property of one joint.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Channel {
+    /// Index into [`Skin::joints`].
+    pub joint: usize,
+    pub property: Property,
+    pub interpolation: Interpolation,
+    /// The time of each keyframe in seconds, in increasing order.
+    pub times: Vec<f32>,
+    /// The value at each keyframe. Rotations are quaternions in `xyzw`
+    /// order, and other properties only use `xyz`. Cubic spline keyframes
+    /// have three values each: the in-tangent, the value, and the out-tangent.
+    pub values: Vec<glm::Vec4>,
+}
+
+impl Channel {
+    /// The value of the property at `time`, holding the first and last
+    /// keyframes before and after the channel. `None` if there aren't any
+    /// keyframes.
+    fn sample(&self, time: f32) -> Option<glm::Vec4> {
+        let value = |keyframe: usize| match self.interpolation {
+            Interpolation::CubicSpline => self.values.get(keyframe * 3 + 1).copied(),
+            _ => self.values.get(keyframe).copied(),
+        };
+
+        let next = self.times.partition_point(|&t| t <= time);
+        if next == 0 {
+            return value(0);
+        }
+        if next == self.times.len() {
+            return value(next - 1);
+        }
+
+        let previous = next - 1;
+        let duration = self.times[next] - self.times[previous];
+        let t = (time - self.times[previous]) / duration;
+        let (a, b) = (value(previous)?, value(next)?);
+
+        Some(match (self.interpolation, self.property) {
+            (Interpolation::Step, _) => a,
+            (Interpolation::Linear, Property::Rotation) => {
+                slerp(&glm::Quat::from(a), &glm::Quat::from(b), t).coords
+            }
+            (Interpolation::Linear, _) => glm::lerp(&a, &b, t),
+            (Interpolation::CubicSpline, _) => {
+                // Tangents are scaled by the time between the keyframes
+                let out_tangent = *self.values.get(previous * 3 + 2)? * duration;
+                let in_tangent = *self.values.get(next * 3)? * duration;
+                let (t2, t3) = (t * t, t * t * t);
+
+                let value = a * (2.0 * t3 - 3.0 * t2 + 1.0)
+                    + out_tangent * (t3 - 2.0 * t2 + t)
+                    + b * (-2.0 * t3 + 3.0 * t2)
+                    + in_tangent * (t3 - t2);
+                match self.property {
+                    Property::Rotation => value.normalize(),
+                    _ => value,
+                }
+            }
+        })
+    }
+}
+
+/// Which part of a joint's [`Transform`] a [`Channel`] animates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Property {
+    Translation,
+    Rotation,
+    Scale,
+}
+
+/// How a [`Channel`] gets from one keyframe to the next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Interpolation {
+    /// Jump to each keyframe's value when its time comes.
+    Step,
+    /// Move at a constant rate. Rotations are spherically interpolated.
+    Linear,
+    /// Follow a cubic Hermite spline, with tangents given at each keyframe.
+    CubicSpline,
+}
+
+/// Blend between two poses, where `weight` is 0 for `from` and 1 for `to`.
+pub fn blend_poses(from: &[Transform], to: &[Transform], weight: f32) -> Vec<Transform> {
+    from.iter()
+        .zip(to)
+        .map(|(from, to)| from.lerp(to, weight))
+        .collect()
+}
+
+/// Plays one of a skin's animation clips at a time, crossfading from the last
+/// clip whenever a new one starts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AnimationPlayer {
+    current: Option<PlayingClip>,
+    previous: Option<PlayingClip>,
+    /// How many seconds it takes to fade from `previous` to `current`.
+    fade_duration: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PlayingClip {
+    /// Index into the clips passed to [`AnimationPlayer::pose()`].
+    clip: usize,
+    start_time: f32,
+}
+
+impl AnimationPlayer {
+    /// Start playing `clip` at `time`, right away.
+    pub fn new(clip: Option<usize>, time: f32) -> Self {
+        Self {
+            current: clip.map(|clip| PlayingClip {
+                clip,
+                start_time: time,
+            }),
+            previous: None,
+            fade_duration: 0.0,
+        }
+    }
+
+    /// The clip that's playing, or being faded into.
+    pub fn clip(&self) -> Option<usize> {
+        self.current.map(|c| c.clip)
+    }
+
+    /// Start playing `clip` from its beginning at `time`, fading into it from
+    /// the current clip over `fade_duration` seconds.
+    pub fn play(&mut self, clip: usize, time: f32, fade_duration: f32) {
+        self.previous = self.current;
+        self.current = Some(PlayingClip {
+            clip,
+            start_time: time,
+        });
+        self.fade_duration = fade_duration;
+    }
+
+    /// Sample the playing clips into a pose of `skin` at `time`.
+    pub fn pose(&self, skin: &Skin, clips: &[AnimationClip], time: f32) -> Vec<Transform> {
+        let sample = |playing: PlayingClip| {
+            let mut pose = skin.rest_pose();
+            if let Some(clip) = clips.get(playing.clip) {
+                clip.sample(time - playing.start_time, &mut pose);
+            }
+            pose
+        };
+
+        let Some(current) = self.current else {
+            return skin.rest_pose();
+        };
+        let pose = sample(current);
+
+        let fade = if self.fade_duration > 0.0 {
+            (time - current.start_time) / self.fade_duration
+        } else {
+            1.0
+        };
+        match self.previous {
+            Some(previous) if fade < 1.0 => blend_poses(&sample(previous), &pose, fade.max(0.0)),
+            _ => pose,
+        }
+    }
+}
+
+/// Spherically interpolate between two rotations, the short way around.
+fn slerp(a: &glm::Quat, b: &glm::Quat, t: f32) -> glm::Quat {
+    // q and -q are the same rotation, but interpolating towards the one
+    // further away goes the long way around
+    let b = if a.dot(b) < 0.0 { -b } else { *b };
+
+    if a.dot(&b) > 1.0 - f32::EPSILON {
+        return glm::quat_normalize(&glm::lerp(&a.coords, &b.coords, t).into());
+    }
+    glm::quat_slerp(a, &b, t)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn rotation_z(angle: f32) -> glm::Quat {
+        glm::quat_angle_axis(angle, &glm::Vec3::z())
+    }
+
+    /// Two joints in a line along x, with the second one 1 unit from the
+    /// first.
+    fn arm() -> Skin {
+        let joint = |parent, translation| Joint {
+            name: None,
+            parent,
+            parent_transform: glm::identity(),
+            rest_pose: Transform {
+                translation,
+                ..Transform::identity()
+            },
+            inverse_bind_matrix: glm::identity(),
+        };
+        let mut skin = Skin {
+            joints: vec![
+                joint(None, glm::Vec3::zeros()),
+                joint(Some(0), glm::Vec3::x()),
+            ],
+        };
+        skin.joints[1].inverse_bind_matrix = glm::translation(&-glm::Vec3::x());
+        skin
+    }
+
+    fn bend(interpolation: Interpolation) -> AnimationClip {
+        AnimationClip {
+            name: Some("bend".into()),
+            duration: 1.0,
+            channels: vec![Channel {
+                joint: 0,
+                property: Property::Rotation,
+                interpolation,
+                times: vec![0.0, 1.0],
+                values: vec![rotation_z(0.0).coords, rotation_z(FRAC_PI_2).coords],
+            }],
+        }
+    }
+
+    #[test]
+    fn rest_pose_gives_identity_joint_matrices() {
+        let skin = arm();
+
+        for matrix in skin.joint_matrices(&skin.rest_pose()) {
+            assert!((matrix - glm::Mat4::identity()).abs().max() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn children_follow_their_parents() {
+        let skin = arm();
+        let mut pose = skin.rest_pose();
+        bend(Interpolation::Linear).sample(0.5, &mut pose);
+
+        // Halfway through, the arm is bent 45 degrees, so the end of the arm
+        // at (2, 0, 0) rotates around the origin
+        let matrices = skin.joint_matrices(&pose);
+        let end = matrices[1] * glm::vec4(2.0, 0.0, 0.0, 1.0);
+        let expected = 2.0 * std::f32::consts::FRAC_1_SQRT_2;
+        assert!((end - glm::vec4(expected, expected, 0.0, 1.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn channels_interpolate_between_keyframes() {
+        let channel = Channel {
+            joint: 0,
+            property: Property::Translation,
+            interpolation: Interpolation::Linear,
+            times: vec![1.0, 2.0],
+            values: vec![glm::vec4(0.0, 0.0, 0.0, 0.0), glm::vec4(4.0, 0.0, 0.0, 0.0)],
+        };
+        assert_eq!(channel.sample(0.0).unwrap().x, 0.0);
+        assert_eq!(channel.sample(1.25).unwrap().x, 1.0);
+        assert_eq!(channel.sample(3.0).unwrap().x, 4.0);
+
+        let step = Channel {
+            interpolation: Interpolation::Step,
+            ..channel.clone()
+        };
+        assert_eq!(step.sample(1.9).unwrap().x, 0.0);
+
+        // Flat tangents ease in and out, passing through the middle halfway
+        let cubic = Channel {
+            interpolation: Interpolation::CubicSpline,
+            values: vec![
+                glm::Vec4::zeros(),
+                glm::vec4(0.0, 0.0, 0.0, 0.0),
+                glm::Vec4::zeros(),
+                glm::Vec4::zeros(),
+                glm::vec4(4.0, 0.0, 0.0, 0.0),
+                glm::Vec4::zeros(),
+            ],
+            ..channel
+        };
+        assert!((cubic.sample(1.5).unwrap().x - 2.0).abs() < 1e-5);
+        assert!(cubic.sample(1.25).unwrap().x < 1.0);
+    }
+
+    #[test]
+    fn player_crossfades_between_clips() {
+        let skin = arm();
+        let clips = [
+            bend(Interpolation::Step),
+            AnimationClip {
+                name: Some("still".into()),
+                duration: 1.0,
+                channels: vec![],
+            },
+        ];
+        let mut player = AnimationPlayer::new(Some(0), 0.0);
+
+        // The step clip holds its first keyframe for the whole first second
+        let angle = |pose: &[Transform]| pose[0].rotation.coords.z.asin() * 2.0;
+        assert_eq!(angle(&player.pose(&skin, &clips, 0.5)), 0.0);
+        assert_eq!(angle(&player.pose(&skin, &clips, 0.99)), 0.0);
+
+        // Halfway through fading out of the bend, the arm is bent half as far
+        // as the bend alone would bend it
+        let clips = [bend(Interpolation::Linear), clips[1].clone()];
+        player.play(1, 0.25, 1.0);
+        let halfway = angle(&player.pose(&skin, &clips, 0.75));
+        assert!((halfway - FRAC_PI_2 * 0.75 * 0.5).abs() < 1e-4, "{halfway}");
+        assert_eq!(angle(&player.pose(&skin, &clips, 1.25)), 0.0);
+        assert_eq!(player.clip(), Some(1));
+    }
+}
@@ -1,9 +1,15 @@
 use crate::{
-    model::load_model,
+    animation::{AnimationClip, AnimationPlayer, Skin},
+    bounds::{Aabb, Bounds},
+    bundle::BundleTexture,
+    camera::{Camera, CameraMode, FlyController, OrbitController},
+    loader::{Asset, AssetData, AssetLoader, Job, LoadedAsset},
+    model::{self, primitives, select_lod, ModelOptions, Topology},
     mvp_matrix::{MvpMat, MvpMatUBO},
     renderer::{
-        buffers::{
-            create_index_buffer, create_vertex_buffer, destroy_index_buffer, destroy_vertex_buffer,
+        buffers::{create_mesh_buffers, destroy_mesh_buffers, MeshBuffers},
+        capture::{
+            destroy_capture, read_capture, read_image_pixels, record_image_capture, ImageCapture,
         },
         commands::{create_command_buffers, create_command_pools},
         depth_tests::create_depth_objects,
@@ -11,24 +17,46 @@ use crate::{
         extensions::Extensions,
         instance::create_instance,
         multisampling::create_color_objects,
-        pipeline::{create_framebuffers, create_pipeline, create_render_pass},
+        offscreen::{create_offscreen_target, destroy_offscreen_target},
+        pipeline::{
+            create_framebuffers, create_pipeline, create_render_pass, MaterialPushConstants,
+        },
         swapchain::{create_swapchain, create_swapchain_image_views},
         synchronization::{create_sync_objects, destroy_sync_objects},
-        texture::{create_texture_image, create_texture_image_view, create_texture_sampler},
+        texture::{
+            create_texture_from_image, create_texture_from_mips, create_texture_from_pixels,
+            create_texture_sampler, destroy_texture, Texture,
+        },
         uniforms::{
             create_descriptor_pool, create_descriptor_set_layout, create_descriptor_sets,
-            create_uniform_buffers, destroy_descriptor_pool, destroy_uniform_buffers,
+            create_joint_buffers, create_texture_descriptor_sets, create_uniform_buffers,
+            destroy_descriptor_pool, destroy_descriptor_set_layout, destroy_joint_buffers,
+            destroy_texture_descriptor_pools, destroy_uniform_buffers, instance_buffer_size,
+            joint_buffer_size, update_joint_descriptor_sets,
         },
-        validation::should_enable_validation_layers,
+        uploads::{
+            begin_upload, destroy_upload, is_upload_finished, submit_upload, wait_for_upload,
+            Upload,
+        },
+    },
+    scene::Scene,
+    screenshot::Screenshot,
+    settings::AppSettings,
+    vertex::{
+        dequantize_matrix, InstanceData, JointWeights, QuantizedVertex, SkinnedVertex, Vertex,
+        VertexFormat,
     },
-    vertex::Vertex,
+    watcher::AssetWatcher,
     MAX_FRAMES_IN_FLIGHT,
 };
 
-use std::mem::size_of;
+use std::mem::{self, size_of};
+use std::ops::Range;
+use std::path::PathBuf;
 use std::ptr;
 use std::time::Instant;
 
+use ahash::{AHashMap, AHashSet};
 use ash::{
     extensions::{ext as vk_ext, khr as vk_khr},
     vk, Device, Entry, Instance,
@@ -38,11 +66,10 @@ use color_eyre::{
     Result,
 };
 use nalgebra_glm as glm;
-use tracing::debug;
+use tracing::{debug, error, info, warn};
 use winit::window::Window;
 
 /// Our Vulkan app.
-#[derive(Clone)]
 pub struct App {
     entry: Entry,
     instance: Instance,
@@ -52,6 +79,8 @@ pub struct App {
 
     /// The frame we're currently rendering
     frame: usize,
+    /// How many frames have been submitted for rendering in total.
+    frame_count: u64,
 
     /// Tracks if the window has been resized and the app needs to handle that.
     /// Set to true to signal to the app that the window has been resized.
@@ -59,9 +88,67 @@ pub struct App {
     /// event.
     resized: bool,
 
+    /// Whether to capture the next frame rendered for a screenshot. See
+    /// [`App::request_screenshot()`].
+    screenshot_requested: bool,
+    /// The copy of the frame being rendered for a screenshot, if one was
+    /// requested, until the frame finishes rendering.
+    pending_capture: Option<ImageCapture>,
+    /// The most recent screenshot, until it's taken with
+    /// [`App::take_screenshot()`].
+    screenshot: Option<Result<Screenshot>>,
+
     /// Global model-view-projection matrix.
     mvp_mat: MvpMat,
 
+    /// The camera the scene is viewed through. Drives the view and projection
+    /// parts of `mvp_mat`.
+    camera: Camera,
+    /// Which of the controllers below moves `camera`.
+    camera_mode: CameraMode,
+    /// Flies `camera` around from keyboard and mouse input.
+    fly_controller: FlyController,
+    /// Orbits `camera` around its target from mouse input.
+    orbit_controller: OrbitController,
+
+    /// The scene being drawn.
+    scene: Scene,
+    /// Which of the meshes and textures in `data` each of the scene's objects
+    /// is drawn with.
+    object_resources: Vec<ObjectResources>,
+    /// How to draw each of the meshes in `data`, once they've loaded.
+    mesh_draws: Vec<Option<MeshDraws>>,
+    /// How to draw [`AppData::placeholder_mesh`].
+    placeholder_draws: MeshDraws,
+    /// How to store the vertices of meshes as they're uploaded.
+    vertex_format: VertexFormat,
+    /// Which animation clips each of the scene's objects is playing. Objects
+    /// without a skin never play any.
+    animation_players: Vec<AnimationPlayer>,
+    /// How many seconds the scene's animations have been running for.
+    scene_time: f32,
+
+    /// Reads the scene's meshes and textures from disk in the background.
+    loader: AssetLoader,
+    /// Assets that have been read from disk, and are being uploaded to the GPU.
+    pending_assets: Vec<PendingAsset>,
+    /// The scene's assets that have finished loading, or failed to.
+    finished_assets: AHashSet<Asset>,
+    /// The resources owned by each of the scene's assets that has loaded.
+    asset_resources: AHashMap<Asset, AssetResources>,
+    /// Watches the files of the scene's assets, if they're reloaded when they
+    /// change.
+    watcher: Option<AssetWatcher>,
+    /// Resources replaced by reloaded assets, waiting to be destroyed.
+    retired: Vec<RetiredResources>,
+    /// Ranges of [`AppData::textures`] that meshes have stopped using, which
+    /// reloaded meshes can put their textures in.
+    free_textures: Vec<Range<usize>>,
+    /// Whether to frame the objects again each time a mesh loads, because the
+    /// scene doesn't have a camera and the camera hasn't been set since.
+    frame_on_load: bool,
+
+    /// How many of the scene's objects to draw, in order.
     pub num_models: usize,
 
     /// The time that the last frame was rendered at. Used for keeping basic
@@ -72,17 +159,199 @@ pub struct App {
     /// especially when we make the CPU wait for the GPU to render
     /// MAX_FRAMES_IN_FLIGHT frames with memory fences.
     last_frame_time: Instant,
+
+    /// If set, the frame clock is frozen and every frame is treated as taking
+    /// exactly this many seconds. See [`App::set_fixed_frame_delta()`].
+    fixed_delta_t: Option<f32>,
     // /// The instant in time the app was started at.
     // app_start_time: Instant,
 }
 
+/// Where the app's frames end up.
+#[derive(Clone, Copy)]
+enum RenderTarget<'a> {
+    /// Present frames to a swapchain created for a window.
+    Window(&'a Window),
+    /// Render frames into an offscreen image of the given size.
+    Offscreen(vk::Extent2D),
+}
+
+/// Indices into [`AppData::meshes`] and [`AppData::textures`] for an object in
+/// the scene.
+#[derive(Clone, Copy, Debug)]
+struct ObjectResources {
+    mesh: usize,
+    /// Overrides the textures of the mesh's materials, if set.
+    texture: Option<usize>,
+    /// Where the object's joint matrices start in [`AppData::joint_buffers`],
+    /// if its mesh is skinned.
+    first_joint: Option<u32>,
+}
+
+/// A range of a mesh's indices to draw with a single texture and base color.
+#[derive(Clone, Copy, Debug)]
+struct SubmeshDraw {
+    first_index: u32,
+    index_count: u32,
+    /// Which of the mesh's textures to draw with, counting from
+    /// [`MeshDraws::first_texture`], or `None` for the white texture.
+    texture: Option<usize>,
+    base_color: glm::Vec4,
+}
+
+/// Copies of one mesh drawn the same way, which are drawn together with one
+/// instanced draw per submesh.
+#[derive(Clone, Debug)]
+struct InstanceBatch {
+    /// Index into [`AppData::meshes`], or `None` for the placeholder mesh.
+    mesh: Option<usize>,
+    lod: usize,
+    /// Overrides the textures of the mesh's materials, if set.
+    texture: Option<usize>,
+    skinned: bool,
+    instances: Vec<InstanceData>,
+}
+
+/// How to draw one of the meshes in [`AppData::meshes`], at each of its levels
+/// of detail.
+#[derive(Clone, Debug)]
+struct MeshDraws {
+    /// The submeshes to draw at each level of detail, starting with full
+    /// detail.
+    lods: Vec<Vec<SubmeshDraw>>,
+    /// The error of each simplified level of detail, as in
+    /// [`model::Lod::error`].
+    lod_errors: Vec<f32>,
+    /// Bounds around the whole mesh, in model space.
+    bounds: Bounds,
+    /// Whether the mesh is drawn as triangles or as a point cloud.
+    topology: Topology,
+    /// The transform from the mesh's positions to model space, if its
+    /// vertices are [`QuantizedVertex`]es.
+    dequantize: Option<glm::Mat4>,
+    /// The skeleton the mesh is skinned to, if it's drawn with
+    /// [`SkinnedVertex`] vertices.
+    skin: Option<Skin>,
+    /// Clips that animate `skin`.
+    animations: Vec<AnimationClip>,
+    /// Where the textures of the mesh's materials start in
+    /// [`AppData::textures`], once they've finished uploading.
+    first_texture: usize,
+}
+
+impl MeshDraws {
+    /// Draw the simplified levels of detail with the same textures and base
+    /// colors as the full-detail submeshes they stand in for.
+    fn new(draws: Vec<SubmeshDraw>, lods: &[model::Lod], bounds: Bounds) -> Self {
+        let simplified: Vec<Vec<_>> = lods
+            .iter()
+            .map(|lod| {
+                draws
+                    .iter()
+                    .zip(&lod.submeshes)
+                    .map(|(draw, submesh)| SubmeshDraw {
+                        first_index: submesh.first_index,
+                        index_count: submesh.index_count,
+                        ..*draw
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            lods: std::iter::once(draws).chain(simplified).collect(),
+            lod_errors: lods.iter().map(|lod| lod.error).collect(),
+            bounds,
+            topology: Topology::Triangles,
+            dequantize: None,
+            skin: None,
+            animations: Vec::new(),
+            first_texture: 0,
+        }
+    }
+
+    /// The index in [`AppData::textures`] of the texture to draw a submesh
+    /// with.
+    fn texture(&self, submesh: &SubmeshDraw) -> usize {
+        submesh
+            .texture
+            .map_or(WHITE_TEXTURE, |i| self.first_texture + i)
+    }
+}
+
+/// An asset whose upload to the GPU is in flight.
+struct PendingAsset {
+    path: PathBuf,
+    upload: Upload,
+    uploaded: UploadedAsset,
+}
+
+/// The resources an upload fills in.
+enum UploadedAsset {
+    Mesh {
+        index: usize,
+        buffers: MeshBuffers,
+        draws: Box<MeshDraws>,
+        /// The textures of the mesh's materials, which are moved into
+        /// [`AppData::textures`] once they've finished uploading.
+        textures: Vec<Texture>,
+    },
+    Texture {
+        index: usize,
+        texture: Texture,
+    },
+}
+
+impl UploadedAsset {
+    fn asset(&self) -> Asset {
+        match *self {
+            Self::Mesh { index, .. } => Asset::Mesh(index),
+            Self::Texture { index, .. } => Asset::Texture(index),
+        }
+    }
+}
+
+/// The textures in [`AppData::textures`] that a loaded asset owns, and the
+/// pool their descriptor sets are allocated from.
+struct AssetResources {
+    textures: Range<usize>,
+    descriptor_pool: vk::DescriptorPool,
+}
+
+/// Resources replaced by a reloaded asset, kept alive until the frames in
+/// flight that might use them have finished.
+#[derive(Default)]
+struct RetiredResources {
+    /// The value of [`App::frame_count`] when they were replaced.
+    frame: u64,
+    mesh: Option<MeshBuffers>,
+    textures: Vec<Texture>,
+    descriptor_pool: vk::DescriptorPool,
+}
+
+/// Where the plain white texture is in [`AppData::textures`]. Submeshes
+/// without a base color texture are drawn with it, so that the shader can
+/// always sample a texture.
+const WHITE_TEXTURE: usize = 0;
+
+/// How many seconds it takes to crossfade from one animation clip to the next.
+const ANIMATION_FADE_DURATION: f32 = 0.25;
+
 /// Vulkan handles and associated properties used by our Vulkan [`App`].
 #[derive(Clone, Debug, Default)]
 pub struct AppData {
+    /// The window surface to present to. Null if the app is rendering
+    /// headlessly, see [`AppData::is_headless()`].
     pub surface: vk::SurfaceKHR,
 
+    /// Whether Vulkan's validation layers are enabled.
+    pub validation_enabled: bool,
+
     pub physical_device: vk::PhysicalDevice,
     pub msaa_samples: vk::SampleCountFlags,
+    /// The maximum anisotropy to use for texture sampling, or `None` if the
+    /// physical device doesn't support anisotropic filtering.
+    pub max_sampler_anisotropy: Option<f32>,
 
     pub graphics_queue: vk::Queue,
     pub present_queue: vk::Queue,
@@ -92,27 +361,72 @@ pub struct AppData {
     pub swapchain_image_views: Vec<vk::ImageView>,
     pub swapchain_format: vk::Format,
     pub swapchain_extent: vk::Extent2D,
+    pub swapchain_image_usage: vk::ImageUsageFlags,
+    /// The presentation mode to use for the swapchain, if supported.
+    pub preferred_present_mode: Option<vk::PresentModeKHR>,
+
+    /// Backing memory for the offscreen render target, which is stored as the
+    /// only image in `swapchain_images` when rendering headlessly.
+    pub offscreen_image_memory: vk::DeviceMemory,
 
     pub render_pass: vk::RenderPass,
+    /// Layout of the per-frame descriptor sets in `descriptor_sets`.
     pub descriptor_set_layout: vk::DescriptorSetLayout,
+    /// Layout of the per-texture descriptor sets in `texture_descriptor_sets`.
+    pub texture_descriptor_set_layout: vk::DescriptorSetLayout,
+    /// Layout of the per-frame descriptor sets in `joint_descriptor_sets`.
+    pub joint_descriptor_set_layout: vk::DescriptorSetLayout,
+    /// Shared by `pipeline` and `skinned_pipeline`.
     pub pipeline_layout: vk::PipelineLayout,
     pub pipeline: vk::Pipeline,
+    /// Draws meshes with [`SkinnedVertex`] vertices.
+    pub skinned_pipeline: vk::Pipeline,
+    /// Draws meshes with [`Topology::Points`] as point clouds.
+    pub point_pipeline: vk::Pipeline,
+    /// The size of the points drawn by `point_pipeline`, in pixels.
+    pub point_size: f32,
+    /// Draws meshes with [`QuantizedVertex`] vertices.
+    pub quantized_pipeline: vk::Pipeline,
+    /// Draws meshes with [`PositionVertex`](crate::vertex::PositionVertex) vertices in their instances'
+    /// tints, e.g. for depth-only passes.
+    pub position_pipeline: vk::Pipeline,
+    /// Draws lines between pairs of
+    /// [`ColoredVertex`](crate::vertex::ColoredVertex) vertices.
+    pub line_pipeline: vk::Pipeline,
 
     pub framebuffers: Vec<vk::Framebuffer>,
 
-    pub vertices: Vec<Vertex>,
-    pub indices: Vec<u32>,
-    pub vertex_buffer: vk::Buffer,
-    pub vertex_buffer_memory: vk::DeviceMemory,
-    pub index_buffer: vk::Buffer,
-    pub index_buffer_memory: vk::DeviceMemory,
+    /// The scene's meshes, in the same order as [`Scene::meshes`]. Each is
+    /// null until it has finished uploading.
+    pub meshes: Vec<MeshBuffers>,
+    /// A cube drawn in place of meshes that haven't finished loading.
+    pub placeholder_mesh: MeshBuffers,
     /// One uniform buffer per swapchain image, because we refer to it from
     /// each swapchain image's command buffer.
     pub uniform_buffers: Vec<vk::Buffer>,
     pub uniform_buffers_memory: Vec<vk::DeviceMemory>,
+    /// One buffer of joint matrices per swapchain image, like
+    /// `uniform_buffers`, holding the joints of every skinned object in turn.
+    pub joint_buffers: Vec<vk::Buffer>,
+    pub joint_buffers_memory: Vec<vk::DeviceMemory>,
+    /// How many joint matrices each of `joint_buffers` has room for.
+    pub joint_count: usize,
+    /// One instance-rate vertex buffer per swapchain image, like
+    /// `uniform_buffers`, holding the [`InstanceData`] of every object drawn.
+    pub instance_buffers: Vec<vk::Buffer>,
+    pub instance_buffers_memory: Vec<vk::DeviceMemory>,
+    /// How many instances each of `instance_buffers` has room for.
+    pub instance_count: usize,
     pub descriptor_pool: vk::DescriptorPool,
     /// One descriptor set per swapchain image.
     pub descriptor_sets: Vec<vk::DescriptorSet>,
+    /// One descriptor set per swapchain image, for `joint_buffers`.
+    pub joint_descriptor_sets: Vec<vk::DescriptorSet>,
+    /// One descriptor set per texture, in the same order as `textures`. Each
+    /// is null until its texture has finished uploading.
+    pub texture_descriptor_sets: Vec<vk::DescriptorSet>,
+    /// The pools `texture_descriptor_sets` are allocated from, one per asset.
+    pub texture_descriptor_pools: Vec<vk::DescriptorPool>,
 
     pub color_image: vk::Image,
     pub color_image_memory: vk::DeviceMemory,
@@ -122,24 +436,13 @@ pub struct AppData {
     pub depth_image_memory: vk::DeviceMemory,
     pub depth_image_view: vk::ImageView,
 
-    pub texture_image: vk::Image,
-    pub texture_image_memory: vk::DeviceMemory,
-    pub texture_image_format: vk::Format,
-    pub texture_image_view: vk::ImageView,
+    /// A plain white texture, followed by the scene's textures in the same
+    /// order as [`Scene::textures`], followed by textures from the meshes'
+    /// materials.
+    pub textures: Vec<Texture>,
+    /// Shared by all textures.
     pub texture_sampler: vk::Sampler,
 
-    /// The count of mip-map levels for the model's textures.
-    ///
-    /// Calculate with something like:
-    ///
-    /// ```ignore
-    /// app_data.mip_levels = (img_width.max(img_height) as f32).log2().floor() as u32 + 1;
-    /// ```
-    ///
-    /// ...which calculates how many times the largest dimension can be divided by 2, while ensuring
-    /// that at least one mip level (the original image) is generated.
-    pub mip_levels: u32,
-
     /// This set of command pools should primarily be used for allocating buffers during rendering.
     /// There is one command pool per swapchain image.
     pub command_pools: Vec<vk::CommandPool>,
@@ -170,9 +473,19 @@ pub struct AppData {
     pub messenger: vk::DebugUtilsMessengerEXT,
 }
 
+impl AppData {
+    /// Returns true if the app renders into an offscreen image instead of
+    /// presenting to a window surface.
+    #[inline]
+    pub fn is_headless(&self) -> bool {
+        self.surface == vk::SurfaceKHR::null()
+    }
+}
+
 impl App {
     /// Creates the Vulkan app, binding it to a surface generated by some winit
-    /// window handle.
+    /// window handle. The window's size takes precedence over the size given
+    /// in `settings`.
     ///
     /// # Safety
     ///
@@ -181,8 +494,40 @@ impl App {
     ///
     /// Fun.
     #[tracing::instrument(level = "DEBUG", name = "App::create", skip_all)]
-    pub unsafe fn create(window: &Window) -> Result<Self> {
-        let mut data = AppData::default();
+    pub unsafe fn create(window: &Window, settings: &AppSettings) -> Result<Self> {
+        Self::create_with_target(RenderTarget::Window(window), settings)
+    }
+
+    /// Creates the Vulkan app without a window. Frames are rendered into an
+    /// offscreen image of the size given in `settings`, and can be read back
+    /// with [`App::render_offscreen()`].
+    ///
+    /// This works on machines without a display, and with software Vulkan
+    /// implementations like lavapipe.
+    ///
+    /// # Safety
+    ///
+    /// Just as unsafe as [`App::create()`], but without the window.
+    #[tracing::instrument(level = "DEBUG", name = "App::create_headless", skip_all)]
+    pub unsafe fn create_headless(settings: &AppSettings) -> Result<Self> {
+        let extent = vk::Extent2D {
+            width: settings.width,
+            height: settings.height,
+        };
+        Self::create_with_target(RenderTarget::Offscreen(extent), settings)
+    }
+
+    unsafe fn create_with_target(target: RenderTarget, settings: &AppSettings) -> Result<Self> {
+        let mut data = AppData {
+            validation_enabled: settings.validation,
+            preferred_present_mode: settings.present_mode.map(Into::into),
+            ..Default::default()
+        };
+
+        let window = match target {
+            RenderTarget::Window(window) => Some(window),
+            RenderTarget::Offscreen(_) => None,
+        };
 
         debug!("Loading instance of Vulkan library");
         let entry = Entry::load()
@@ -190,16 +535,26 @@ impl App {
             .wrap_err("Error loading Vulkan library")?;
         let instance = create_instance(window, &entry, &mut data)?;
 
-        debug!("Creating render surface on main window");
-        data.surface = ash_window::create_surface(&entry, &instance, window, None)?;
+        if let Some(window) = window {
+            debug!("Creating render surface on main window");
+            data.surface = ash_window::create_surface(&entry, &instance, window, None)?;
+        }
 
         debug!("Selecting render device");
-        pick_physical_device(&entry, &instance, &mut data)?;
+        pick_physical_device(&entry, &instance, &mut data, settings)?;
         let device = create_logical_device(&entry, &instance, &mut data)?;
 
-        debug!("Creating swapchain");
-        create_swapchain(window, &entry, &instance, &device, &mut data)?;
-        create_swapchain_image_views(&device, &mut data)?;
+        match target {
+            RenderTarget::Window(window) => {
+                debug!("Creating swapchain");
+                create_swapchain(window, &entry, &instance, &device, &mut data)?;
+                create_swapchain_image_views(&device, &mut data)?;
+            }
+            RenderTarget::Offscreen(extent) => {
+                debug!("Creating offscreen render target");
+                create_offscreen_target(&instance, &device, &mut data, extent)?;
+            }
+        }
 
         debug!("Creating render pipeline");
         create_render_pass(&instance, &device, &mut data)?;
@@ -218,31 +573,103 @@ impl App {
         debug!("Creating framebuffers");
         create_framebuffers(&device, &mut data)?;
 
-        debug!("Creating command, vertex, index, and uniform buffers, and loading textures");
+        debug!("Loading scene");
+        let scene = match &settings.scene_path {
+            Some(path) => Scene::load(path)?,
+            None => Scene::single_model(&settings.model_path, settings.texture_path.as_deref()),
+        };
 
-        let (texture_image, texture_image_memory, texture_image_format, mip_levels) =
-            create_texture_image(
-                &instance,
-                &device,
-                &mut data,
-                "./resources/viking-room/viking-room.png",
-            )?;
-        data.texture_image = texture_image;
-        data.texture_image_memory = texture_image_memory;
-        data.texture_image_format = texture_image_format;
-        data.mip_levels = mip_levels;
-        data.texture_image_view = create_texture_image_view(
+        // Scene::load() makes sure that every object's mesh and texture exist
+        let object_resources: Vec<_> = scene
+            .objects
+            .iter()
+            .map(|object| ObjectResources {
+                mesh: scene.mesh_index(&object.mesh).unwrap(),
+                texture: object
+                    .texture
+                    .as_ref()
+                    .map(|texture| scene.texture_index(texture).unwrap() + 1),
+                first_joint: None,
+            })
+            .collect();
+
+        debug!("Creating command, vertex, index, and uniform buffers, and placeholder assets");
+
+        // Everything is drawn with the white texture or the placeholder mesh
+        // until the scene's assets load, so upload them right away
+        data.texture_sampler = create_texture_sampler(&device, &data)?;
+        let white = model::Image {
+            width: 1,
+            height: 1,
+            pixels: vec![u8::MAX; 4],
+        };
+        let placeholder = primitives::cube(1.0);
+
+        let mut upload = begin_upload(&device, &data)?;
+        data.textures.push(create_texture_from_image(
+            &instance,
             &device,
-            data.texture_image,
-            data.texture_image_format,
-            data.mip_levels,
+            &data,
+            &mut upload,
+            &white,
+        )?);
+        data.placeholder_mesh = create_mesh_buffers(
+            &instance,
+            &device,
+            &data,
+            &mut upload,
+            &placeholder.vertices,
+            &placeholder.indices,
         )?;
-        data.texture_sampler = create_texture_sampler(&device, &data)?;
+        submit_upload(&device, &data, &mut upload)?;
+        wait_for_upload(&device, &upload)?;
+        destroy_upload(&device, &data, &upload);
+        create_texture_descriptor_sets(&device, &mut data, WHITE_TEXTURE..WHITE_TEXTURE + 1)?;
+
+        let placeholder_draws = MeshDraws::new(
+            vec![SubmeshDraw {
+                first_index: 0,
+                index_count: placeholder.indices.len() as u32,
+                texture: None,
+                base_color: glm::vec4(0.5, 0.5, 0.5, 1.0),
+            }],
+            &[],
+            placeholder.bounds,
+        );
+
+        // The scene's assets are filled in as they load, with the scene's
+        // textures going right after the white texture
+        data.meshes = vec![MeshBuffers::default(); scene.meshes.len()];
+        data.textures
+            .resize(1 + scene.textures.len(), Texture::default());
+
+        let loader = AssetLoader::new(ModelOptions {
+            normals: settings.normals,
+            max_lods: settings.max_lods,
+        });
+        let meshes = (scene.meshes.values().enumerate()).map(|(i, path)| (Asset::Mesh(i), path));
+        let textures =
+            (scene.textures.values().enumerate()).map(|(i, path)| (Asset::Texture(i), path));
+        let mut watcher = settings.watch_assets.then(AssetWatcher::new).transpose()?;
+        for (asset, path) in meshes.chain(textures) {
+            if let Some(watcher) = &mut watcher {
+                if let Err(e) = watcher.watch(asset, path) {
+                    warn!(?path, error = %e, "Can't watch asset for changes");
+                }
+            }
+            loader.load(Job {
+                asset,
+                path: path.clone(),
+            });
+        }
+        info!(
+            total = scene.meshes.len() + scene.textures.len(),
+            "Loading assets"
+        );
 
-        load_model(&mut data, "./resources/viking-room/viking-room.obj")?;
-        create_vertex_buffer(&instance, &device, &mut data)?;
-        create_index_buffer(&instance, &device, &mut data)?;
+        let animation_players = vec![AnimationPlayer::new(None, 0.0); scene.objects.len()];
 
+        data.instance_count = scene.objects.len();
         create_uniform_buffers(&instance, &device, &mut data)?;
         create_descriptor_pool(&device, &mut data)?;
         create_descriptor_sets(&device, &mut data)?;
@@ -256,19 +683,57 @@ impl App {
             swapchain: vk_khr::Swapchain::new(&instance, &device),
         };
 
-        Ok(Self {
+        let mut app = Self {
             entry,
             instance,
             data,
             device,
             extensions,
             frame: 0,
+            frame_count: 0,
             resized: false,
+            screenshot_requested: false,
+            pending_capture: None,
+            screenshot: None,
             mvp_mat: MvpMat::default(),
-            num_models: 1,
+            camera: scene.camera.map(Into::into).unwrap_or_default(),
+            camera_mode: settings.camera_mode,
+            fly_controller: FlyController::default(),
+            orbit_controller: OrbitController::default(),
+            // Without a scene file, start with a single model and let the user
+            // add more
+            num_models: if settings.scene_path.is_some() {
+                scene.objects.len()
+            } else {
+                1
+            },
+            frame_on_load: scene.camera.is_none(),
+            mesh_draws: vec![None; scene.meshes.len()],
+            scene,
+            object_resources,
+            placeholder_draws,
+            vertex_format: settings.vertex_format,
+            animation_players,
+            scene_time: 0.0,
+            loader,
+            pending_assets: Vec::new(),
+            finished_assets: AHashSet::new(),
+            asset_resources: AHashMap::new(),
+            watcher,
+            retired: Vec::new(),
+            free_textures: Vec::new(),
             last_frame_time: Instant::now(),
+            fixed_delta_t: None,
             // app_start_time: Instant::now(),
-        })
+        };
+
+        // Scenes without a camera are viewed from wherever shows them best,
+        // which changes as their meshes load
+        if app.frame_on_load {
+            app.frame_objects();
+        }
+
+        Ok(app)
     }
 
     /// Trigger an app resize. Call this if the window manager has indicated that
@@ -363,9 +828,13 @@ impl App {
 
         self.data.images_in_flight[image_index as usize] = self.data.in_flight_fences[self.frame];
 
+        self.update_assets()?;
+
         let delta_t = self.tick_frame_clock();
-        self.update_command_buffers(image_index, delta_t)?;
+        // The camera has to be up to date before recording, so that levels of
+        // detail are picked for what the camera sees this frame
         self.update_uniform_buffers(image_index, delta_t)?;
+        self.update_command_buffers(image_index, delta_t)?;
 
         // Submit command buffers to the queue for rendering.
         let wait_semaphores = &[self.data.image_available_semaphores[self.frame]];
@@ -400,6 +869,7 @@ impl App {
             .extensions
             .swapchain
             .queue_present(self.data.present_queue, &present_info);
+        self.finish_capture()?;
 
         // Recreate the swapchain if needed
         let swapchain_must_be_recreated =
@@ -416,10 +886,203 @@ impl App {
         }
 
         self.frame = (self.frame + 1) % MAX_FRAMES_IN_FLIGHT;
+        self.frame_count += 1;
 
         Ok(())
     }
 
+    /// Render a frame into the offscreen render target of a headless app, and
+    /// copy the resolved pixels back to the CPU.
+    ///
+    /// Returns the frame as tightly-packed rows of 8-bit RGBA pixels in the
+    /// sRGB color space, top row first. See [`App::extent()`] for the size of
+    /// the frame.
+    ///
+    /// # Safety
+    ///
+    /// Just as unsafe as [`App::render()`].
+    #[tracing::instrument(level = "DEBUG", name = "App::render_offscreen", skip_all)]
+    pub unsafe fn render_offscreen(&mut self) -> Result<Vec<u8>> {
+        if !self.data.is_headless() {
+            return Err(eyre!(
+                "Offscreen rendering requires an app created with App::create_headless()"
+            ));
+        }
+
+        // There's only a single render target, so just wait for it to be free.
+        self.device
+            .wait_for_fences(&[self.data.in_flight_fences[self.frame]], true, u64::MAX)?;
+
+        self.update_assets()?;
+
+        let delta_t = self.tick_frame_clock();
+        // The camera has to be up to date before recording, so that levels of
+        // detail are picked for what the camera sees this frame
+        self.update_uniform_buffers(0, delta_t)?;
+        self.update_command_buffers(0, delta_t)?;
+
+        // Submit the command buffer. There's no swapchain to synchronize with,
+        // so no semaphores are needed.
+        let command_buffers = &[self.data.command_buffers[0]];
+        let submit_info = vk::SubmitInfo::builder().command_buffers(command_buffers);
+
+        self.device
+            .reset_fences(&[self.data.in_flight_fences[self.frame]])?;
+
+        self.device.queue_submit(
+            self.data.graphics_queue,
+            &[*submit_info],
+            self.data.in_flight_fences[self.frame],
+        )?;
+
+        // Wait for the frame to finish rendering, then copy it back.
+        self.device
+            .wait_for_fences(&[self.data.in_flight_fences[self.frame]], true, u64::MAX)?;
+        self.finish_capture()?;
+
+        let pixels = read_image_pixels(
+            &self.instance,
+            &self.device,
+            &self.data,
+            self.data.swapchain_images[0],
+            self.data.swapchain_format,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            self.data.swapchain_extent,
+        )?;
+
+        self.frame = (self.frame + 1) % MAX_FRAMES_IN_FLIGHT;
+        self.frame_count += 1;
+
+        Ok(pixels)
+    }
+
+    /// Take a screenshot of the most recently rendered frame of a headless
+    /// app. Windowed apps can't copy frames once they've been presented, so
+    /// use [`App::request_screenshot()`] for them instead.
+    ///
+    /// # Safety
+    ///
+    /// Waits for the device to go idle, so don't call this every frame.
+    #[tracing::instrument(level = "DEBUG", name = "App::capture_screenshot", skip_all)]
+    pub unsafe fn capture_screenshot(&self) -> Result<Screenshot> {
+        if !self.data.is_headless() {
+            return Err(eyre!(
+                "Presented frames can't be captured, use App::request_screenshot() instead"
+            ));
+        }
+
+        // Make sure the frame has finished rendering before copying it.
+        self.device.device_wait_idle()?;
+
+        let pixels = read_image_pixels(
+            &self.instance,
+            &self.device,
+            &self.data,
+            self.data.swapchain_images[0],
+            self.data.swapchain_format,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            self.data.swapchain_extent,
+        )?;
+
+        Screenshot::from_raw(
+            self.data.swapchain_extent.width,
+            self.data.swapchain_extent.height,
+            self.data.swapchain_format,
+            pixels,
+        )
+    }
+
+    /// Capture the next frame rendered for a screenshot. The frame is copied
+    /// as part of rendering it, before it's presented, and can be collected
+    /// with [`App::take_screenshot()`] once it has been rendered.
+    #[inline]
+    pub fn request_screenshot(&mut self) {
+        self.screenshot_requested = true;
+    }
+
+    /// The screenshot captured since the last call, if one was requested with
+    /// [`App::request_screenshot()`] and the frame has finished rendering.
+    #[inline]
+    pub fn take_screenshot(&mut self) -> Option<Result<Screenshot>> {
+        self.screenshot.take()
+    }
+
+    /// Record copying the frame being rendered to `image_index` at the end of
+    /// `command_buffer`, if a screenshot was requested.
+    unsafe fn record_capture(&mut self, command_buffer: vk::CommandBuffer, image_index: u32) {
+        if !mem::take(&mut self.screenshot_requested) {
+            return;
+        }
+
+        if !self
+            .data
+            .swapchain_image_usage
+            .contains(vk::ImageUsageFlags::TRANSFER_SRC)
+        {
+            self.screenshot = Some(Err(eyre!(
+                "The surface doesn't support copying from swapchain images"
+            )));
+            return;
+        }
+
+        // The render pass leaves the image ready to present, or to copy back
+        // when rendering headlessly
+        let layout = if self.data.is_headless() {
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+        } else {
+            vk::ImageLayout::PRESENT_SRC_KHR
+        };
+        match record_image_capture(
+            &self.instance,
+            &self.device,
+            &self.data,
+            command_buffer,
+            self.data.swapchain_images[image_index as usize],
+            self.data.swapchain_format,
+            layout,
+            self.data.swapchain_extent,
+        ) {
+            Ok(capture) => self.pending_capture = Some(capture),
+            Err(e) => self.screenshot = Some(Err(e)),
+        }
+    }
+
+    /// Read back the frame captured for a screenshot, if any, once the current
+    /// frame's fence has been signalled.
+    unsafe fn finish_capture(&mut self) -> Result<()> {
+        let Some(capture) = self.pending_capture.take() else {
+            return Ok(());
+        };
+
+        let signalled =
+            self.device
+                .wait_for_fences(&[self.data.in_flight_fences[self.frame]], true, u64::MAX);
+        if let Err(e) = signalled {
+            destroy_capture(&self.device, &capture);
+            return Err(e.into());
+        }
+
+        self.screenshot = Some(read_capture(&self.device, capture).and_then(|pixels| {
+            Screenshot::from_raw(
+                capture.extent.width,
+                capture.extent.height,
+                capture.format,
+                pixels,
+            )
+        }));
+
+        Ok(())
+    }
+
+    /// The size of the frames being rendered, in pixels, as `(width, height)`.
+    #[inline]
+    pub fn extent(&self) -> (u32, u32) {
+        (
+            self.data.swapchain_extent.width,
+            self.data.swapchain_extent.height,
+        )
+    }
+
     /// Increment the frame clock. Returns the delta time since the last frame
     /// in seconds.
     fn tick_frame_clock(&mut self) -> f32 {
@@ -428,29 +1091,480 @@ impl App {
         let delta_t = (now - self.last_frame_time).as_secs_f32();
         self.last_frame_time = now;
 
-        delta_t
+        self.fixed_delta_t.unwrap_or(delta_t)
+    }
+
+    /// Freeze the frame clock so that every frame advances animations by
+    /// exactly `delta_t` seconds, regardless of how long frames actually take.
+    /// Pass `None` to go back to using real time.
+    ///
+    /// Freezing the clock with a delta of `0.0` makes rendering deterministic,
+    /// which is useful for tests.
+    #[inline]
+    pub fn set_fixed_frame_delta(&mut self, delta_t: Option<f32>) {
+        self.fixed_delta_t = delta_t;
+    }
+
+    /// The camera the scene is viewed through.
+    #[inline]
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Replace the camera the scene is viewed through. The objects are no
+    /// longer framed as they load after this.
+    #[inline]
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+        self.frame_on_load = false;
+    }
+
+    /// Which controller moves the camera around.
+    #[inline]
+    pub fn camera_mode(&self) -> CameraMode {
+        self.camera_mode
+    }
+
+    /// Switch which controller moves the camera around. Both controllers
+    /// stop, so that nothing carries on moving from input given before the
+    /// switch.
+    pub fn set_camera_mode(&mut self, mode: CameraMode) {
+        self.camera_mode = mode;
+        self.fly_controller.stop();
+        self.orbit_controller.stop();
+        debug!(%mode, "Switched camera mode");
+    }
+
+    /// Flies the camera around from keyboard and mouse input, in
+    /// [`CameraMode::Fly`]. Input given to it is applied at the start of each
+    /// frame.
+    #[inline]
+    pub fn fly_controller(&mut self) -> &mut FlyController {
+        &mut self.fly_controller
+    }
+
+    /// Orbits the camera around its target from mouse input, in
+    /// [`CameraMode::Orbit`]. Input given to it is applied at the start of
+    /// each frame.
+    #[inline]
+    pub fn orbit_controller(&mut self) -> &mut OrbitController {
+        &mut self.orbit_controller
+    }
+
+    /// The scene being drawn.
+    #[inline]
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    /// Bounds around one of the scene's meshes, in model space, in the same
+    /// order as [`Scene::meshes`]. Meshes that haven't loaded yet have the
+    /// bounds of the placeholder drawn in their place.
+    #[inline]
+    pub fn mesh_bounds(&self, mesh_index: usize) -> &Bounds {
+        &self.mesh_draws[mesh_index]
+            .as_ref()
+            .unwrap_or(&self.placeholder_draws)
+            .bounds
+    }
+
+    /// How many of the scene's meshes and textures have finished loading, out
+    /// of how many there are, as `(finished, total)`. Assets that failed to
+    /// load count as finished.
+    pub fn loading_progress(&self) -> (usize, usize) {
+        (self.finished_assets.len(), self.asset_count())
+    }
+
+    fn asset_count(&self) -> usize {
+        self.scene.meshes.len() + self.scene.textures.len()
+    }
+
+    /// Block until every one of the scene's assets has loaded and finished
+    /// uploading to the GPU. Returns the first error hit by an asset that
+    /// fails to load.
+    ///
+    /// # Safety
+    ///
+    /// Uploads assets to the GPU, so it's as unsafe as [`App::render()`].
+    #[tracing::instrument(level = "DEBUG", name = "App::wait_for_assets", skip_all)]
+    pub unsafe fn wait_for_assets(&mut self) -> Result<()> {
+        while self.finished_assets.len() < self.asset_count() {
+            // Uploads finish quicker than loads, so wait for them first
+            if let Some(pending) = self.pending_assets.first() {
+                wait_for_upload(&self.device, &pending.upload)?;
+                self.finish_uploads()?;
+            } else {
+                let loaded = self.loader.wait()?;
+                self.receive_asset(loaded)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start uploading the assets that have loaded since the last frame, and
+    /// start drawing the ones that have finished uploading. Assets that fail
+    /// to load are logged and left as they were.
+    ///
+    /// Call this between frames, after waiting for the frame's fence, so that
+    /// resources replaced by reloaded assets can be destroyed once no frame is
+    /// using them.
+    unsafe fn update_assets(&mut self) -> Result<()> {
+        // Once MAX_FRAMES_IN_FLIGHT more frames have started, the fence of
+        // every frame that could have used retired resources has been waited for
+        let frame_count = self.frame_count;
+        self.retired.retain(|retired| {
+            let in_use = frame_count < retired.frame + MAX_FRAMES_IN_FLIGHT as u64;
+            if !in_use {
+                destroy_retired_resources(&self.device, retired);
+            }
+            in_use
+        });
+
+        if let Some(watcher) = &self.watcher {
+            for job in watcher.changed() {
+                info!(path = ?job.path, "Reloading changed asset");
+                self.loader.load(job);
+            }
+        }
+
+        for loaded in self.loader.poll() {
+            if let Err(e) = self.receive_asset(loaded) {
+                error!(error = ?e, "Failed to load asset");
+            }
+        }
+
+        self.finish_uploads()
+    }
+
+    /// Record and submit the upload of an asset that's been read from disk.
+    unsafe fn receive_asset(&mut self, loaded: LoadedAsset) -> Result<()> {
+        let asset = loaded.asset;
+        let path = loaded.path.clone();
+        let result = self.upload_asset(loaded);
+        if result.is_err() {
+            self.finished_assets.insert(asset);
+        }
+        result.wrap_err_with(|| format!("Error loading asset {path:?}"))
+    }
+
+    unsafe fn upload_asset(&mut self, loaded: LoadedAsset) -> Result<()> {
+        let asset_data = loaded.data?;
+
+        // Only once a model has loaded is it known which other files it uses
+        if let (Some(watcher), AssetData::Model(mesh)) = (&mut self.watcher, &asset_data) {
+            for dependency in &mesh.dependencies {
+                if let Err(e) = watcher.watch_dependency(loaded.asset, &loaded.path, dependency) {
+                    warn!(?dependency, error = %e, "Can't watch asset for changes");
+                }
+            }
+        }
+
+        let mut upload = begin_upload(&self.device, &self.data)?;
+
+        let result = match loaded.asset {
+            Asset::Mesh(index) => upload_mesh(
+                &self.instance,
+                &self.device,
+                &self.data,
+                &mut upload,
+                asset_data,
+                self.vertex_format,
+            )
+            .map(|(buffers, draws, textures)| UploadedAsset::Mesh {
+                index,
+                buffers,
+                draws: Box::new(draws),
+                textures,
+            }),
+            Asset::Texture(index) => upload_texture(
+                &self.instance,
+                &self.device,
+                &self.data,
+                &mut upload,
+                asset_data,
+            )
+            .map(|texture| UploadedAsset::Texture { index, texture }),
+        };
+        let uploaded = match result {
+            Ok(uploaded) => uploaded,
+            Err(e) => {
+                destroy_upload(&self.device, &self.data, &upload);
+                return Err(e);
+            }
+        };
+        if let Err(e) = submit_upload(&self.device, &self.data, &mut upload) {
+            destroy_upload(&self.device, &self.data, &upload);
+            destroy_uploaded_asset(&self.device, &uploaded);
+            return Err(e);
+        }
+
+        self.pending_assets.push(PendingAsset {
+            path: loaded.path,
+            upload,
+            uploaded,
+        });
+
+        Ok(())
+    }
+
+    /// Start drawing every asset whose upload has finished.
+    unsafe fn finish_uploads(&mut self) -> Result<()> {
+        let mut i = 0;
+        while i < self.pending_assets.len() {
+            if is_upload_finished(&self.device, &self.pending_assets[i].upload)? {
+                let pending = self.pending_assets.remove(i);
+                self.finish_asset(pending)?;
+            } else {
+                i += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Swap a freshly uploaded asset in for whatever was drawn in its place,
+    /// retiring the resources it replaces if it's been reloaded.
+    unsafe fn finish_asset(&mut self, pending: PendingAsset) -> Result<()> {
+        destroy_upload(&self.device, &self.data, &pending.upload);
+
+        let asset = pending.uploaded.asset();
+        let reloaded = self.asset_resources.contains_key(&asset);
+        let mut retired = RetiredResources {
+            frame: self.frame_count,
+            ..Default::default()
+        };
+
+        let textures = match pending.uploaded {
+            UploadedAsset::Mesh {
+                index,
+                buffers,
+                mut draws,
+                textures,
+            } => {
+                // A reloaded mesh with as many textures as before takes over
+                // the old ones' slots
+                let old_slots = self
+                    .asset_resources
+                    .get(&asset)
+                    .map(|old| old.textures.clone());
+                let reused = old_slots
+                    .as_ref()
+                    .is_some_and(|old| old.len() == textures.len());
+                let slots = match old_slots {
+                    Some(old) if reused => old,
+                    _ => self.allocate_textures(textures.len()),
+                };
+                for (slot, texture) in slots.clone().zip(textures) {
+                    let old_texture = mem::replace(&mut self.data.textures[slot], texture);
+                    if reused {
+                        retired.textures.push(old_texture);
+                    }
+                }
+                draws.first_texture = slots.start;
+
+                let old_buffers = mem::replace(&mut self.data.meshes[index], buffers);
+                if self.mesh_draws[index].replace(*draws).is_some() {
+                    retired.mesh = Some(old_buffers);
+                }
+                slots
+            }
+            UploadedAsset::Texture { index, texture } => {
+                let slot = index + 1;
+                let old_texture = mem::replace(&mut self.data.textures[slot], texture);
+                if reloaded {
+                    retired.textures.push(old_texture);
+                }
+                slot..slot + 1
+            }
+        };
+
+        let descriptor_pool =
+            create_texture_descriptor_sets(&self.device, &mut self.data, textures.clone())?;
+        let resources = AssetResources {
+            textures: textures.clone(),
+            descriptor_pool,
+        };
+        if let Some(old) = self.asset_resources.insert(asset, resources) {
+            // The textures a reloaded mesh had before are free for other
+            // meshes if it didn't take over their slots
+            if old.textures != textures {
+                for i in old.textures.clone() {
+                    retired.textures.push(mem::take(&mut self.data.textures[i]));
+                    self.data.texture_descriptor_sets[i] = vk::DescriptorSet::null();
+                }
+                if !old.textures.is_empty() {
+                    self.free_textures.push(old.textures);
+                }
+            }
+            self.data
+                .texture_descriptor_pools
+                .retain(|&pool| pool != old.descriptor_pool);
+            retired.descriptor_pool = old.descriptor_pool;
+            self.retired.push(retired);
+        }
+
+        if let Asset::Mesh(index) = asset {
+            self.add_mesh_objects(index)?;
+
+            if self.frame_on_load {
+                self.frame_objects();
+            }
+        }
+
+        if reloaded {
+            info!(path = ?pending.path, "Reloaded asset");
+        } else {
+            self.finished_assets.insert(asset);
+            info!(
+                loaded = self.finished_assets.len(),
+                total = self.asset_count(),
+                path = ?pending.path,
+                "Loaded asset"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Find `count` slots in a row in [`AppData::textures`] for a mesh's
+    /// textures, reusing slots that other meshes have freed if possible.
+    fn allocate_textures(&mut self, count: usize) -> Range<usize> {
+        if let Some(i) = self.free_textures.iter().position(|r| r.len() >= count) {
+            let free = &mut self.free_textures[i];
+            let slots = free.start..free.start + count;
+            free.start = slots.end;
+            if free.start == free.end {
+                self.free_textures.swap_remove(i);
+            }
+            return slots;
+        }
+
+        let start = self.data.textures.len();
+        self.data.textures.resize(start + count, Texture::default());
+        start..start + count
+    }
+
+    /// Start playing the animations of the objects drawn with a mesh that's
+    /// just loaded, and lay out the joint matrices of every skinned object
+    /// again, since the mesh's skin is new.
+    unsafe fn add_mesh_objects(&mut self, mesh_index: usize) -> Result<()> {
+        let Some(draws) = &self.mesh_draws[mesh_index] else {
+            return Ok(());
+        };
+
+        for ((object, _), player) in self
+            .scene
+            .objects
+            .iter()
+            .zip(&self.object_resources)
+            .zip(&mut self.animation_players)
+            .filter(|((_, resources), _)| resources.mesh == mesh_index)
+        {
+            let clip = match &object.animation {
+                Some(name) => {
+                    let clip = draws
+                        .animations
+                        .iter()
+                        .position(|clip| clip.name.as_deref() == Some(name));
+                    if clip.is_none() {
+                        warn!(mesh = ?object.mesh, animation = ?name, "Mesh has no animation with this name");
+                    }
+                    clip
+                }
+                None => (!draws.animations.is_empty()).then_some(0),
+            };
+            *player = AnimationPlayer::new(clip, self.scene_time);
+        }
+
+        let mut joint_count = 0;
+        for resources in &mut self.object_resources {
+            let skin = self.mesh_draws[resources.mesh]
+                .as_ref()
+                .and_then(|draws| draws.skin.as_ref());
+            resources.first_joint = skin.map(|skin| {
+                let first_joint = joint_count as u32;
+                joint_count += skin.joints.len();
+                first_joint
+            });
+        }
+
+        // Frames in flight may be reading the joint buffers, so wait for them
+        // before replacing the buffers with ones of the new size
+        if joint_count != self.data.joint_count {
+            self.device.device_wait_idle()?;
+            destroy_joint_buffers(&self.device, &self.data);
+            self.data.joint_count = joint_count;
+            create_joint_buffers(&self.instance, &self.device, &mut self.data)?;
+            update_joint_descriptor_sets(&self.device, &self.data);
+        }
+
+        Ok(())
+    }
+
+    /// Bounds around one of the scene's objects, in world space, where it is
+    /// at the current point in its animation.
+    pub fn object_bounds(&self, object_index: usize) -> Bounds {
+        let object = &self.scene.objects[object_index];
+        let mesh = self.object_resources[object_index].mesh;
+
+        self.mesh_bounds(mesh)
+            .transform(&object.model_matrix(self.scene_time))
+    }
+
+    /// Point the camera at the objects being drawn, from the direction it's
+    /// already looking from, and close enough that they fill the view.
+    pub fn frame_objects(&mut self) {
+        let sphere = (0..self.num_models)
+            .map(|i| self.object_bounds(i).sphere)
+            .reduce(|a, b| a.union(&b));
+
+        if let Some(sphere) = sphere {
+            let extent = self.data.swapchain_extent;
+            self.camera
+                .frame(&sphere, extent.width as f32 / extent.height as f32);
+            debug!(?sphere, camera = ?self.camera, "Framed objects");
+        }
+    }
+
+    /// Crossfade every object being drawn into the next of its mesh's
+    /// animation clips, wrapping around to the first after the last.
+    pub fn next_animation(&mut self) {
+        let num_objects = self.num_models.min(self.scene.objects.len());
+        for (resources, player) in self
+            .object_resources
+            .iter()
+            .zip(&mut self.animation_players)
+            .take(num_objects)
+        {
+            if let (Some(clip), Some(draws)) = (player.clip(), &self.mesh_draws[resources.mesh]) {
+                let next = (clip + 1) % draws.animations.len();
+                player.play(next, self.scene_time, ANIMATION_FADE_DURATION);
+            }
+        }
     }
 
     /// Update all uniform buffers that need updating. Should be called right
     /// after we wait for the fence for the acquired swapchain image to be
     /// signalled in the render loop.
-    fn update_uniform_buffers(&mut self, image_index: u32, _delta_t: f32) -> Result<()> {
-        // Update model-view-projection matrix
-        self.mvp_mat
-            // Look at model from a distance
-            .look_at(
-                &glm::vec3(6.0, 0.0, 2.0),
-                &glm::vec3(0.0, 0.0, 0.0),
-                &glm::vec3(0.0, 0.0, 1.0),
-            )
-            // Use a perspective projection with a 45-degree vertical FOV. Make sure
-            // use the current swapchain extent so the aspect ratio is correct!
-            .perspective(
-                self.data.swapchain_extent.width as f32 / self.data.swapchain_extent.height as f32,
-                glm::radians(&glm::vec1(45.0))[0],
-                0.1,
-                10.0,
-            );
+    fn update_uniform_buffers(&mut self, image_index: u32, delta_t: f32) -> Result<()> {
+        // Move the camera, which stops it being framed automatically
+        let moved = match self.camera_mode {
+            CameraMode::Fly => self.fly_controller.update(&mut self.camera, delta_t),
+            CameraMode::Orbit => self
+                .orbit_controller
+                .update(&mut self.camera, self.data.swapchain_extent.height as f32),
+        };
+        if moved {
+            self.frame_on_load = false;
+        }
+
+        // Update model-view-projection matrix from the camera. Make sure to
+        // use the current swapchain extent so the aspect ratio is correct!
+        self.camera.apply(
+            &mut self.mvp_mat,
+            self.data.swapchain_extent.width as f32 / self.data.swapchain_extent.height as f32,
+        );
 
         // Send model-view-projection matrix to the GPU
         let ubo = self.mvp_mat.as_ubo();
@@ -470,6 +1584,57 @@ impl App {
         Ok(())
     }
 
+    /// Pose the skin of every skinned object being drawn, and send their joint
+    /// matrices to the GPU.
+    fn update_joint_buffer(&mut self, image_index: u32) -> Result<()> {
+        if self.data.joint_count == 0 {
+            return Ok(());
+        }
+
+        let memory = self.data.joint_buffers_memory[image_index as usize];
+        let num_objects = self.num_models.min(self.scene.objects.len());
+        unsafe {
+            // scope the memory-map pointer for safety
+            let joints = self
+                .device
+                .map_memory(
+                    memory,
+                    0,
+                    joint_buffer_size(&self.data),
+                    vk::MemoryMapFlags::empty(),
+                )?
+                .cast::<glm::Mat4>();
+
+            for (resources, player) in self
+                .object_resources
+                .iter()
+                .zip(&self.animation_players)
+                .take(num_objects)
+            {
+                let (Some(first_joint), Some(draws)) =
+                    (resources.first_joint, &self.mesh_draws[resources.mesh])
+                else {
+                    continue;
+                };
+                let Some(skin) = &draws.skin else {
+                    continue;
+                };
+
+                let pose = player.pose(skin, &draws.animations, self.scene_time);
+                let matrices = skin.joint_matrices(&pose);
+                ptr::copy_nonoverlapping(
+                    matrices.as_ptr(),
+                    joints.add(first_joint as usize),
+                    matrices.len(),
+                );
+            }
+
+            self.device.unmap_memory(memory);
+        }
+
+        Ok(())
+    }
+
     /// Update all command buffers that need updating.
     fn update_command_buffers(&mut self, image_index: u32, delta_t: f32) -> Result<()> {
         // Reset the per-framebuffer command pool, resetting all command buffers allocated from it
@@ -495,9 +1660,9 @@ impl App {
                 self.data.command_buffers[image_index as usize]
             };
 
-        // Update model rotation
-        self.mvp_mat
-            .model_rotate_z(delta_t * glm::radians(&glm::vec1(90.0))[0]);
+        // Advance the scene's animations
+        self.scene_time += delta_t;
+        self.update_joint_buffer(image_index)?;
 
         // Record the command buffer for this particular frame.
 
@@ -528,29 +1693,34 @@ impl App {
         };
         let clear_values = &[color_clear_value, depth_clear_value];
 
+        // Gather the objects into batches, and send their instances to the GPU
+        let batches = self.instance_batches();
+        self.update_instance_buffer(image_index, &batches)?;
+
         unsafe {
             // Begin render pass in the current framebuffer. All rendering
-            // commands are performed in secondary command buffers.
+            // commands are recorded inline, in the primary command buffer.
             let info = vk::RenderPassBeginInfo::builder()
                 .render_pass(self.data.render_pass)
                 .framebuffer(self.data.framebuffers[image_index as usize])
                 .render_area(*render_area)
                 .clear_values(clear_values);
-            self.device.cmd_begin_render_pass(
-                command_buffer,
-                &info,
-                vk::SubpassContents::SECONDARY_COMMAND_BUFFERS,
-            );
-
-            // Draw model using a secondary command buffer
-            let secondary_command_buffers = (0..self.num_models)
-                .map(|i| self.update_secondary_command_buffer(image_index, i))
-                .collect::<Result<Vec<_>, _>>()?;
             self.device
-                .cmd_execute_commands(command_buffer, &secondary_command_buffers[..]);
+                .cmd_begin_render_pass(command_buffer, &info, vk::SubpassContents::INLINE);
+
+            // Draw each batch of objects, with their instances one after
+            // another in the instance buffer
+            let mut first_instance = 0;
+            for batch in &batches {
+                self.draw_batch(command_buffer, image_index, batch, first_instance);
+                first_instance += batch.instances.len() as u32;
+            }
 
             // End render pass
             self.device.cmd_end_render_pass(command_buffer);
+
+            // Copy the frame back before it's presented, if asked to
+            self.record_capture(command_buffer, image_index);
         }
 
         // End recording the command buffer
@@ -561,117 +1731,221 @@ impl App {
         Ok(())
     }
 
-    /// Record and update a secondary command buffer.
-    fn update_secondary_command_buffer(
-        &mut self,
-        image_index: u32,
-        model_index: usize,
-    ) -> Result<vk::CommandBuffer> {
-        let image_index = image_index as usize;
-
-        // Allocate the buffer
-        let allocate_info = vk::CommandBufferAllocateInfo::builder()
-            .command_pool(self.data.command_pools[image_index])
-            .level(vk::CommandBufferLevel::SECONDARY)
-            .command_buffer_count(1);
+    /// Group the objects being drawn into batches that can each be drawn with
+    /// instanced draws. Opaque objects come first, in batches in the order
+    /// their first object appears in the scene, with each batch's instances
+    /// in scene order.
+    ///
+    /// Translucent objects blend with whatever has been drawn behind them, so
+    /// they come after every opaque object, one per batch, from back to front.
+    fn instance_batches(&mut self) -> Vec<InstanceBatch> {
+        let num_objects = self.num_models.min(self.scene.objects.len());
+        let mut batches: Vec<InstanceBatch> = Vec::new();
+        let mut translucent = Vec::new();
+
+        for object_index in 0..num_objects {
+            let object = &self.scene.objects[object_index];
+            let resources = self.object_resources[object_index];
+            let model = object.model_matrix(self.scene_time);
+
+            // Place the object in the world
+            self.mvp_mat.set_model(model);
+
+            // Draw the placeholder until the object's mesh has loaded
+            let (mesh, draws) = match &self.mesh_draws[resources.mesh] {
+                Some(draws) => (Some(resources.mesh), draws),
+                None => (None, &self.placeholder_draws),
+            };
 
-        let command_buffer = unsafe { self.device.allocate_command_buffers(&allocate_info)?[0] };
+            // Use the least detailed LOD that's still accurate to within
+            // about a pixel at the object's size on screen
+            let screen_size = self.mvp_mat.projected_size(
+                &draws.bounds.sphere,
+                self.data.swapchain_extent.height as f32,
+            );
+            let lod = select_lod(draws.lod_errors.iter().copied(), screen_size);
+
+            let instance = InstanceData {
+                model: match &draws.dequantize {
+                    Some(dequantize) => model * dequantize,
+                    None => model,
+                },
+                tint: glm::vec4(
+                    object.tint[0],
+                    object.tint[1],
+                    object.tint[2],
+                    object.opacity,
+                ),
+                first_joint: resources.first_joint.unwrap_or(0),
+            };
+            let skinned = resources.first_joint.is_some();
+
+            // Overriding the textures also overrides the base colors
+            let is_translucent = object.opacity < 1.0
+                || (resources.texture.is_none()
+                    && draws.lods[lod].iter().any(|s| s.base_color.w < 1.0));
+            if is_translucent {
+                let depth = self.mvp_mat.view_depth(&draws.bounds.sphere.center);
+                let batch = InstanceBatch {
+                    mesh,
+                    lod,
+                    texture: resources.texture,
+                    skinned,
+                    instances: vec![instance],
+                };
+                translucent.push((depth, batch));
+                continue;
+            }
 
-        // Set model position based on which index model this is
-        let y = (((model_index % 2) as f32) * 2.5) - 1.25;
-        let z = (((model_index / 2) as f32) * -2.0) + 1.0;
+            match batches.iter_mut().find(|b| {
+                b.mesh == mesh
+                    && b.lod == lod
+                    && b.texture == resources.texture
+                    && b.skinned == skinned
+            }) {
+                Some(batch) => batch.instances.push(instance),
+                None => batches.push(InstanceBatch {
+                    mesh,
+                    lod,
+                    texture: resources.texture,
+                    skinned,
+                    instances: vec![instance],
+                }),
+            }
+        }
 
-        self.mvp_mat.model_set_position(&glm::vec3(0.0, y, z));
+        // Objects at the same depth stay in scene order
+        translucent.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        batches.extend(translucent.into_iter().map(|(_, batch)| batch));
 
-        let mvp_mat_pcs = self.mvp_mat.as_push_constants();
-        let (_, mvp_mat_pcs_model_bytes, _) =
-            unsafe { mvp_mat_pcs.model.as_slice().align_to::<u8>() };
+        batches
+    }
 
-        // Update model opacity
-        let opacity: f32 = (model_index + 1) as f32 * 0.25;
-        let opacity_bytes = &opacity.to_ne_bytes()[..];
+    /// Send the instances of every batch to the GPU, one batch after another.
+    fn update_instance_buffer(&self, image_index: u32, batches: &[InstanceBatch]) -> Result<()> {
+        let memory = self.data.instance_buffers_memory[image_index as usize];
+        unsafe {
+            // scope the memory-map pointer for safety
+            let mut instances = self
+                .device
+                .map_memory(
+                    memory,
+                    0,
+                    instance_buffer_size(&self.data),
+                    vk::MemoryMapFlags::empty(),
+                )?
+                .cast::<InstanceData>();
+
+            for batch in batches {
+                ptr::copy_nonoverlapping(
+                    batch.instances.as_ptr(),
+                    instances,
+                    batch.instances.len(),
+                );
+                instances = instances.add(batch.instances.len());
+            }
 
-        // Specify which render pass, subpass, and framebuffer the secondary
-        // command buffer will be used with
-        let inheritance_info = vk::CommandBufferInheritanceInfo::builder()
-            .render_pass(self.data.render_pass)
-            .subpass(0)
-            .framebuffer(self.data.framebuffers[image_index]);
+            self.device.unmap_memory(memory);
+        }
 
-        // Begin recording command buffer
-        let info = vk::CommandBufferBeginInfo::builder()
-            .flags(vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE) // cmd buf will be executed entirely inside render pass
-            .inheritance_info(&inheritance_info);
+        Ok(())
+    }
 
-        unsafe {
-            self.device.begin_command_buffer(command_buffer, &info)?;
-        }
+    /// Record drawing every instance in a batch, whose instances start at
+    /// `first_instance` in the instance buffer.
+    unsafe fn draw_batch(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image_index: u32,
+        batch: &InstanceBatch,
+        first_instance: u32,
+    ) {
+        let image_index = image_index as usize;
+        let (mesh, draws) = match batch.mesh {
+            Some(mesh) => (
+                self.data.meshes[mesh],
+                self.mesh_draws[mesh]
+                    .as_ref()
+                    .unwrap_or(&self.placeholder_draws),
+            ),
+            None => (self.data.placeholder_mesh, &self.placeholder_draws),
+        };
 
-        // Draw the model
-        unsafe {
-            self.device.cmd_bind_pipeline(
-                command_buffer,
-                vk::PipelineBindPoint::GRAPHICS,
-                self.data.pipeline,
-            );
+        let pipeline = match (draws.topology, batch.skinned) {
+            (Topology::Points, _) => self.data.point_pipeline,
+            (Topology::Triangles, true) => self.data.skinned_pipeline,
+            (Topology::Triangles, false) if draws.dequantize.is_some() => {
+                self.data.quantized_pipeline
+            }
+            (Topology::Triangles, false) => self.data.pipeline,
+        };
+        self.device
+            .cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
 
-            self.device.cmd_bind_vertex_buffers(
-                command_buffer,
-                0,
-                &[self.data.vertex_buffer],
-                &[0],
-            );
-            self.device.cmd_bind_index_buffer(
-                command_buffer,
-                self.data.index_buffer,
-                0,
-                vk::IndexType::UINT32,
-            );
+        self.device.cmd_bind_vertex_buffers(
+            command_buffer,
+            0,
+            &[mesh.vertex_buffer, self.data.instance_buffers[image_index]],
+            &[0, 0],
+        );
+        self.device
+            .cmd_bind_index_buffer(command_buffer, mesh.index_buffer, 0, mesh.index_type);
 
+        // Skinned meshes also need their joints, which each instance knows
+        // where to find
+        if batch.skinned {
             self.device.cmd_bind_descriptor_sets(
                 command_buffer,
                 vk::PipelineBindPoint::GRAPHICS,
                 self.data.pipeline_layout,
-                0,
-                &[self.data.descriptor_sets[image_index as usize]],
+                2,
+                &[self.data.joint_descriptor_sets[image_index]],
                 &[],
             );
+        }
 
-            // Model push constant
-            self.device.cmd_push_constants(
+        // Draw each submesh with its own texture and base color, unless the
+        // objects override them
+        for submesh in &draws.lods[batch.lod] {
+            let (texture, base_color) = match batch.texture {
+                Some(texture) => (texture, glm::vec4(1.0, 1.0, 1.0, 1.0)),
+                None => (draws.texture(submesh), submesh.base_color),
+            };
+            // Textures that haven't finished uploading don't have a
+            // descriptor set yet
+            let texture_set = match self.data.texture_descriptor_sets.get(texture) {
+                Some(&set) if set != vk::DescriptorSet::null() => set,
+                _ => self.data.texture_descriptor_sets[WHITE_TEXTURE],
+            };
+
+            self.device.cmd_bind_descriptor_sets(
                 command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
                 self.data.pipeline_layout,
-                vk::ShaderStageFlags::VERTEX,
                 0,
-                mvp_mat_pcs_model_bytes,
+                &[self.data.descriptor_sets[image_index], texture_set],
+                &[],
             );
 
-            // Opacity push constant
+            let material_pcs = MaterialPushConstants { base_color };
+            let (_, material_pcs_bytes, _) = std::slice::from_ref(&material_pcs).align_to::<u8>();
             self.device.cmd_push_constants(
                 command_buffer,
                 self.data.pipeline_layout,
                 vk::ShaderStageFlags::FRAGMENT,
-                std::mem::size_of_val(&mvp_mat_pcs.model) as u32,
-                opacity_bytes,
+                0,
+                material_pcs_bytes,
             );
 
-            // Draw
             self.device.cmd_draw_indexed(
                 command_buffer,
-                self.data.indices.len() as u32,
-                1,
-                0,
-                0,
+                submesh.index_count,
+                batch.instances.len() as u32,
+                submesh.first_index,
                 0,
+                first_instance,
             );
         }
-
-        // End recording command buffer
-        unsafe {
-            self.device.end_command_buffer(command_buffer)?;
-        }
-
-        Ok(command_buffer)
     }
 
     /// Wait for the app's GPU to stop processing. Use this before destroying
@@ -690,18 +1964,33 @@ impl App {
     pub unsafe fn destroy(&mut self) {
         self.destroy_swapchain();
 
+        if let Some(capture) = self.pending_capture.take() {
+            destroy_capture(&self.device, &capture);
+        }
+
+        // The device is idle, so every upload has finished
+        for pending in &self.pending_assets {
+            destroy_upload(&self.device, &self.data, &pending.upload);
+            destroy_uploaded_asset(&self.device, &pending.uploaded);
+        }
+        self.retired
+            .iter()
+            .for_each(|retired| destroy_retired_resources(&self.device, retired));
+        destroy_texture_descriptor_pools(&self.device, &self.data);
+
         self.device.destroy_sampler(self.data.texture_sampler, None);
-        self.device
-            .destroy_image_view(self.data.texture_image_view, None);
-        self.device.destroy_image(self.data.texture_image, None);
-        self.device
-            .free_memory(self.data.texture_image_memory, None);
+        self.data
+            .textures
+            .iter()
+            .for_each(|t| destroy_texture(&self.device, t));
 
-        self.device
-            .destroy_descriptor_set_layout(self.data.descriptor_set_layout, None);
+        destroy_descriptor_set_layout(&self.device, &self.data);
 
-        destroy_vertex_buffer(&self.device, &self.data);
-        destroy_index_buffer(&self.device, &self.data);
+        self.data
+            .meshes
+            .iter()
+            .for_each(|m| destroy_mesh_buffers(&self.device, m));
+        destroy_mesh_buffers(&self.device, &self.data.placeholder_mesh);
         destroy_sync_objects(&self.device, &self.data);
 
         self.data
@@ -713,9 +2002,12 @@ impl App {
 
         self.device.destroy_device(None);
 
-        vk_khr::Surface::new(&self.entry, &self.instance).destroy_surface(self.data.surface, None);
+        if !self.data.is_headless() {
+            vk_khr::Surface::new(&self.entry, &self.instance)
+                .destroy_surface(self.data.surface, None);
+        }
 
-        if should_enable_validation_layers() {
+        if self.data.validation_enabled {
             vk_ext::DebugUtils::new(&self.entry, &self.instance)
                 .destroy_debug_utils_messenger(self.data.messenger, None);
         }
@@ -749,17 +2041,324 @@ impl App {
             .for_each(|f| self.device.destroy_framebuffer(*f, None));
 
         self.device.destroy_pipeline(self.data.pipeline, None);
+        self.device
+            .destroy_pipeline(self.data.skinned_pipeline, None);
+        self.device.destroy_pipeline(self.data.point_pipeline, None);
+        self.device
+            .destroy_pipeline(self.data.quantized_pipeline, None);
+        self.device
+            .destroy_pipeline(self.data.position_pipeline, None);
+        self.device.destroy_pipeline(self.data.line_pipeline, None);
         self.device
             .destroy_pipeline_layout(self.data.pipeline_layout, None);
 
         self.device.destroy_render_pass(self.data.render_pass, None);
 
-        self.data
-            .swapchain_image_views
+        if self.data.is_headless() {
+            destroy_offscreen_target(&self.device, &self.data);
+        } else {
+            self.data
+                .swapchain_image_views
+                .iter()
+                .for_each(|v| self.device.destroy_image_view(*v, None));
+            self.extensions
+                .swapchain
+                .destroy_swapchain(self.data.swapchain, None);
+        }
+    }
+}
+
+/// Destroy resources replaced by a reloaded asset.
+unsafe fn destroy_retired_resources(device: &Device, retired: &RetiredResources) {
+    if let Some(buffers) = &retired.mesh {
+        destroy_mesh_buffers(device, buffers);
+    }
+    retired
+        .textures
+        .iter()
+        .for_each(|t| destroy_texture(device, t));
+    device.destroy_descriptor_pool(retired.descriptor_pool, None);
+}
+
+/// Record the upload of a loaded mesh's buffers and material textures, and
+/// work out how to draw it.
+unsafe fn upload_mesh(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    upload: &mut Upload,
+    asset_data: AssetData,
+    vertex_format: VertexFormat,
+) -> Result<(MeshBuffers, MeshDraws, Vec<Texture>)> {
+    let mut uploaded_textures = Vec::new();
+    match asset_data {
+        // Cooked meshes are uploaded straight from the mapped bundle
+        AssetData::Bundle(bundle) => {
+            let mesh = bundle
+                .mesh()
+                .ok_or_else(|| eyre!("Bundle doesn't contain a mesh"))?;
+            let textures: Vec<_> = bundle.textures().collect();
+            let quantize = quantization(
+                vertex_format,
+                mesh.topology,
+                mesh.skin_weights,
+                &mesh.bounds,
+            );
+
+            let buffers = create_model_buffers(
+                instance,
+                device,
+                data,
+                upload,
+                mesh.vertices,
+                mesh.skin_weights,
+                mesh.indices,
+                quantize.as_ref(),
+            )?;
+            let draws = create_submesh_draws(
+                mesh.submeshes,
+                mesh.materials,
+                mesh.indices.len() as u32,
+                &mut uploaded_textures,
+                |i| create_bundle_texture(instance, device, data, upload, &textures[i]),
+            )
+            .inspect_err(|_| destroy_partial_mesh(device, &buffers, &uploaded_textures))?;
+            Ok((
+                buffers,
+                MeshDraws {
+                    topology: mesh.topology,
+                    dequantize: quantize.as_ref().map(dequantize_matrix),
+                    skin: mesh.skin.cloned(),
+                    animations: mesh.animations.to_vec(),
+                    ..MeshDraws::new(draws, mesh.lods, mesh.bounds)
+                },
+                uploaded_textures,
+            ))
+        }
+        AssetData::Model(mesh) => {
+            let quantize = quantization(
+                vertex_format,
+                mesh.topology,
+                &mesh.skin_weights,
+                &mesh.bounds,
+            );
+
+            let buffers = create_model_buffers(
+                instance,
+                device,
+                data,
+                upload,
+                &mesh.vertices,
+                &mesh.skin_weights,
+                &mesh.indices,
+                quantize.as_ref(),
+            )?;
+            let draws = create_submesh_draws(
+                &mesh.submeshes,
+                &mesh.materials,
+                mesh.indices.len() as u32,
+                &mut uploaded_textures,
+                |i| create_texture_from_image(instance, device, data, upload, &mesh.images[i]),
+            )
+            .inspect_err(|_| destroy_partial_mesh(device, &buffers, &uploaded_textures))?;
+            Ok((
+                buffers,
+                MeshDraws {
+                    topology: mesh.topology,
+                    dequantize: quantize.as_ref().map(dequantize_matrix),
+                    skin: mesh.skin,
+                    animations: mesh.animations,
+                    ..MeshDraws::new(draws, &mesh.lods, mesh.bounds)
+                },
+                uploaded_textures,
+            ))
+        }
+        AssetData::Png(_) => Err(eyre!("A PNG image isn't a mesh")),
+    }
+}
+
+/// Record the upload of a loaded texture.
+unsafe fn upload_texture(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    upload: &mut Upload,
+    asset_data: AssetData,
+) -> Result<Texture> {
+    match asset_data {
+        AssetData::Png(image) => create_texture_from_pixels(
+            instance,
+            device,
+            data,
+            upload,
+            image.width,
+            image.height,
+            image.format,
+            &image.pixels,
+        ),
+        AssetData::Bundle(bundle) => {
+            let texture = bundle
+                .textures()
+                .next()
+                .ok_or_else(|| eyre!("Bundle doesn't contain a texture"))?;
+            create_bundle_texture(instance, device, data, upload, &texture)
+        }
+        AssetData::Model(_) => Err(eyre!("A model isn't a texture")),
+    }
+}
+
+/// Destroy the buffers and textures created for a mesh whose upload failed
+/// part way through. Nothing has been submitted yet, so nothing uses them.
+unsafe fn destroy_partial_mesh(device: &Device, buffers: &MeshBuffers, textures: &[Texture]) {
+    destroy_mesh_buffers(device, buffers);
+    textures.iter().for_each(|t| destroy_texture(device, t));
+}
+
+/// Destroy the resources of an asset whose upload won't be finished.
+unsafe fn destroy_uploaded_asset(device: &Device, uploaded: &UploadedAsset) {
+    match uploaded {
+        UploadedAsset::Mesh {
+            buffers, textures, ..
+        } => destroy_partial_mesh(device, buffers, textures),
+        UploadedAsset::Texture { texture, .. } => destroy_texture(device, texture),
+    }
+}
+
+/// Upload the base color textures of a mesh's materials, and work out which
+/// texture and base color to draw each of its submeshes with. A mesh without
+/// submeshes is drawn as a single submesh with the default material.
+///
+/// `upload_image` uploads the image with the given index, which is what the
+/// materials' texture indices refer to. The uploaded textures are added to
+/// `textures`, which the draws' texture indices refer to.
+fn create_submesh_draws<F>(
+    submeshes: &[model::Submesh],
+    materials: &[model::Material],
+    index_count: u32,
+    textures: &mut Vec<Texture>,
+    mut upload_image: F,
+) -> Result<Vec<SubmeshDraw>>
+where
+    F: FnMut(usize) -> Result<Texture>,
+{
+    let whole_mesh = [model::Submesh {
+        first_index: 0,
+        index_count,
+        material: None,
+    }];
+    let submeshes = if submeshes.is_empty() {
+        &whole_mesh[..]
+    } else {
+        submeshes
+    };
+
+    // Indices into textures for each of the mesh's images, once uploaded
+    let mut image_textures = AHashMap::new();
+    let default_material = model::Material::default();
+
+    let mut draws = Vec::with_capacity(submeshes.len());
+    for submesh in submeshes {
+        let material = submesh
+            .material
+            .map_or(&default_material, |i| &materials[i]);
+
+        let texture = match material.base_color_texture {
+            Some(image) => Some(match image_textures.get(&image) {
+                Some(&texture) => texture,
+                None => {
+                    let texture = textures.len();
+                    textures.push(upload_image(image)?);
+                    image_textures.insert(image, texture);
+                    texture
+                }
+            }),
+            None => None,
+        };
+
+        draws.push(SubmeshDraw {
+            first_index: submesh.first_index,
+            index_count: submesh.index_count,
+            texture,
+            base_color: material.base_color.into(),
+        });
+    }
+
+    Ok(draws)
+}
+
+/// Upload a mesh's vertices and indices, pairing each vertex with its joint
+/// weights if the mesh is skinned, or quantizing the vertices to a bounding
+/// box if there is one.
+#[allow(clippy::too_many_arguments)]
+unsafe fn create_model_buffers(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    upload: &mut Upload,
+    vertices: &[Vertex],
+    skin_weights: &[JointWeights],
+    indices: &[u32],
+    quantize: Option<&Aabb>,
+) -> Result<MeshBuffers> {
+    if let Some(aabb) = quantize {
+        let vertices: Vec<_> = vertices
             .iter()
-            .for_each(|v| self.device.destroy_image_view(*v, None));
-        self.extensions
-            .swapchain
-            .destroy_swapchain(self.data.swapchain, None);
+            .map(|vertex| QuantizedVertex::new(vertex, aabb))
+            .collect();
+        return create_mesh_buffers(instance, device, data, upload, &vertices, indices);
+    }
+    if skin_weights.is_empty() {
+        return create_mesh_buffers(instance, device, data, upload, vertices, indices);
     }
+
+    let vertices: Vec<_> = vertices
+        .iter()
+        .zip(skin_weights)
+        .map(|(&vertex, &joint_weights)| SkinnedVertex {
+            vertex,
+            joint_weights,
+        })
+        .collect();
+    create_mesh_buffers(instance, device, data, upload, &vertices, indices)
+}
+
+/// The bounding box to quantize a mesh's vertices to, if they should be
+/// quantized. Skinning needs model space positions, and point clouds don't
+/// have a quantized pipeline, so only static triangle meshes are quantized.
+fn quantization(
+    vertex_format: VertexFormat,
+    topology: Topology,
+    skin_weights: &[JointWeights],
+    bounds: &Bounds,
+) -> Option<Aabb> {
+    let quantize = vertex_format == VertexFormat::Compact
+        && topology == Topology::Triangles
+        && skin_weights.is_empty();
+    quantize.then_some(bounds.aabb)
+}
+
+/// Upload a texture from a bundle, with its prebuilt mip chain.
+unsafe fn create_bundle_texture(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    upload: &mut Upload,
+    texture: &BundleTexture,
+) -> Result<Texture> {
+    let format = if texture.srgb {
+        vk::Format::R8G8B8A8_SRGB
+    } else {
+        vk::Format::R8G8B8A8_UNORM
+    };
+
+    create_texture_from_mips(
+        instance,
+        device,
+        data,
+        upload,
+        texture.width,
+        texture.height,
+        format,
+        &texture.levels,
+    )
 }
@@ -0,0 +1,84 @@
+//! Cooks models and textures into bundles that `vk-tut` can load without any
+//! parsing. See [`vk_tut::bundle`].
+
+use std::{fs, path::PathBuf};
+
+use clap::Parser;
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use tracing::info;
+use vk_tut::{
+    bundle::{cook, default_bundle_path},
+    model::{ModelOptions, NormalGeneration},
+};
+
+/// Cook OBJ, glTF, PLY, or STL models and PNG or JPEG textures into bundles for vk-tut.
+/// Use the bundles in place of the original files, in scene files or with
+/// vk-tut's --model and --texture options.
+#[derive(Debug, Parser)]
+#[clap(version)]
+struct Cli {
+    /// The models and textures to cook. Each one becomes its own bundle.
+    #[clap(value_parser, value_name = "PATH", required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Where to write the bundle, when cooking a single file. Defaults to the
+    /// input path with .vkb added to the end.
+    #[clap(short, long, value_parser, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// How to generate normals for models that don't have any: smooth or
+    /// flat.
+    #[clap(long, value_parser, value_name = "MODE", default_value_t)]
+    normals: NormalGeneration,
+
+    /// The most simplified levels of detail to generate for each model.
+    #[clap(long, value_parser, value_name = "COUNT", default_value_t = ModelOptions::default().max_lods)]
+    lods: usize,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    setup_logging()?;
+
+    if cli.output.is_some() && cli.inputs.len() > 1 {
+        return Err(eyre!(
+            "--output can only be used when cooking a single file"
+        ));
+    }
+
+    let outputs: Vec<_> = match cli.output {
+        Some(output) => vec![output],
+        None => cli.inputs.iter().map(|p| default_bundle_path(p)).collect(),
+    };
+    let options = ModelOptions {
+        normals: cli.normals,
+        max_lods: cli.lods,
+    };
+
+    for (input, output) in cli.inputs.iter().zip(&outputs) {
+        let bundle = cook(input, &options)?;
+        fs::write(output, &bundle).wrap_err_with(|| format!("Error writing {output:?}"))?;
+
+        info!(?input, ?output, size = bundle.len(), "Cooked bundle");
+    }
+
+    Ok(())
+}
+
+fn setup_logging() -> Result<()> {
+    use tracing_subscriber::{prelude::*, EnvFilter};
+    use tracing_tree::HierarchicalLayer;
+
+    color_eyre::install()?;
+
+    tracing_subscriber::registry()
+        .with(HierarchicalLayer::new(4).with_bracketed_fields(true))
+        .with(EnvFilter::from_default_env())
+        .try_init()?;
+
+    Ok(())
+}
@@ -0,0 +1,259 @@
+//! Bounding volumes, for framing meshes with the camera and working out how
+//! much of the screen they cover.
+
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+
+/// An axis-aligned bounding box and a bounding sphere around the same points.
+/// The box is tighter for long, thin meshes, while the sphere is cheaper to
+/// test and doesn't change when the mesh rotates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl Bounds {
+    /// Bound a set of points. Empty sets of points get an empty box and a
+    /// sphere of radius zero at the origin.
+    pub fn from_points<'a, I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = &'a glm::Vec3>,
+        I::IntoIter: Clone,
+    {
+        let points = points.into_iter();
+        let aabb = Aabb::from_points(points.clone());
+
+        // Centering the sphere on the box doesn't give the smallest sphere,
+        // but it's close, and it's the same every time
+        let center = if aabb.is_empty() {
+            glm::Vec3::zeros()
+        } else {
+            aabb.center()
+        };
+        let radius = points
+            .map(|p| glm::distance(&center, p))
+            .fold(0.0, f32::max);
+
+        Self {
+            aabb,
+            sphere: BoundingSphere { center, radius },
+        }
+    }
+
+    /// Bounds that contain these bounds after they're transformed by
+    /// `matrix`.
+    pub fn transform(&self, matrix: &glm::Mat4) -> Self {
+        Self {
+            aabb: self.aabb.transform(matrix),
+            sphere: self.sphere.transform(matrix),
+        }
+    }
+}
+
+/// An axis-aligned bounding box. A box with `min` greater than `max` is empty,
+/// and contains nothing.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Aabb {
+    pub min: glm::Vec3,
+    pub max: glm::Vec3,
+}
+
+impl Aabb {
+    /// A box that contains nothing, and that any point grows to fit.
+    pub fn empty() -> Self {
+        Self {
+            min: glm::Vec3::repeat(f32::INFINITY),
+            max: glm::Vec3::repeat(f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn from_points<'a, I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = &'a glm::Vec3>,
+    {
+        points
+            .into_iter()
+            .fold(Self::empty(), |aabb, point| aabb.union_point(point))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|i| self.min[i] > self.max[i])
+    }
+
+    pub fn center(&self) -> glm::Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    /// The width, height, and depth of the box.
+    pub fn size(&self) -> glm::Vec3 {
+        self.max - self.min
+    }
+
+    /// The smallest box that contains both this box and `point`.
+    pub fn union_point(&self, point: &glm::Vec3) -> Self {
+        Self {
+            min: glm::min2(&self.min, point),
+            max: glm::max2(&self.max, point),
+        }
+    }
+
+    /// The smallest box that contains both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: glm::min2(&self.min, &other.min),
+            max: glm::max2(&self.max, &other.max),
+        }
+    }
+
+    /// The smallest axis-aligned box that contains this box after it's
+    /// transformed by `matrix`. Rotating a box makes its bounding box bigger,
+    /// so this grows each time it's done.
+    pub fn transform(&self, matrix: &glm::Mat4) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+
+        (0..8)
+            .map(|corner| {
+                let point = glm::vec3(
+                    if corner & 1 == 0 {
+                        self.min.x
+                    } else {
+                        self.max.x
+                    },
+                    if corner & 2 == 0 {
+                        self.min.y
+                    } else {
+                        self.max.y
+                    },
+                    if corner & 4 == 0 {
+                        self.min.z
+                    } else {
+                        self.max.z
+                    },
+                );
+                (matrix * point.push(1.0)).xyz()
+            })
+            .fold(Self::empty(), |aabb, point| aabb.union_point(&point))
+    }
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct BoundingSphere {
+    pub center: glm::Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// A sphere that contains this sphere after it's transformed by `matrix`.
+    /// Non-uniform scaling makes it bigger than it needs to be, since the
+    /// radius is scaled by the largest scaling factor.
+    pub fn transform(&self, matrix: &glm::Mat4) -> Self {
+        let scale = (0..3)
+            .map(|i| matrix.column(i).xyz().norm())
+            .fold(0.0, f32::max);
+
+        Self {
+            center: (matrix * self.center.push(1.0)).xyz(),
+            radius: self.radius * scale,
+        }
+    }
+
+    /// The smallest sphere that contains both spheres.
+    pub fn union(&self, other: &Self) -> Self {
+        let offset = other.center - self.center;
+        let distance = offset.norm();
+
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return *other;
+        }
+
+        let radius = (distance + self.radius + other.radius) / 2.0;
+        Self {
+            center: self.center + offset * ((radius - self.radius) / distance),
+            radius,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_contain_all_points() {
+        let points = [
+            glm::vec3(-1.0, 0.0, 0.0),
+            glm::vec3(3.0, 2.0, 0.0),
+            glm::vec3(1.0, 1.0, 4.0),
+        ];
+
+        let bounds = Bounds::from_points(&points);
+
+        assert_eq!(bounds.aabb.min, glm::vec3(-1.0, 0.0, 0.0));
+        assert_eq!(bounds.aabb.max, glm::vec3(3.0, 2.0, 4.0));
+        assert_eq!(bounds.sphere.center, glm::vec3(1.0, 1.0, 2.0));
+        for point in &points {
+            assert!(glm::distance(&bounds.sphere.center, point) <= bounds.sphere.radius);
+        }
+
+        let empty = Bounds::from_points(&[] as &[glm::Vec3]);
+        assert!(empty.aabb.is_empty());
+        assert_eq!(empty.sphere.radius, 0.0);
+    }
+
+    #[test]
+    fn transformed_bounds_contain_transformed_box() {
+        let aabb = Aabb {
+            min: glm::vec3(-1.0, -1.0, -1.0),
+            max: glm::vec3(1.0, 1.0, 1.0),
+        };
+        let bounds = Bounds {
+            aabb,
+            sphere: BoundingSphere {
+                center: glm::Vec3::zeros(),
+                radius: 3.0_f32.sqrt(),
+            },
+        };
+        let matrix = glm::translation(&glm::vec3(5.0, 0.0, 0.0))
+            * glm::rotation(std::f32::consts::FRAC_PI_4, &glm::Vec3::z())
+            * glm::scaling(&glm::vec3(2.0, 2.0, 2.0));
+
+        let transformed = bounds.transform(&matrix);
+
+        let half_diagonal = 2.0 * 2.0_f32.sqrt();
+        assert!((transformed.aabb.max.x - (5.0 + half_diagonal)).abs() < 1e-5);
+        assert!((transformed.aabb.min.y + half_diagonal).abs() < 1e-5);
+        assert_eq!(transformed.aabb.max.z, 2.0);
+        assert!((transformed.sphere.center - glm::vec3(5.0, 0.0, 0.0)).norm() < 1e-5);
+        assert!((transformed.sphere.radius - 2.0 * 3.0_f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sphere_union_contains_both_spheres() {
+        let a = BoundingSphere {
+            center: glm::vec3(0.0, 0.0, 0.0),
+            radius: 1.0,
+        };
+        let b = BoundingSphere {
+            center: glm::vec3(4.0, 0.0, 0.0),
+            radius: 2.0,
+        };
+
+        let union = a.union(&b);
+
+        assert_eq!(union.center, glm::vec3(2.5, 0.0, 0.0));
+        assert_eq!(union.radius, 3.5);
+        assert_eq!(a.union(&union), union);
+    }
+}
@@ -0,0 +1,673 @@
+//! Cooked asset bundles, which hold a mesh or texture in the form the GPU
+//! wants it, so that the app can upload it without any parsing.
+//!
+//! Bundles are made by the `vk-tut-cook` binary, and have the extension
+//! [`BUNDLE_EXTENSION`]. A mesh bundle holds a mesh's deduplicated vertices
+//! and indices, its submeshes and materials, and every texture its materials
+//! use. Skinned meshes also keep their skin weights, skeleton, and animation
+//! clips. A texture bundle holds a single texture. Textures are stored as 8-bit
+//! RGBA pixels with their full mip chain.
+//!
+//! The layout of a bundle is:
+//!
+//! 1. A 32-byte header: the magic bytes `VKTB`, the format version, the size
+//!    of a [`Vertex`], four reserved bytes, and the offset and length of the
+//!    table of contents. All of these are little-endian.
+//! 2. Blobs of vertices, indices, skin weights, and pixels, each aligned to 16 bytes.
+//!    Vertices and indices are stored exactly as they are in memory, so
+//!    bundles are only portable between little-endian machines.
+//! 3. The table of contents, in RON, giving the metadata and the location of
+//!    each blob.
+
+use std::{
+    fmt::Debug,
+    fs::File,
+    mem::size_of,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animation::{AnimationClip, Skin},
+    bounds::Bounds,
+    model::{load_model, Image, Lod, Material, Mesh, ModelOptions, Submesh, Topology},
+    vertex::{JointWeights, Vertex},
+};
+
+/// The file extension for bundles.
+pub const BUNDLE_EXTENSION: &str = "vkb";
+
+/// The version of the bundle format. Bump this whenever the layout of a
+/// bundle or of [`Vertex`] changes.
+pub const BUNDLE_VERSION: u32 = 5;
+
+const MAGIC: &[u8; 4] = b"VKTB";
+const HEADER_SIZE: usize = 32;
+const BLOB_ALIGNMENT: usize = 16;
+
+/// Whether `path` looks like a bundle, going by its extension.
+pub fn is_bundle(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == BUNDLE_EXTENSION)
+}
+
+/// Where a cooked version of the asset at `path` goes by default: next to it,
+/// with [`BUNDLE_EXTENSION`] tacked on, so that `room.obj` and `room.png`
+/// become `room.obj.vkb` and `room.png.vkb`.
+pub fn default_bundle_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_owned();
+    file_name.push(".");
+    file_name.push(BUNDLE_EXTENSION);
+    path.with_file_name(file_name)
+}
+
+/// Cook a model or image file into a bundle, returning the bundle's bytes.
+/// PNG and JPEG files become texture bundles, while anything else is loaded
+/// with [`load_model()`].
+#[tracing::instrument(level = "DEBUG", skip_all, fields(path = ?path))]
+pub fn cook<P>(path: P, options: &ModelOptions) -> Result<Vec<u8>>
+where
+    P: AsRef<Path> + Debug,
+{
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+
+    match extension.as_deref() {
+        Some("png" | "jpg" | "jpeg") => {
+            let image = ::image::open(path)
+                .wrap_err_with(|| format!("Error loading image {path:?}"))?
+                .into_rgba8();
+            let image = Image {
+                width: image.width(),
+                height: image.height(),
+                pixels: image.into_raw(),
+            };
+            Ok(cook_texture(&image))
+        }
+        _ => Ok(cook_mesh(&load_model(path, options)?)),
+    }
+}
+
+/// Cook a mesh, along with all of its images, into a bundle.
+pub fn cook_mesh(mesh: &Mesh) -> Vec<u8> {
+    let mut writer = BundleWriter::new();
+
+    let vertices = writer.add_blob(slice_bytes(&mesh.vertices));
+    let indices = writer.add_blob(slice_bytes(&mesh.indices));
+    let skin_weights = mesh
+        .is_skinned()
+        .then(|| writer.add_blob(slice_bytes(&mesh.skin_weights)));
+    let textures = mesh
+        .images
+        .iter()
+        .enumerate()
+        .map(|(i, image)| {
+            // Only color textures are in sRGB, see model::Image
+            let srgb = mesh
+                .materials
+                .iter()
+                .any(|m| m.base_color_texture == Some(i));
+            writer.add_texture(image, srgb)
+        })
+        .collect();
+
+    writer.finish(Toc {
+        mesh: Some(MeshToc {
+            vertices,
+            indices,
+            topology: mesh.topology,
+            submeshes: mesh.submeshes.clone(),
+            lods: mesh.lods.clone(),
+            bounds: mesh.bounds,
+            materials: mesh.materials.clone(),
+            skin_weights,
+            skin: mesh.skin.clone(),
+            animations: mesh.animations.clone(),
+        }),
+        textures,
+    })
+}
+
+/// Cook a single color texture into a bundle.
+pub fn cook_texture(image: &Image) -> Vec<u8> {
+    let mut writer = BundleWriter::new();
+    let texture = writer.add_texture(image, true);
+
+    writer.finish(Toc {
+        mesh: None,
+        textures: vec![texture],
+    })
+}
+
+/// A memory-mapped bundle.
+pub struct Bundle {
+    map: Mmap,
+    toc: Toc,
+}
+
+impl Bundle {
+    /// Memory-map a bundle, and check that it's valid.
+    ///
+    /// The file mustn't be modified while the bundle is open.
+    #[tracing::instrument(level = "DEBUG", skip_all, fields(path = ?path))]
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path> + Debug,
+    {
+        let path = path.as_ref();
+        let file = File::open(path).wrap_err_with(|| format!("Error opening bundle {path:?}"))?;
+
+        // Safety: the caller promises not to modify the file while it's mapped
+        let map = unsafe { Mmap::map(&file) }
+            .wrap_err_with(|| format!("Error memory-mapping bundle {path:?}"))?;
+
+        Self::from_map(map).wrap_err_with(|| format!("Error loading bundle {path:?}"))
+    }
+
+    fn from_map(map: Mmap) -> Result<Self> {
+        let toc = parse_header(&map)?;
+        let bundle = Self { map, toc };
+        bundle.validate()?;
+
+        Ok(bundle)
+    }
+
+    /// The mesh in the bundle, if it's a mesh bundle.
+    pub fn mesh(&self) -> Option<BundleMesh<'_>> {
+        let mesh = self.toc.mesh.as_ref()?;
+
+        Some(BundleMesh {
+            vertices: cast_slice(self.blob(&mesh.vertices)),
+            indices: cast_slice(self.blob(&mesh.indices)),
+            topology: mesh.topology,
+            submeshes: &mesh.submeshes,
+            lods: &mesh.lods,
+            bounds: mesh.bounds,
+            materials: &mesh.materials,
+            skin_weights: mesh
+                .skin_weights
+                .as_ref()
+                .map_or(&[], |blob| cast_slice(self.blob(blob))),
+            skin: mesh.skin.as_ref(),
+            animations: &mesh.animations,
+        })
+    }
+
+    /// The textures in the bundle. Materials refer to these by index, in
+    /// place of indices into [`Mesh::images`].
+    pub fn textures(&self) -> impl ExactSizeIterator<Item = BundleTexture<'_>> {
+        self.toc.textures.iter().map(|texture| BundleTexture {
+            width: texture.width,
+            height: texture.height,
+            srgb: texture.srgb,
+            levels: texture.levels.iter().map(|b| self.blob(b)).collect(),
+        })
+    }
+
+    fn blob(&self, blob: &Blob) -> &[u8] {
+        &self.map[blob.range()]
+    }
+
+    /// Check everything that could make the renderer read out of bounds.
+    fn validate(&self) -> Result<()> {
+        let blobs = self
+            .toc
+            .mesh
+            .iter()
+            .flat_map(|m| [Some(&m.vertices), Some(&m.indices), m.skin_weights.as_ref()])
+            .flatten();
+        let levels = self.toc.textures.iter().flat_map(|t| &t.levels);
+        for blob in blobs.chain(levels) {
+            let end = blob.offset.saturating_add(blob.len);
+            if !(blob.offset as usize).is_multiple_of(BLOB_ALIGNMENT) || end > self.map.len() as u64
+            {
+                return Err(eyre!("Blob {blob:?} is misaligned or out of bounds"));
+            }
+        }
+        if let Some(mesh) = &self.toc.mesh {
+            if !(mesh.vertices.len as usize).is_multiple_of(size_of::<Vertex>())
+                || !(mesh.indices.len as usize).is_multiple_of(size_of::<u32>())
+            {
+                return Err(eyre!("Vertex or index data has a partial element"));
+            }
+            if mesh.skin_weights.is_some() != mesh.skin.is_some()
+                || mesh.skin_weights.is_some_and(|b| {
+                    b.len
+                        != (mesh.vertices.len / size_of::<Vertex>() as u64)
+                            * size_of::<JointWeights>() as u64
+                })
+            {
+                return Err(eyre!("Skin weights don't match the vertices"));
+            }
+        }
+
+        for texture in &self.toc.textures {
+            let expected = mip_sizes(texture.width, texture.height);
+            if texture.levels.len() != expected.len()
+                || texture
+                    .levels
+                    .iter()
+                    .zip(expected)
+                    .any(|(blob, (w, h))| blob.len != 4 * w as u64 * h as u64)
+            {
+                return Err(eyre!(
+                    "Texture of size {}x{} has the wrong mip levels",
+                    texture.width,
+                    texture.height
+                ));
+            }
+        }
+
+        let Some(mesh) = self.mesh() else {
+            return Ok(());
+        };
+
+        let vertex_count = mesh.vertices.len();
+        if let Some(index) = mesh.indices.iter().find(|&&i| i as usize >= vertex_count) {
+            return Err(eyre!(
+                "Index {index} is out of range for {vertex_count} vertices"
+            ));
+        }
+        let lod_submeshes = mesh.lods.iter().flat_map(|lod| &lod.submeshes);
+        if let Some(submesh) = mesh.submeshes.iter().chain(lod_submeshes).find(|s| {
+            s.first_index as usize + s.index_count as usize > mesh.indices.len()
+                || s.material.is_some_and(|m| m >= mesh.materials.len())
+        }) {
+            return Err(eyre!("Submesh {submesh:?} is out of range"));
+        }
+        if mesh
+            .lods
+            .iter()
+            .any(|lod| lod.submeshes.len() != mesh.submeshes.len())
+        {
+            return Err(eyre!("Level of detail doesn't match the mesh's submeshes"));
+        }
+        if let Some(skin) = mesh.skin {
+            // Joints have to come after their parents for
+            // Skin::joint_matrices(), and the shader can't check indices
+            let joint_count = skin.joints.len();
+            let weight_joints = mesh.skin_weights.iter().flat_map(|w| w.joints);
+            let channel_joints = mesh.animations.iter().flat_map(|a| &a.channels);
+            if skin
+                .joints
+                .iter()
+                .enumerate()
+                .any(|(i, joint)| joint.parent.is_some_and(|p| p >= i))
+                || weight_joints.into_iter().any(|j| j as usize >= joint_count)
+                || channel_joints.into_iter().any(|c| c.joint >= joint_count)
+            {
+                return Err(eyre!(
+                    "Skin or animation uses a joint that isn't in the skin"
+                ));
+            }
+        }
+        let texture_count = self.toc.textures.len();
+        if mesh.materials.iter().any(|m| {
+            [m.base_color_texture, m.normal_texture, m.specular_texture]
+                .iter()
+                .flatten()
+                .any(|&t| t >= texture_count)
+        }) {
+            return Err(eyre!("Material uses a texture that isn't in the bundle"));
+        }
+
+        Ok(())
+    }
+}
+
+/// A mesh in a [`Bundle`], borrowed straight from the mapped file.
+#[derive(Clone, Copy, Debug)]
+pub struct BundleMesh<'a> {
+    pub vertices: &'a [Vertex],
+    pub indices: &'a [u32],
+    pub topology: Topology,
+    pub submeshes: &'a [Submesh],
+    pub lods: &'a [Lod],
+    pub bounds: Bounds,
+    /// Texture indices refer to [`Bundle::textures()`].
+    pub materials: &'a [Material],
+    /// Empty unless the mesh is skinned.
+    pub skin_weights: &'a [JointWeights],
+    pub skin: Option<&'a Skin>,
+    pub animations: &'a [AnimationClip],
+}
+
+/// A texture in a [`Bundle`], borrowed straight from the mapped file.
+#[derive(Clone, Debug)]
+pub struct BundleTexture<'a> {
+    pub width: u32,
+    pub height: u32,
+    /// Whether the pixels are in the sRGB color space, rather than linear.
+    pub srgb: bool,
+    /// Tightly-packed RGBA pixels for each mip level, from largest to
+    /// smallest.
+    pub levels: Vec<&'a [u8]>,
+}
+
+/// The metadata of a bundle, which is stored at the end of the file.
+#[derive(Debug, Deserialize, Serialize)]
+struct Toc {
+    mesh: Option<MeshToc>,
+    textures: Vec<TextureToc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct MeshToc {
+    vertices: Blob,
+    indices: Blob,
+    topology: Topology,
+    submeshes: Vec<Submesh>,
+    lods: Vec<Lod>,
+    bounds: Bounds,
+    materials: Vec<Material>,
+    skin_weights: Option<Blob>,
+    skin: Option<Skin>,
+    animations: Vec<AnimationClip>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct TextureToc {
+    width: u32,
+    height: u32,
+    srgb: bool,
+    levels: Vec<Blob>,
+}
+
+/// Where some bytes are in a bundle.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct Blob {
+    offset: u64,
+    len: u64,
+}
+
+impl Blob {
+    fn range(&self) -> Range<usize> {
+        self.offset as usize..self.offset.saturating_add(self.len) as usize
+    }
+}
+
+struct BundleWriter {
+    bytes: Vec<u8>,
+}
+
+impl BundleWriter {
+    fn new() -> Self {
+        // The header is filled in by finish()
+        Self {
+            bytes: vec![0; HEADER_SIZE],
+        }
+    }
+
+    fn add_blob(&mut self, blob: &[u8]) -> Blob {
+        let padding = self.bytes.len().next_multiple_of(BLOB_ALIGNMENT) - self.bytes.len();
+        self.bytes.extend(std::iter::repeat_n(0, padding));
+
+        let offset = self.bytes.len() as u64;
+        self.bytes.extend_from_slice(blob);
+
+        Blob {
+            offset,
+            len: blob.len() as u64,
+        }
+    }
+
+    fn add_texture(&mut self, image: &Image, srgb: bool) -> TextureToc {
+        let levels = mip_chain(image)
+            .iter()
+            .map(|level| self.add_blob(level))
+            .collect();
+
+        TextureToc {
+            width: image.width,
+            height: image.height,
+            srgb,
+            levels,
+        }
+    }
+
+    fn finish(mut self, toc: Toc) -> Vec<u8> {
+        let toc = ron::to_string(&toc).expect("Bundle contents should serialize");
+        let toc = self.add_blob(toc.as_bytes());
+
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&BUNDLE_VERSION.to_le_bytes());
+        header.extend_from_slice(&(size_of::<Vertex>() as u32).to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(&toc.offset.to_le_bytes());
+        header.extend_from_slice(&toc.len.to_le_bytes());
+        self.bytes[..HEADER_SIZE].copy_from_slice(&header);
+
+        self.bytes
+    }
+}
+
+fn parse_header(bytes: &[u8]) -> Result<Toc> {
+    let header = bytes
+        .get(..HEADER_SIZE)
+        .ok_or_else(|| eyre!("File is too small to be a bundle"))?;
+    let u32_at = |i: usize| u32::from_le_bytes(header[i..i + 4].try_into().unwrap());
+    let u64_at = |i: usize| u64::from_le_bytes(header[i..i + 8].try_into().unwrap());
+
+    if &header[..4] != MAGIC {
+        return Err(eyre!("File isn't a bundle"));
+    }
+    let version = u32_at(4);
+    if version != BUNDLE_VERSION {
+        return Err(eyre!(
+            "Bundle has version {version}, but only version {BUNDLE_VERSION} is supported. \
+             Re-cook it with vk-tut-cook"
+        ));
+    }
+    if u32_at(8) as usize != size_of::<Vertex>() {
+        return Err(eyre!("Bundle was cooked with a different vertex layout"));
+    }
+
+    let toc = Blob {
+        offset: u64_at(16),
+        len: u64_at(24),
+    };
+    let toc = bytes
+        .get(toc.range())
+        .ok_or_else(|| eyre!("Bundle table of contents is out of bounds"))?;
+
+    Ok(ron::de::from_bytes(toc)?)
+}
+
+/// The width and height of each level in a full mip chain, in the same way
+/// that the renderer counts them.
+fn mip_sizes(width: u32, height: u32) -> Vec<(u32, u32)> {
+    let mip_levels = (width.max(height) as f32).log2().floor() as u32 + 1;
+
+    (0..mip_levels)
+        .map(|level| ((width >> level).max(1), (height >> level).max(1)))
+        .collect()
+}
+
+/// Downsample an image into a full mip chain, starting with the image itself.
+fn mip_chain(image: &Image) -> Vec<Vec<u8>> {
+    let full = ::image::RgbaImage::from_raw(image.width, image.height, image.pixels.clone())
+        .expect("Image should have 4 bytes per pixel");
+
+    mip_sizes(image.width, image.height)
+        .into_iter()
+        .map(|(width, height)| {
+            if (width, height) == (image.width, image.height) {
+                image.pixels.clone()
+            } else {
+                ::image::imageops::resize(
+                    &full,
+                    width,
+                    height,
+                    ::image::imageops::FilterType::Triangle,
+                )
+                .into_raw()
+            }
+        })
+        .collect()
+}
+
+fn slice_bytes<T: Copy>(slice: &[T]) -> &[u8] {
+    // Safety: T is plain old data (vertices or indices), and u8 has no
+    // alignment requirements
+    unsafe { std::slice::from_raw_parts(slice.as_ptr().cast(), std::mem::size_of_val(slice)) }
+}
+
+/// Reinterpret a blob as a slice of `T`s. The blob's alignment and length have
+/// already been checked.
+fn cast_slice<T: Copy>(bytes: &[u8]) -> &[T] {
+    // Safety: T is plain old data (vertices or indices), which any bit
+    // pattern is valid for
+    let (prefix, slice, suffix) = unsafe { bytes.align_to::<T>() };
+    assert!(
+        prefix.is_empty() && suffix.is_empty(),
+        "Bundle blob isn't a whole number of aligned elements"
+    );
+    slice
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use nalgebra_glm as glm;
+
+    use super::*;
+    use crate::animation::{Channel, Interpolation, Joint, Property, Transform};
+
+    fn open_bytes(bytes: &[u8]) -> Result<Bundle> {
+        let mut map = memmap2::MmapMut::map_anon(bytes.len())?;
+        (&mut map[..]).write_all(bytes)?;
+        Bundle::from_map(map.make_read_only()?)
+    }
+
+    #[test]
+    fn mesh_round_trips() {
+        let vertex = |x| {
+            Vertex::new(
+                glm::vec3(x, 0.0, 0.0),
+                glm::vec3(1.0, 1.0, 1.0),
+                glm::vec2(x, 0.0),
+                glm::Vec3::z(),
+            )
+        };
+        let mesh = Mesh {
+            vertices: vec![vertex(0.0), vertex(1.0), vertex(2.0)],
+            indices: vec![0, 1, 2],
+            topology: Topology::Triangles,
+            submeshes: vec![Submesh {
+                first_index: 0,
+                index_count: 3,
+                material: Some(0),
+            }],
+            lods: vec![Lod {
+                submeshes: vec![Submesh {
+                    first_index: 3,
+                    index_count: 0,
+                    material: Some(0),
+                }],
+                error: 0.5,
+            }],
+            bounds: Bounds::from_points(&[glm::vec3(0.0, 0.0, 0.0), glm::vec3(2.0, 0.0, 0.0)]),
+            materials: vec![Material {
+                base_color_texture: Some(0),
+                normal_texture: Some(1),
+                ..Default::default()
+            }],
+            images: vec![
+                Image {
+                    width: 4,
+                    height: 2,
+                    pixels: vec![255; 32],
+                },
+                Image {
+                    width: 1,
+                    height: 1,
+                    pixels: vec![128; 4],
+                },
+            ],
+            skin: Some(Skin {
+                joints: vec![Joint {
+                    name: Some("root".into()),
+                    parent: None,
+                    parent_transform: glm::identity(),
+                    rest_pose: Transform::identity(),
+                    inverse_bind_matrix: glm::translation(&glm::vec3(0.0, 0.0, -1.0)),
+                }],
+            }),
+            skin_weights: vec![
+                JointWeights {
+                    joints: [0; 4],
+                    weights: [1.0, 0.0, 0.0, 0.0],
+                };
+                3
+            ],
+            animations: vec![AnimationClip {
+                name: Some("spin".into()),
+                duration: 1.0,
+                channels: vec![Channel {
+                    joint: 0,
+                    property: Property::Rotation,
+                    interpolation: Interpolation::Linear,
+                    times: vec![0.0, 1.0],
+                    values: vec![glm::Quat::identity().coords; 2],
+                }],
+            }],
+            dependencies: Vec::new(),
+        };
+
+        let bundle = open_bytes(&cook_mesh(&mesh)).unwrap();
+        let cooked = bundle.mesh().unwrap();
+
+        assert_eq!(cooked.vertices, &mesh.vertices[..]);
+        assert_eq!(cooked.indices, &mesh.indices[..]);
+        assert_eq!(cooked.topology, mesh.topology);
+        assert_eq!(cooked.submeshes, &mesh.submeshes[..]);
+        assert_eq!(cooked.lods, &mesh.lods[..]);
+        assert_eq!(cooked.bounds, mesh.bounds);
+        assert_eq!(cooked.skin_weights, &mesh.skin_weights[..]);
+        assert_eq!(cooked.skin, mesh.skin.as_ref());
+        assert_eq!(cooked.animations, &mesh.animations[..]);
+        assert_eq!(cooked.materials, &mesh.materials[..]);
+
+        let textures: Vec<_> = bundle.textures().collect();
+        assert_eq!(textures.len(), 2);
+        assert!(textures[0].srgb);
+        assert!(!textures[1].srgb);
+        // 4x2, 2x1, 1x1
+        assert_eq!(
+            textures[0]
+                .levels
+                .iter()
+                .map(|l| l.len())
+                .collect::<Vec<_>>(),
+            vec![32, 8, 4]
+        );
+        assert_eq!(textures[0].levels[2], &[255; 4]);
+    }
+
+    #[test]
+    fn rejects_other_versions_and_corruption() {
+        let mut bytes = cook_texture(&Image {
+            width: 1,
+            height: 1,
+            pixels: vec![0; 4],
+        });
+        assert!(open_bytes(&bytes).unwrap().mesh().is_none());
+
+        bytes[4] = 0;
+        let error = open_bytes(&bytes).err().unwrap();
+        assert!(error.to_string().contains("version 0"));
+
+        assert!(open_bytes(b"VKTB").is_err());
+    }
+}
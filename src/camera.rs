@@ -0,0 +1,515 @@
+//! Cameras, which decide where the scene is viewed from.
+
+use std::{fmt, str::FromStr};
+
+use nalgebra_glm as glm;
+
+use crate::{bounds::BoundingSphere, mvp_matrix::MvpMat};
+
+/// How much closer than the front of a framed sphere [`Camera::frame()`] puts
+/// the near plane.
+const FRAMING_NEAR_SLACK: f32 = 0.5;
+
+/// How much further than the back of a framed sphere [`Camera::frame()`] puts
+/// the far plane.
+const FRAMING_FAR_SLACK: f32 = 2.0;
+
+/// How far a [`FlyController`] turns for each pixel the mouse moves, in
+/// radians.
+const FLY_LOOK_SENSITIVITY: f32 = 0.003;
+
+/// How close to straight up or down cameras can be turned to look, in
+/// radians. Looking exactly along the camera's up vector would leave no way to
+/// tell left from right.
+const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+/// How much each line scrolled scales a [`FlyController`]'s speed by.
+const FLY_SCROLL_FACTOR: f32 = 1.2;
+
+/// The slowest and fastest a [`FlyController`] can be scrolled to move, in
+/// units per second.
+const FLY_SPEED_RANGE: (f32, f32) = (0.01, 1000.0);
+
+/// How many times faster a [`FlyController`] moves while sprinting.
+const FLY_SPRINT_FACTOR: f32 = 4.0;
+
+/// How far an [`OrbitController`] orbits for each pixel the mouse is dragged,
+/// in radians.
+const ORBIT_SENSITIVITY: f32 = 0.01;
+
+/// How much each line scrolled scales an [`OrbitController`]'s distance to
+/// its target by.
+const ORBIT_ZOOM_FACTOR: f32 = 1.1;
+
+/// The closest an [`OrbitController`] can zoom in to its target.
+const ORBIT_MIN_DISTANCE: f32 = 1e-3;
+
+/// A perspective camera looking at a point in the scene.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// The position of the camera.
+    pub eye: glm::Vec3,
+    /// The point the camera is looking at.
+    pub target: glm::Vec3,
+    /// Which way is up for the camera.
+    pub up: glm::Vec3,
+    /// The vertical field of view, in radians.
+    pub fovy: f32,
+    /// The distance to the near clip plane.
+    pub near: f32,
+    /// The distance to the far clip plane.
+    pub far: f32,
+}
+
+impl Camera {
+    /// Position and orientate the view and projection matrices of `mvp_mat`
+    /// according to this camera, for a viewport with the given aspect ratio.
+    pub fn apply(&self, mvp_mat: &mut MvpMat, aspect_ratio: f32) {
+        mvp_mat
+            .look_at(&self.eye, &self.target, &self.up)
+            .perspective(aspect_ratio, self.fovy, self.near, self.far);
+    }
+
+    /// Move the camera towards or away from `sphere` until it just fits in
+    /// view, for a viewport with the given aspect ratio, and look at its
+    /// center. The camera keeps looking from the same direction. The near and
+    /// far planes are fitted around the sphere with some room to spare, so
+    /// that spinning objects and small camera moves don't get clipped.
+    pub fn frame(&mut self, sphere: &BoundingSphere, aspect_ratio: f32) {
+        let direction = (self.eye - self.target)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(|| Self::default().eye.normalize());
+
+        // The sphere has to fit both vertically and horizontally
+        let half_fovy = self.fovy / 2.0;
+        let half_fovx = (half_fovy.tan() * aspect_ratio).atan();
+        let radius = sphere.radius.max(f32::EPSILON);
+        let distance = radius / half_fovy.min(half_fovx).sin();
+
+        self.target = sphere.center;
+        self.eye = sphere.center + direction * distance;
+        self.near = (distance - radius) * FRAMING_NEAR_SLACK;
+        self.far = (distance + radius) * FRAMING_FAR_SLACK;
+    }
+}
+
+impl Default for Camera {
+    /// Looks at the origin from a distance, with a 45-degree vertical FOV.
+    fn default() -> Self {
+        Self {
+            eye: glm::vec3(6.0, 0.0, 2.0),
+            target: glm::vec3(0.0, 0.0, 0.0),
+            up: glm::vec3(0.0, 0.0, 1.0),
+            fovy: glm::radians(&glm::vec1(45.0))[0],
+            near: 0.1,
+            far: 10.0,
+        }
+    }
+}
+
+/// The directions a [`FlyController`] can move a camera in, relative to
+/// where the camera is looking.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlyDirection {
+    Forward,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Flies a [`Camera`] around the scene, first-person style, from keyboard and
+/// mouse input.
+///
+/// Input is collected as it arrives, and applied to the camera once per frame
+/// by [`FlyController::update()`], so that movement is the same speed
+/// regardless of frame rate.
+#[derive(Clone, Debug)]
+pub struct FlyController {
+    /// How fast the camera moves, in units per second.
+    pub speed: f32,
+    /// Whether to move [`FLY_SPRINT_FACTOR`] times faster than `speed`.
+    pub sprinting: bool,
+    /// Which directions are being moved in, indexed by [`FlyDirection`].
+    moving: [bool; 6],
+    /// How far the mouse has moved since the last update, in pixels.
+    look: glm::Vec2,
+}
+
+impl FlyController {
+    /// Start or stop moving in a direction, such as when its key is pressed
+    /// or released.
+    pub fn set_moving(&mut self, direction: FlyDirection, moving: bool) {
+        self.moving[direction as usize] = moving;
+    }
+
+    /// Stop moving and sprinting in every direction, such as when the window
+    /// loses focus and key releases can't be seen anymore.
+    pub fn stop(&mut self) {
+        self.moving = [false; 6];
+        self.sprinting = false;
+    }
+
+    /// Turn the camera by a mouse movement, in pixels. Moving right turns
+    /// right, and moving down looks down.
+    pub fn look(&mut self, dx: f32, dy: f32) {
+        self.look += glm::vec2(dx, dy);
+    }
+
+    /// Speed up or slow down by a number of lines scrolled. Scrolling up
+    /// speeds up.
+    pub fn scroll(&mut self, lines: f32) {
+        let (min, max) = FLY_SPEED_RANGE;
+        self.speed = (self.speed * FLY_SCROLL_FACTOR.powf(lines)).clamp(min, max);
+    }
+
+    /// Turn and move `camera` by the input since the last update, moving as
+    /// far as it would in `delta_t` seconds. The camera keeps the same
+    /// distance to its target, so that it can still be framed and orbited
+    /// from wherever it ends up.
+    ///
+    /// Returns whether the camera changed.
+    pub fn update(&mut self, camera: &mut Camera, delta_t: f32) -> bool {
+        let look = std::mem::take(&mut self.look);
+        if look == glm::Vec2::zeros() && !self.moving.contains(&true) {
+            return false;
+        }
+
+        let up = camera.up.normalize();
+        let offset = camera.target - camera.eye;
+        let distance = offset.norm();
+        let Some(forward) = offset.try_normalize(f32::EPSILON) else {
+            return false;
+        };
+
+        let forward = turn(
+            &forward,
+            &up,
+            -look.x * FLY_LOOK_SENSITIVITY,
+            -look.y * FLY_LOOK_SENSITIVITY,
+        );
+        let right = forward
+            .cross(&up)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(glm::Vec3::zeros);
+
+        let direction = [
+            (FlyDirection::Forward, forward),
+            (FlyDirection::Back, -forward),
+            (FlyDirection::Left, -right),
+            (FlyDirection::Right, right),
+            (FlyDirection::Up, up),
+            (FlyDirection::Down, -up),
+        ]
+        .into_iter()
+        .filter(|(d, _)| self.moving[*d as usize])
+        .map(|(_, v)| v)
+        .sum::<glm::Vec3>();
+
+        // Moving diagonally isn't any faster than moving straight
+        if let Some(direction) = direction.try_normalize(f32::EPSILON) {
+            let speed = if self.sprinting {
+                self.speed * FLY_SPRINT_FACTOR
+            } else {
+                self.speed
+            };
+            camera.eye += direction * speed * delta_t;
+        }
+        camera.target = camera.eye + forward * distance;
+
+        true
+    }
+}
+
+impl Default for FlyController {
+    /// Not moving, at 2 units per second.
+    fn default() -> Self {
+        Self {
+            speed: 2.0,
+            sprinting: false,
+            moving: [false; 6],
+            look: glm::Vec2::zeros(),
+        }
+    }
+}
+
+/// Which of the camera controllers drives the camera.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// A [`FlyController`].
+    #[default]
+    Fly,
+    /// An [`OrbitController`].
+    Orbit,
+}
+
+impl CameraMode {
+    /// The other camera mode.
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Fly => Self::Orbit,
+            Self::Orbit => Self::Fly,
+        }
+    }
+}
+
+impl FromStr for CameraMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fly" => Ok(Self::Fly),
+            "orbit" => Ok(Self::Orbit),
+            _ => Err(format!(
+                "Unknown camera mode {s:?}, expected one of: fly, orbit"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CameraMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Fly => "fly",
+            Self::Orbit => "orbit",
+        })
+    }
+}
+
+/// What dragging the mouse does to an [`OrbitController`]'s camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrbitDrag {
+    /// Orbit around the target.
+    Orbit,
+    /// Move the camera and its target across the view.
+    Pan,
+}
+
+/// Orbits a [`Camera`] around its target, arcball style, for inspecting
+/// models with the mouse.
+///
+/// Like [`FlyController`], input is collected as it arrives, and applied to
+/// the camera once per frame by [`OrbitController::update()`].
+#[derive(Clone, Debug, Default)]
+pub struct OrbitController {
+    /// The drag in progress, if any.
+    drag: Option<OrbitDrag>,
+    /// How far the mouse has been dragged to orbit since the last update, in
+    /// pixels.
+    orbit: glm::Vec2,
+    /// How far the mouse has been dragged to pan since the last update, in
+    /// pixels.
+    pan: glm::Vec2,
+    /// How many lines have been scrolled since the last update.
+    zoom: f32,
+}
+
+impl OrbitController {
+    /// Start dragging, such as when a mouse button is pressed. Replaces any
+    /// drag already in progress.
+    pub fn start_drag(&mut self, drag: OrbitDrag) {
+        self.drag = Some(drag);
+    }
+
+    /// Stop dragging, such as when the mouse button that started the drag is
+    /// released.
+    pub fn end_drag(&mut self, drag: OrbitDrag) {
+        if self.drag == Some(drag) {
+            self.drag = None;
+        }
+    }
+
+    /// Stop any drag in progress, such as when the window loses focus and
+    /// button releases can't be seen anymore.
+    pub fn stop(&mut self) {
+        self.drag = None;
+    }
+
+    /// Move the mouse, in pixels. Does nothing unless a drag is in progress.
+    pub fn drag(&mut self, dx: f32, dy: f32) {
+        match self.drag {
+            Some(OrbitDrag::Orbit) => self.orbit += glm::vec2(dx, dy),
+            Some(OrbitDrag::Pan) => self.pan += glm::vec2(dx, dy),
+            None => {}
+        }
+    }
+
+    /// Zoom in or out by a number of lines scrolled. Scrolling up zooms in.
+    pub fn zoom(&mut self, lines: f32) {
+        self.zoom += lines;
+    }
+
+    /// Orbit, pan, and zoom `camera` by the input since the last update, for
+    /// a viewport `viewport_height` pixels tall. Panning moves the target
+    /// along with the mouse, and zooming scales the near and far planes along
+    /// with the distance to the target, so that whatever was between them
+    /// stays between them.
+    ///
+    /// Returns whether the camera changed.
+    pub fn update(&mut self, camera: &mut Camera, viewport_height: f32) -> bool {
+        let orbit = std::mem::take(&mut self.orbit);
+        let pan = std::mem::take(&mut self.pan);
+        let zoom = std::mem::take(&mut self.zoom);
+        if orbit == glm::Vec2::zeros() && pan == glm::Vec2::zeros() && zoom == 0.0 {
+            return false;
+        }
+
+        let up = camera.up.normalize();
+        let offset = camera.target - camera.eye;
+        let distance = offset.norm();
+        let Some(forward) = offset.try_normalize(f32::EPSILON) else {
+            return false;
+        };
+
+        // Dragging turns the model the same way, so the camera goes the
+        // other way around it
+        let forward = turn(
+            &forward,
+            &up,
+            -orbit.x * ORBIT_SENSITIVITY,
+            -orbit.y * ORBIT_SENSITIVITY,
+        );
+
+        // Move the target across the view by as many units as the mouse moved
+        // across the target's depth
+        if let Some(right) = forward.cross(&up).try_normalize(f32::EPSILON) {
+            let view_up = right.cross(&forward);
+            let units_per_pixel =
+                2.0 * distance * (camera.fovy / 2.0).tan() / viewport_height.max(1.0);
+            camera.target += (view_up * pan.y - right * pan.x) * units_per_pixel;
+        }
+
+        let new_distance = (distance * ORBIT_ZOOM_FACTOR.powf(-zoom)).max(ORBIT_MIN_DISTANCE);
+        let scale = new_distance / distance;
+        camera.near *= scale;
+        camera.far *= scale;
+        camera.eye = camera.target - forward * new_distance;
+
+        true
+    }
+}
+
+/// Turn a `forward` direction about the `up` vector by `yaw` radians, then
+/// tilt it `pitch` radians towards `up`, stopping short of looking straight up
+/// or down.
+fn turn(forward: &glm::Vec3, up: &glm::Vec3, yaw: f32, pitch: f32) -> glm::Vec3 {
+    let forward = glm::rotate_vec3(forward, yaw, up);
+    let Some(right) = forward.cross(up).try_normalize(f32::EPSILON) else {
+        return forward;
+    };
+
+    let old_pitch = forward.dot(up).clamp(-1.0, 1.0).asin();
+    let new_pitch = (old_pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+    glm::rotate_vec3(&forward, new_pitch - old_pitch, &right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framing_fits_the_sphere_between_the_clip_planes() {
+        let sphere = BoundingSphere {
+            center: glm::vec3(1.0, 2.0, 3.0),
+            radius: 2.0,
+        };
+        let mut camera = Camera::default();
+        let direction = (camera.eye - camera.target).normalize();
+
+        camera.frame(&sphere, 16.0 / 9.0);
+
+        assert_eq!(camera.target, sphere.center);
+        let distance = glm::distance(&camera.eye, &sphere.center);
+        assert!((camera.eye - sphere.center).normalize().dot(&direction) > 0.9999);
+        assert!((distance - 2.0 / (camera.fovy / 2.0).sin()).abs() < 1e-4);
+        assert!(camera.near > 0.0 && camera.near < distance - sphere.radius);
+        assert!(camera.far > distance + sphere.radius);
+
+        // Narrow viewports have to back off further to fit the sphere in
+        let mut narrow = Camera::default();
+        narrow.frame(&sphere, 0.5);
+        assert!(glm::distance(&narrow.eye, &sphere.center) > distance);
+    }
+
+    #[test]
+    fn flying_is_independent_of_frame_rate() {
+        let mut fly = FlyController::default();
+        fly.set_moving(FlyDirection::Forward, true);
+        fly.set_moving(FlyDirection::Right, true);
+
+        let mut one_frame = Camera::default();
+        fly.update(&mut one_frame, 0.5);
+        let mut two_frames = Camera::default();
+        fly.update(&mut two_frames, 0.25);
+        fly.update(&mut two_frames, 0.25);
+
+        let start = Camera::default();
+        assert!(glm::distance(&one_frame.eye, &two_frames.eye) < 1e-5);
+        assert!((glm::distance(&one_frame.eye, &start.eye) - fly.speed * 0.5).abs() < 1e-5);
+        assert!(
+            (glm::distance(&one_frame.eye, &one_frame.target)
+                - glm::distance(&start.eye, &start.target))
+            .abs()
+                < 1e-4
+        );
+    }
+
+    #[test]
+    fn looking_up_stops_short_of_the_up_vector() {
+        let mut fly = FlyController::default();
+        let mut camera = Camera::default();
+
+        fly.look(0.0, -100_000.0);
+        assert!(fly.update(&mut camera, 0.0));
+
+        let forward = (camera.target - camera.eye).normalize();
+        let pitch = forward.dot(&camera.up).asin();
+        assert!((pitch - MAX_PITCH).abs() < 1e-4);
+
+        // With no input, the camera is left alone
+        let before = camera;
+        assert!(!fly.update(&mut camera, 1.0));
+        assert_eq!(camera, before);
+    }
+
+    #[test]
+    fn orbiting_keeps_the_target_and_distance() {
+        let mut orbit = OrbitController::default();
+        let mut camera = Camera::default();
+        let distance = glm::distance(&camera.eye, &camera.target);
+
+        orbit.start_drag(OrbitDrag::Orbit);
+        orbit.drag(120.0, -40.0);
+        orbit.end_drag(OrbitDrag::Orbit);
+        orbit.drag(1000.0, 1000.0);
+        assert!(orbit.update(&mut camera, 768.0));
+
+        assert_eq!(camera.target, Camera::default().target);
+        assert!((glm::distance(&camera.eye, &camera.target) - distance).abs() < 1e-4);
+        assert!((camera.eye - Camera::default().eye).norm() > 0.1);
+    }
+
+    #[test]
+    fn panning_and_zooming_move_the_target_and_clip_planes() {
+        let mut orbit = OrbitController::default();
+        let mut camera = Camera::default();
+        let start = camera;
+
+        // Dragging down moves the scene down, so the camera and its target
+        // go up together
+        orbit.start_drag(OrbitDrag::Pan);
+        orbit.drag(0.0, 100.0);
+        orbit.update(&mut camera, 768.0);
+        assert!(camera.target.z > start.target.z);
+        assert!(glm::distance(&(camera.target - camera.eye), &(start.target - start.eye)) < 1e-4);
+
+        orbit.zoom(2.0);
+        orbit.update(&mut camera, 768.0);
+        let scale =
+            glm::distance(&camera.eye, &camera.target) / glm::distance(&start.eye, &start.target);
+        assert!((scale - ORBIT_ZOOM_FACTOR.powi(-2)).abs() < 1e-4);
+        assert!((camera.near - start.near * scale).abs() < 1e-6);
+        assert!((camera.far - start.far * scale).abs() < 1e-4);
+    }
+}
@@ -1,9 +1,18 @@
+pub mod animation;
 pub mod app;
-pub(crate) mod model;
+pub mod bounds;
+pub mod bundle;
+pub mod camera;
+pub(crate) mod loader;
+pub mod model;
 pub(crate) mod mvp_matrix;
 pub(crate) mod renderer;
+pub mod scene;
+pub mod screenshot;
+pub mod settings;
 pub mod util;
-pub(crate) mod vertex;
+pub mod vertex;
+pub(crate) mod watcher;
 
 /// The maximum number of frames that the app is allowed to submit to the GPU
 /// for rendering before we have to wait for the GPU to finish rendering a
@@ -0,0 +1,189 @@
+//! Loading assets from disk on worker threads.
+//!
+//! Reading and parsing models and images is the slow part of loading a scene,
+//! and doesn't need the GPU, so an [`AssetLoader`] does it on a pool of worker
+//! threads. The render loop polls for loaded assets each frame and uploads
+//! them to the GPU, drawing placeholders until they're ready.
+
+use std::{
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use color_eyre::{eyre::eyre, Result};
+use tracing::{debug, info_span};
+
+use crate::{
+    bundle::{is_bundle, Bundle},
+    model::{load_model, Mesh, ModelOptions},
+    renderer::texture::{read_png, PngImage},
+};
+
+/// Which of a scene's assets a job loads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Asset {
+    /// The mesh with this index in [`Scene::meshes`](crate::scene::Scene::meshes).
+    Mesh(usize),
+    /// The texture with this index in
+    /// [`Scene::textures`](crate::scene::Scene::textures).
+    Texture(usize),
+}
+
+/// An asset to load from a file.
+#[derive(Clone, Debug)]
+pub struct Job {
+    pub asset: Asset,
+    pub path: PathBuf,
+}
+
+/// An asset that's been read into memory, ready to be uploaded to the GPU.
+pub enum AssetData {
+    /// A mesh parsed from a model file.
+    Model(Mesh),
+    /// A texture decoded from a PNG image.
+    Png(PngImage),
+    /// A mapped bundle, holding either a mesh or a texture.
+    Bundle(Bundle),
+}
+
+/// The result of a [`Job`].
+pub struct LoadedAsset {
+    pub asset: Asset,
+    pub path: PathBuf,
+    pub data: Result<AssetData>,
+}
+
+/// A pool of worker threads that load assets in the background.
+///
+/// Jobs are taken by whichever worker is free, so assets can finish loading in
+/// any order. The workers are stopped when the loader is dropped, after they
+/// finish the jobs they're working on.
+pub struct AssetLoader {
+    jobs: Option<Sender<Job>>,
+    results: Receiver<LoadedAsset>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl AssetLoader {
+    /// Start one worker per CPU core. Models are loaded with `options`.
+    pub fn new(options: ModelOptions) -> Self {
+        let worker_count = thread::available_parallelism().map_or(1, |n| n.get());
+
+        let (jobs, job_receiver) = mpsc::channel();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..worker_count)
+            .map(|_| {
+                let job_receiver = Arc::clone(&job_receiver);
+                let result_sender = result_sender.clone();
+                thread::spawn(move || loop {
+                    // Only hold the lock while waiting for a job, so that the
+                    // other workers can take jobs while this one is busy
+                    let job = match job_receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+                    let Ok(job) = job else {
+                        return;
+                    };
+
+                    let data = load_asset(&job, &options);
+                    let loaded = LoadedAsset {
+                        asset: job.asset,
+                        path: job.path,
+                        data,
+                    };
+                    if result_sender.send(loaded).is_err() {
+                        return;
+                    }
+                })
+            })
+            .collect();
+
+        debug!(worker_count, "Started asset loader");
+
+        Self {
+            jobs: Some(jobs),
+            results,
+            workers,
+        }
+    }
+
+    /// Queue an asset to be loaded. Loading the same asset again reads it from
+    /// disk again.
+    pub fn load(&self, job: Job) {
+        // The workers only stop once `jobs` is dropped, so this can't fail
+        if let Some(jobs) = &self.jobs {
+            jobs.send(job).ok();
+        }
+    }
+
+    /// Take the assets that have finished loading since the last poll, without
+    /// waiting for any more.
+    pub fn poll(&self) -> Vec<LoadedAsset> {
+        self.results.try_iter().collect()
+    }
+
+    /// Wait for the next asset to finish loading.
+    pub fn wait(&self) -> Result<LoadedAsset> {
+        self.results
+            .recv()
+            .map_err(|_| eyre!("Every asset loader thread has stopped"))
+    }
+}
+
+impl Drop for AssetLoader {
+    fn drop(&mut self) {
+        // Hanging up makes each worker stop once it's finished its current job
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            worker.join().ok();
+        }
+    }
+}
+
+/// Read an asset from its file.
+fn load_asset(job: &Job, options: &ModelOptions) -> Result<AssetData> {
+    let _span = info_span!("load_asset", asset = ?job.asset, path = ?job.path).entered();
+
+    if is_bundle(&job.path) {
+        return Ok(AssetData::Bundle(Bundle::open(&job.path)?));
+    }
+
+    match job.asset {
+        Asset::Mesh(_) => Ok(AssetData::Model(load_model(&job.path, options)?)),
+        Asset::Texture(_) => Ok(AssetData::Png(read_png(&job.path)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_assets_and_reports_failures() {
+        let loader = AssetLoader::new(ModelOptions::default());
+        loader.load(Job {
+            asset: Asset::Mesh(0),
+            path: "resources/viking-room/viking-room.obj".into(),
+        });
+        loader.load(Job {
+            asset: Asset::Texture(3),
+            path: "resources/does-not-exist.png".into(),
+        });
+
+        let mut loaded: Vec<_> = (0..2).map(|_| loader.wait().unwrap()).collect();
+        loaded.sort_by_key(|loaded| matches!(loaded.asset, Asset::Texture(_)));
+
+        assert_eq!(loaded[0].asset, Asset::Mesh(0));
+        assert!(matches!(loaded[0].data, Ok(AssetData::Model(_))));
+        assert_eq!(loaded[1].asset, Asset::Texture(3));
+        assert!(loaded[1].data.is_err());
+        assert!(loader.poll().is_empty());
+    }
+}
@@ -1,22 +1,162 @@
+use clap::Parser;
 use color_eyre::Result;
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use tracing::{debug, info, warn};
-use vk_tut::app::App;
+use vk_tut::{
+    app::App,
+    camera::{CameraMode, FlyDirection, OrbitDrag},
+    model::{ModelOptions, NormalGeneration},
+    screenshot::Screenshot,
+    settings::{AppSettings, DeviceSelector, PresentMode},
+    vertex::VertexFormat,
+};
 use winit::{
     dpi::LogicalSize,
-    event::{ElementState, Event, VirtualKeyCode, WindowEvent},
+    event::{
+        DeviceEvent, ElementState, Event, MouseButton, MouseScrollDelta, VirtualKeyCode,
+        WindowEvent,
+    },
     event_loop::{ControlFlow, EventLoop},
     window::{Window, WindowBuilder},
 };
 
+/// A Rust implementation of the famous vulkan-tutorial.com
+#[derive(Debug, Parser)]
+#[clap(version, about)]
+struct Cli {
+    /// Path to a RON scene file describing the meshes, textures, and objects
+    /// to display.
+    #[clap(long, value_parser, value_name = "PATH", conflicts_with_all = &["model", "texture"])]
+    scene: Option<PathBuf>,
+
+    /// Path to the OBJ, glTF, PLY, or STL model to display, or a bundle cooked from one
+    /// with vk-tut-cook.
+    #[clap(long, value_parser, value_name = "PATH")]
+    model: Option<PathBuf>,
+
+    /// Path to a PNG texture, or a bundle cooked from one, to apply to the
+    /// whole model. If a model is given without a texture, the model's own
+    /// materials are used.
+    #[clap(long, value_parser, value_name = "PATH")]
+    texture: Option<PathBuf>,
+
+    /// How to generate normals for models that don't have any: smooth or
+    /// flat.
+    #[clap(long, value_parser, value_name = "MODE", default_value_t)]
+    normals: NormalGeneration,
+
+    /// The most simplified levels of detail to generate for each model.
+    #[clap(long, value_parser, value_name = "COUNT", default_value_t = ModelOptions::default().max_lods)]
+    lods: usize,
+
+    /// How to store vertices on the GPU: full, or compact to quantize them to
+    /// less than half the size.
+    #[clap(long, value_parser, value_name = "FORMAT", default_value_t)]
+    vertex_format: VertexFormat,
+
+    /// Initial width of the window, in pixels.
+    #[clap(long, value_parser, default_value_t = 1024)]
+    width: u32,
+
+    /// Initial height of the window, in pixels.
+    #[clap(long, value_parser, default_value_t = 768)]
+    height: u32,
+
+    /// Number of samples per pixel for multisampled anti-aliasing, from 2 to
+    /// 64. Defaults to the maximum supported by the GPU.
+    #[clap(long, value_parser, value_name = "SAMPLES")]
+    msaa: Option<u32>,
+
+    /// Preferred swapchain present mode: immediate, mailbox, fifo, or
+    /// fifo-relaxed. Defaults to mailbox if supported, and fifo otherwise.
+    #[clap(long, value_parser, value_name = "MODE")]
+    present_mode: Option<PresentMode>,
+
+    /// Size of the points point clouds are drawn with, in pixels.
+    #[clap(long, value_parser, value_name = "PIXELS", default_value_t = 1.0)]
+    point_size: f32,
+
+    /// Enable Vulkan validation layers. Enabled by default in debug builds,
+    /// or if ENABLE_VULKAN_VALIDATION_LAYERS is set.
+    #[clap(long, overrides_with = "no_validation")]
+    validation: bool,
+
+    /// Disable Vulkan validation layers.
+    #[clap(long, overrides_with = "validation")]
+    no_validation: bool,
+
+    /// Use the GPU with this index or name (case-insensitive substring),
+    /// instead of picking the best one. Overrides VK_TUT_DEVICE.
+    #[clap(long, value_parser, value_name = "INDEX|NAME")]
+    gpu: Option<DeviceSelector>,
+
+    /// Reload models and textures when their files change on disk.
+    #[clap(long)]
+    watch: bool,
+
+    /// How to move the camera around to begin with: fly, to fly around with
+    /// WASD and the mouse, or orbit, to drag the camera around the model.
+    /// Press C to switch between them.
+    #[clap(long, value_parser, value_name = "MODE", default_value_t)]
+    camera: CameraMode,
+}
+
+impl Cli {
+    /// Convert command-line options into settings for the app, using the
+    /// defaults for anything that wasn't specified.
+    fn into_settings(self) -> AppSettings {
+        let defaults = AppSettings::default();
+
+        AppSettings {
+            scene_path: self.scene,
+            // Only fall back to the default texture for the default model
+            texture_path: match (&self.model, self.texture) {
+                (_, Some(texture)) => Some(texture),
+                (Some(_), None) => None,
+                (None, None) => defaults.texture_path,
+            },
+            model_path: self.model.unwrap_or(defaults.model_path),
+            normals: self.normals,
+            max_lods: self.lods,
+            vertex_format: self.vertex_format,
+            width: self.width,
+            height: self.height,
+            msaa_samples: self.msaa,
+            present_mode: self.present_mode,
+            point_size: self.point_size,
+            validation: match (self.validation, self.no_validation) {
+                (true, _) => true,
+                (_, true) => false,
+                _ => defaults.validation,
+            },
+            device: self.gpu.or(defaults.device),
+            watch_assets: self.watch,
+            camera_mode: self.camera,
+        }
+    }
+}
+
 fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    // Logging has to be set up first to see warnings about the settings
     setup_logging()?;
+    let settings = cli.into_settings();
 
-    let (event_loop, window) = build_window()?;
+    let (event_loop, window) = build_window(&settings)?;
 
     info!("Initializing app");
-    let mut app = unsafe { App::create(&window)? };
+    let mut app = unsafe { App::create(&window, &settings)? };
     let mut destroying = false;
     let mut is_minimized = false;
+    // Whether the cursor is grabbed for looking around with the mouse
+    let mut cursor_grabbed = false;
+    // When the left mouse button was last pressed while orbiting, for
+    // spotting double clicks
+    let mut last_click: Option<Instant> = None;
 
     info!("Running event loop");
     event_loop.run(move |event, _, control_flow| {
@@ -27,7 +167,15 @@ fn main() -> Result<()> {
             // Render a frame if our Vulkan app is not being destroyed and if
             // it is not minimized.
             Event::MainEventsCleared if !destroying && !is_minimized => {
-                unsafe { app.render(&window) }.unwrap()
+                unsafe { app.render(&window) }.unwrap();
+
+                // Save screenshots once the frame they were requested for has
+                // been rendered
+                if let Some(screenshot) = app.take_screenshot() {
+                    if let Err(e) = screenshot.and_then(|s| save_screenshot(&s)) {
+                        warn!("Failed to save screenshot: {e:?}");
+                    }
+                }
             }
 
             // Signal to our app if the window is resized, and track minimization.
@@ -43,18 +191,137 @@ fn main() -> Result<()> {
                 }
             }
 
-            // Handle key presses
+            // Fly the camera with WASD to move, Q and E to move down and up,
+            // and shift to sprint
+            Event::WindowEvent {
+                event: WindowEvent::KeyboardInput { input, .. },
+                ..
+            } if app.camera_mode() == CameraMode::Fly
+                && input.virtual_keycode.is_some_and(is_fly_key) =>
+            {
+                let pressed = input.state == ElementState::Pressed;
+                let fly = app.fly_controller();
+                match input.virtual_keycode.and_then(fly_direction) {
+                    Some(direction) => fly.set_moving(direction, pressed),
+                    None => fly.sprinting = pressed,
+                }
+            }
+
+            // Clicking grabs the cursor to look around with the mouse, and
+            // escape lets go of it
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Left,
+                        ..
+                    },
+                ..
+            } if app.camera_mode() == CameraMode::Fly && !cursor_grabbed => {
+                cursor_grabbed = grab_cursor(&window, true)
+            }
             Event::WindowEvent {
                 event: WindowEvent::KeyboardInput { input, .. },
                 ..
+            } if cursor_grabbed
+                && input.state == ElementState::Pressed
+                && input.virtual_keycode == Some(VirtualKeyCode::Escape) =>
+            {
+                cursor_grabbed = grab_cursor(&window, false);
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta: (dx, dy) },
+                ..
+            } if cursor_grabbed => app.fly_controller().look(dx as f32, dy as f32),
+
+            // Orbit the camera by dragging with the left mouse button, and pan
+            // by dragging with the right. Double clicking frames the models.
+            Event::WindowEvent {
+                event: WindowEvent::MouseInput { state, button, .. },
+                ..
+            } if app.camera_mode() == CameraMode::Orbit => {
+                let drag = match button {
+                    MouseButton::Left => Some(OrbitDrag::Orbit),
+                    MouseButton::Right => Some(OrbitDrag::Pan),
+                    _ => None,
+                };
+                match (state, drag) {
+                    (ElementState::Pressed, Some(OrbitDrag::Orbit))
+                        if last_click.is_some_and(|t| t.elapsed() < DOUBLE_CLICK_TIME) =>
+                    {
+                        last_click = None;
+                        app.frame_objects();
+                    }
+                    (ElementState::Pressed, Some(drag)) => {
+                        if drag == OrbitDrag::Orbit {
+                            last_click = Some(Instant::now());
+                        }
+                        app.orbit_controller().start_drag(drag);
+                    }
+                    (ElementState::Released, Some(drag)) => app.orbit_controller().end_drag(drag),
+                    (_, None) => {}
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta: (dx, dy) },
+                ..
+            } if app.camera_mode() == CameraMode::Orbit => {
+                app.orbit_controller().drag(dx as f32, dy as f32)
+            }
+
+            // Scrolling changes how fast the camera flies, or zooms when
+            // orbiting
+            Event::WindowEvent {
+                event: WindowEvent::MouseWheel { delta, .. },
+                ..
+            } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => (position.y / PIXELS_PER_LINE) as f32,
+                };
+                match app.camera_mode() {
+                    CameraMode::Fly => app.fly_controller().scroll(lines),
+                    CameraMode::Orbit => app.orbit_controller().zoom(lines),
+                }
+            }
+
+            // Key and button releases can't be seen without focus, so stop
+            // moving the camera and let go of the cursor
+            Event::WindowEvent {
+                event: WindowEvent::Focused(false),
+                ..
             } => {
-                // When left/right pressed, incr/decr number of models displayed
-                if input.state == ElementState::Pressed {
-                    match input.virtual_keycode {
-                        Some(VirtualKeyCode::Left) if app.num_models > 1 => app.num_models -= 1,
-                        Some(VirtualKeyCode::Right) if app.num_models < 4 => app.num_models += 1,
-                        _ => {}
+                app.fly_controller().stop();
+                app.orbit_controller().stop();
+                if cursor_grabbed {
+                    cursor_grabbed = grab_cursor(&window, false);
+                }
+            }
+
+            // Handle key presses
+            Event::WindowEvent {
+                event: WindowEvent::KeyboardInput { input, .. },
+                ..
+            } if input.state == ElementState::Pressed => {
+                // When left/right pressed, incr/decr number of models displayed.
+                // F frames the displayed models with the camera. N plays the
+                // next animation. C switches between flying and orbiting the
+                // camera. F12 saves a screenshot.
+                match input.virtual_keycode {
+                    Some(VirtualKeyCode::Left) if app.num_models > 1 => app.num_models -= 1,
+                    Some(VirtualKeyCode::Right) if app.num_models < app.scene().objects.len() => {
+                        app.num_models += 1
+                    }
+                    Some(VirtualKeyCode::F) => app.frame_objects(),
+                    Some(VirtualKeyCode::N) => app.next_animation(),
+                    Some(VirtualKeyCode::C) => {
+                        app.set_camera_mode(app.camera_mode().toggled());
+                        if cursor_grabbed {
+                            cursor_grabbed = grab_cursor(&window, false);
+                        }
                     }
+                    Some(VirtualKeyCode::F12) => app.request_screenshot(),
+                    _ => {}
                 }
             }
 
@@ -81,15 +348,55 @@ fn main() -> Result<()> {
     });
 }
 
+/// Roughly how many pixels touchpads scroll by per line of a mouse wheel.
+const PIXELS_PER_LINE: f64 = 40.0;
+
+/// The longest time between two clicks for them to count as a double click.
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+
+/// Whether a key flies the camera around.
+fn is_fly_key(key: VirtualKeyCode) -> bool {
+    matches!(key, VirtualKeyCode::LShift | VirtualKeyCode::RShift) || fly_direction(key).is_some()
+}
+
+/// The direction a key flies the camera in, if any.
+fn fly_direction(key: VirtualKeyCode) -> Option<FlyDirection> {
+    match key {
+        VirtualKeyCode::W => Some(FlyDirection::Forward),
+        VirtualKeyCode::S => Some(FlyDirection::Back),
+        VirtualKeyCode::A => Some(FlyDirection::Left),
+        VirtualKeyCode::D => Some(FlyDirection::Right),
+        VirtualKeyCode::E => Some(FlyDirection::Up),
+        VirtualKeyCode::Q => Some(FlyDirection::Down),
+        _ => None,
+    }
+}
+
+/// Grab and hide the cursor, or let go of it and show it again. Returns
+/// whether the cursor is now grabbed.
+fn grab_cursor(window: &Window, grab: bool) -> bool {
+    if let Err(e) = window.set_cursor_grab(grab) {
+        warn!("Failed to change cursor grab: {e}");
+        return !grab;
+    }
+    window.set_cursor_visible(!grab);
+
+    grab
+}
+
 /// Create the window and event loop.
-#[tracing::instrument(level = "DEBUG")]
-fn build_window() -> Result<(EventLoop<()>, Window)> {
-    debug!("Creating window of default size and event loop");
+#[tracing::instrument(level = "DEBUG", skip_all)]
+fn build_window(settings: &AppSettings) -> Result<(EventLoop<()>, Window)> {
+    debug!(
+        width = settings.width,
+        height = settings.height,
+        "Creating window and event loop"
+    );
 
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
         .with_title("Rusty Vulkan Tutorial")
-        .with_inner_size(LogicalSize::new(1024, 768))
+        .with_inner_size(LogicalSize::new(settings.width, settings.height))
         .build(&event_loop)?;
 
     debug!(window_id = ?window.id(), "Window creation successful");
@@ -97,6 +404,17 @@ fn build_window() -> Result<(EventLoop<()>, Window)> {
     Ok((event_loop, window))
 }
 
+/// Save a screenshot to a timestamped PNG file in the current directory.
+fn save_screenshot(screenshot: &Screenshot) -> Result<()> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let path = PathBuf::from(format!("screenshot-{timestamp}.png"));
+
+    screenshot.save_png(&path)?;
+    info!(?path, "Saved screenshot");
+
+    Ok(())
+}
+
 fn setup_logging() -> Result<()> {
     use tracing_subscriber::{prelude::*, EnvFilter};
     use tracing_tree::HierarchicalLayer;
@@ -0,0 +1,844 @@
+//! Loading glTF 2.0 models, in either the `.gltf` or binary `.glb` format.
+
+use std::path::{Path, PathBuf};
+
+use ::gltf::{
+    animation::{util::ReadOutputs, Interpolation as GltfInterpolation},
+    buffer, image,
+    mesh::Mode,
+    Document, Node, Scene,
+};
+use ahash::{AHashMap, AHashSet};
+use color_eyre::{eyre::eyre, Result};
+use nalgebra_glm as glm;
+use tracing::warn;
+
+use super::{Image, Material, Mesh, Submesh};
+use crate::{
+    animation::{AnimationClip, Channel, Interpolation, Joint, Property, Skin, Transform},
+    vertex::{JointWeights, Vertex},
+};
+
+/// Load a model from a glTF file, along with any external buffers and images
+/// it refers to.
+///
+/// Every mesh instance in the default scene is flattened into a single mesh,
+/// with its node's world transform baked into its vertices. Each primitive
+/// becomes its own [`Submesh`].
+///
+/// The joints of all the skins in the scene are merged into a single
+/// [`Skin`], and the animations that move them are loaded as its clips.
+/// Skinned meshes are left where their skin binds them, since it's the joints
+/// that place them in the world.
+pub(super) fn load_gltf(path: &Path) -> Result<Mesh> {
+    let (document, buffers, images) = ::gltf::import(path)?;
+    let mut mesh = convert_gltf(&document, &buffers, images)?;
+
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    mesh.dependencies = external_files(&document)
+        .map(|file| base_dir.join(file))
+        .collect();
+
+    Ok(mesh)
+}
+
+/// Convert an imported glTF document into a [`Mesh`].
+fn convert_gltf(
+    document: &Document,
+    buffers: &[buffer::Data],
+    images: Vec<image::Data>,
+) -> Result<Mesh> {
+    let mut mesh = Mesh {
+        images: images
+            .into_iter()
+            .map(convert_image)
+            .collect::<Result<_>>()?,
+        materials: document.materials().map(convert_material).collect(),
+        ..Default::default()
+    };
+
+    match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => {
+            let skin_joints = match Skeleton::load(document, buffers, &scene)? {
+                Some(skeleton) => {
+                    mesh.animations = document
+                        .animations()
+                        .map(|animation| {
+                            convert_animation(animation, buffers, &skeleton.node_joints)
+                        })
+                        .filter_map(Result::transpose)
+                        .collect::<Result<_>>()?;
+                    mesh.skin = Some(skeleton.skin);
+                    skeleton.skin_joints
+                }
+                None => Vec::new(),
+            };
+
+            for node in scene.nodes() {
+                add_node(&mut mesh, buffers, &skin_joints, &node, &glm::identity())?;
+            }
+        }
+
+        // Without any scenes there is no node hierarchy, so just draw every
+        // mesh where it is. Skins need the hierarchy, so there aren't any.
+        None => {
+            for gltf_mesh in document.meshes() {
+                add_mesh(&mut mesh, buffers, &gltf_mesh, &glm::identity(), None)?;
+            }
+        }
+    }
+
+    Ok(mesh)
+}
+
+/// Every joint of every glTF skin in a scene, merged into one [`Skin`] so
+/// that a single palette of joint matrices can skin the whole mesh.
+struct Skeleton {
+    skin: Skin,
+    /// The joint that each joint node became, by node index.
+    node_joints: AHashMap<usize, usize>,
+    /// For each glTF skin, the joint that each of its joints became.
+    skin_joints: Vec<Vec<u16>>,
+}
+
+impl Skeleton {
+    /// Merge the skins of a scene, or return `None` if there aren't any.
+    fn load(document: &Document, buffers: &[buffer::Data], scene: &Scene) -> Result<Option<Self>> {
+        if document.skins().len() == 0 {
+            return Ok(None);
+        }
+
+        let joint_nodes: AHashSet<usize> = document
+            .skins()
+            .flat_map(|skin| skin.joints().map(|node| node.index()))
+            .collect();
+        let mut skeleton = Self {
+            skin: Skin { joints: Vec::new() },
+            node_joints: AHashMap::new(),
+            skin_joints: Vec::new(),
+        };
+        for node in scene.nodes() {
+            skeleton.add_node(&node, &joint_nodes, None, &glm::identity());
+        }
+
+        // Vertices refer to joints with 16-bit indices
+        if skeleton.skin.joints.len() > usize::from(u16::MAX) + 1 {
+            return Err(eyre!(
+                "glTF skins have {} joints, but at most {} are supported",
+                skeleton.skin.joints.len(),
+                usize::from(u16::MAX) + 1
+            ));
+        }
+
+        for gltf_skin in document.skins() {
+            let joints = gltf_skin
+                .joints()
+                .map(|node| {
+                    skeleton
+                        .node_joints
+                        .get(&node.index())
+                        .map(|&joint| joint as u16)
+                        .ok_or_else(|| {
+                            eyre!("glTF skin joint node {} isn't in the scene", node.index())
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            // Without inverse bind matrices, the mesh is bound where the
+            // joints are
+            let reader = gltf_skin.reader(|buffer| buffers.get(buffer.index()).map(|b| &b[..]));
+            if let Some(matrices) = reader.read_inverse_bind_matrices() {
+                for (&joint, matrix) in joints.iter().zip(matrices) {
+                    skeleton.skin.joints[usize::from(joint)].inverse_bind_matrix =
+                        glm::Mat4::from(matrix);
+                }
+            }
+
+            skeleton.skin_joints.push(joints);
+        }
+
+        Ok(Some(skeleton))
+    }
+
+    /// Add the joints among a node and its children, recursively, so that
+    /// parents come before their children.
+    fn add_node(
+        &mut self,
+        node: &Node,
+        joint_nodes: &AHashSet<usize>,
+        parent: Option<usize>,
+        parent_transform: &glm::Mat4,
+    ) {
+        let (parent, parent_transform) = if joint_nodes.contains(&node.index()) {
+            let (translation, [x, y, z, w], scale) = node.transform().decomposed();
+            let joint = self.skin.joints.len();
+            self.skin.joints.push(Joint {
+                name: node.name().map(Into::into),
+                parent,
+                parent_transform: *parent_transform,
+                rest_pose: Transform {
+                    translation: translation.into(),
+                    rotation: glm::quat(x, y, z, w),
+                    scale: scale.into(),
+                },
+                inverse_bind_matrix: glm::identity(),
+            });
+            self.node_joints.insert(node.index(), joint);
+
+            (Some(joint), glm::identity())
+        } else {
+            (
+                parent,
+                parent_transform * glm::Mat4::from(node.transform().matrix()),
+            )
+        };
+
+        for child in node.children() {
+            self.add_node(&child, joint_nodes, parent, &parent_transform);
+        }
+    }
+}
+
+/// Add the meshes of a node and its children, recursively. `skin_joints` is
+/// [`Skeleton::skin_joints`], or empty if there isn't a skeleton.
+fn add_node(
+    mesh: &mut Mesh,
+    buffers: &[buffer::Data],
+    skin_joints: &[Vec<u16>],
+    node: &Node,
+    parent_transform: &glm::Mat4,
+) -> Result<()> {
+    let transform = parent_transform * glm::Mat4::from(node.transform().matrix());
+
+    if let Some(gltf_mesh) = node.mesh() {
+        match node.skin().and_then(|skin| skin_joints.get(skin.index())) {
+            // The joints place skinned meshes, so their node's transform is
+            // ignored
+            Some(joints) => add_mesh(mesh, buffers, &gltf_mesh, &glm::identity(), Some(joints))?,
+            None => add_mesh(mesh, buffers, &gltf_mesh, &transform, None)?,
+        }
+    }
+
+    for child in node.children() {
+        add_node(mesh, buffers, skin_joints, &child, &transform)?;
+    }
+
+    Ok(())
+}
+
+/// Add each triangle primitive of a glTF mesh as a submesh, transformed into
+/// world space. `skin_joints` maps the joints of the mesh's skin, if it has
+/// one, to the joints of the mesh's [`Skin`].
+///
+/// If the mesh has a skin, every vertex gets joint weights, with unskinned
+/// vertices getting none so that they stay where they are.
+fn add_mesh(
+    mesh: &mut Mesh,
+    buffers: &[buffer::Data],
+    gltf_mesh: &::gltf::Mesh,
+    transform: &glm::Mat4,
+    skin_joints: Option<&[u16]>,
+) -> Result<()> {
+    // Normals have to be transformed by the inverse transpose to stay
+    // perpendicular to non-uniformly scaled surfaces.
+    let normal_transform = glm::mat4_to_mat3(transform)
+        .try_inverse()
+        .unwrap_or_else(glm::Mat3::identity)
+        .transpose();
+
+    // Mirroring transforms turn counter-clockwise triangles clockwise, which
+    // would get them back-face culled. They also flip the bitangents.
+    let flip_winding = glm::mat4_to_mat3(transform).determinant() < 0.0;
+    let bitangent_sign = if flip_winding { -1.0 } else { 1.0 };
+
+    for primitive in gltf_mesh.primitives() {
+        if primitive.mode() != Mode::Triangles {
+            warn!(
+                mesh = gltf_mesh.name(),
+                mode = ?primitive.mode(),
+                "Skipping glTF primitive that isn't a triangle list"
+            );
+            continue;
+        }
+
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|b| &b[..]));
+
+        let positions = reader
+            .read_positions()
+            .ok_or_else(|| eyre!("glTF primitive has no vertex positions"))?;
+        let mut normals = reader.read_normals();
+        let mut tangents = reader.read_tangents();
+        let mut tex_coords = reader.read_tex_coords(0).map(|t| t.into_f32());
+        let mut colors = reader.read_colors(0).map(|c| c.into_rgb_f32());
+        let mut joints = reader.read_joints(0).map(|j| j.into_u16());
+        let mut weights = reader.read_weights(0).map(|w| w.into_f32());
+
+        let first_vertex = mesh.vertices.len() as u32;
+        for pos in positions {
+            let pos = transform * glm::vec4(pos[0], pos[1], pos[2], 1.0);
+            let normal = normals
+                .as_mut()
+                .and_then(Iterator::next)
+                .map(|n| (normal_transform * glm::Vec3::from(n)).normalize())
+                .unwrap_or_else(glm::Vec3::zeros);
+            let tex_coord = tex_coords
+                .as_mut()
+                .and_then(Iterator::next)
+                .unwrap_or_default();
+            let color = colors.as_mut().and_then(Iterator::next).unwrap_or([1.0; 3]);
+
+            let mut vertex = Vertex::new(pos.xyz(), color.into(), tex_coord.into(), normal);
+            if let Some([x, y, z, w]) = tangents.as_mut().and_then(Iterator::next) {
+                let tangent = (transform * glm::vec4(x, y, z, 0.0)).xyz().normalize();
+                vertex.tangent = glm::vec4(tangent.x, tangent.y, tangent.z, w * bitangent_sign);
+            }
+            mesh.vertices.push(vertex);
+
+            if mesh.is_skinned() {
+                let joints = joints.as_mut().and_then(Iterator::next);
+                let weights = weights.as_mut().and_then(Iterator::next);
+                mesh.skin_weights
+                    .push(match (skin_joints, joints, weights) {
+                        (Some(skin_joints), Some(joints), Some(weights)) => {
+                            convert_joint_weights(skin_joints, joints, weights)?
+                        }
+                        _ => JointWeights::default(),
+                    });
+            }
+        }
+        let vertex_count = mesh.vertices.len() as u32 - first_vertex;
+
+        // Non-indexed primitives draw their vertices in order
+        let mut indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..vertex_count).collect(),
+        };
+
+        if let Some(index) = indices.iter().find(|&&i| i >= vertex_count) {
+            return Err(eyre!(
+                "glTF primitive index {index} is out of range for {vertex_count} vertices"
+            ));
+        }
+
+        if flip_winding {
+            indices.chunks_exact_mut(3).for_each(|t| t.swap(1, 2));
+        }
+
+        mesh.submeshes.push(Submesh {
+            first_index: mesh.indices.len() as u32,
+            index_count: indices.len() as u32,
+            material: primitive.material().index(),
+        });
+        mesh.indices
+            .extend(indices.into_iter().map(|i| first_vertex + i));
+    }
+
+    Ok(())
+}
+
+/// Map a vertex's joints from its glTF skin's joints to the merged skeleton's,
+/// and normalize its weights so that they add up to 1.
+fn convert_joint_weights(
+    skin_joints: &[u16],
+    joints: [u16; 4],
+    weights: [f32; 4],
+) -> Result<JointWeights> {
+    let total: f32 = weights.iter().sum();
+    let mut joint_weights = JointWeights::default();
+
+    for (i, (&joint, &weight)) in joints.iter().zip(&weights).enumerate() {
+        // Unweighted joints can be anything, including out of range
+        if weight <= 0.0 {
+            continue;
+        }
+
+        joint_weights.joints[i] = *skin_joints.get(usize::from(joint)).ok_or_else(|| {
+            eyre!(
+                "glTF vertex joint {joint} is out of range for a skin with {} joints",
+                skin_joints.len()
+            )
+        })?;
+        joint_weights.weights[i] = weight / total;
+    }
+
+    Ok(joint_weights)
+}
+
+/// Convert the channels of a glTF animation that move joints into an
+/// [`AnimationClip`], or return `None` if none of them do.
+fn convert_animation(
+    animation: ::gltf::Animation,
+    buffers: &[buffer::Data],
+    node_joints: &AHashMap<usize, usize>,
+) -> Result<Option<AnimationClip>> {
+    let mut channels = Vec::new();
+    let mut skipped = 0;
+
+    for gltf_channel in animation.channels() {
+        let Some(&joint) = node_joints.get(&gltf_channel.target().node().index()) else {
+            skipped += 1;
+            continue;
+        };
+
+        let reader = gltf_channel.reader(|buffer| buffers.get(buffer.index()).map(|b| &b[..]));
+        let times: Vec<f32> = reader
+            .read_inputs()
+            .ok_or_else(|| eyre!("glTF animation sampler has no keyframe times"))?
+            .collect();
+        let (property, values): (_, Vec<_>) = match reader
+            .read_outputs()
+            .ok_or_else(|| eyre!("glTF animation sampler has no keyframe values"))?
+        {
+            ReadOutputs::Translations(values) => (
+                Property::Translation,
+                values.map(|[x, y, z]| glm::vec4(x, y, z, 0.0)).collect(),
+            ),
+            ReadOutputs::Rotations(values) => (
+                Property::Rotation,
+                values
+                    .into_f32()
+                    .map(|[x, y, z, w]| glm::vec4(x, y, z, w))
+                    .collect(),
+            ),
+            ReadOutputs::Scales(values) => (
+                Property::Scale,
+                values.map(|[x, y, z]| glm::vec4(x, y, z, 0.0)).collect(),
+            ),
+            // Morph targets aren't supported
+            ReadOutputs::MorphTargetWeights(_) => {
+                skipped += 1;
+                continue;
+            }
+        };
+
+        let interpolation = match gltf_channel.sampler().interpolation() {
+            GltfInterpolation::Step => Interpolation::Step,
+            GltfInterpolation::Linear => Interpolation::Linear,
+            GltfInterpolation::CubicSpline => Interpolation::CubicSpline,
+        };
+        let values_per_keyframe = match interpolation {
+            Interpolation::CubicSpline => 3,
+            _ => 1,
+        };
+        if values.len() != times.len() * values_per_keyframe {
+            return Err(eyre!(
+                "glTF animation channel has {} values for {} keyframes",
+                values.len(),
+                times.len()
+            ));
+        }
+
+        channels.push(Channel {
+            joint,
+            property,
+            interpolation,
+            times,
+            values,
+        });
+    }
+
+    if skipped > 0 {
+        warn!(
+            animation = animation.name(),
+            skipped, "Skipping glTF animation channels that don't move joints"
+        );
+    }
+    if channels.is_empty() {
+        return Ok(None);
+    }
+
+    let duration = channels
+        .iter()
+        .filter_map(|channel| channel.times.last().copied())
+        .fold(0.0, f32::max);
+
+    Ok(Some(AnimationClip {
+        name: animation.name().map(Into::into),
+        duration,
+        channels,
+    }))
+}
+
+fn convert_material(material: ::gltf::Material) -> Material {
+    let pbr = material.pbr_metallic_roughness();
+
+    Material {
+        name: material.name().map(Into::into),
+        base_color: pbr.base_color_factor(),
+        base_color_texture: pbr
+            .base_color_texture()
+            .map(|info| info.texture().source().index()),
+        normal_texture: material
+            .normal_texture()
+            .map(|info| info.texture().source().index()),
+        // Specular maps need the KHR_materials_specular extension
+        specular_texture: None,
+    }
+}
+
+/// The files of a glTF document's external buffers and images, relative to
+/// the document unless their URIs are absolute `file:` URIs.
+fn external_files<'a>(document: &'a Document) -> impl Iterator<Item = PathBuf> + 'a {
+    let buffer_uris = document
+        .buffers()
+        .filter_map(|buffer| match buffer.source() {
+            buffer::Source::Uri(uri) => Some(uri),
+            buffer::Source::Bin => None,
+        });
+    let image_uris = document.images().filter_map(|image| match image.source() {
+        image::Source::Uri { uri, .. } => Some(uri),
+        image::Source::View { .. } => None,
+    });
+
+    buffer_uris.chain(image_uris).filter_map(uri_path)
+}
+
+/// The path of the file a glTF URI refers to, decoded the same way
+/// `gltf::import()` decodes it, or `None` for embedded `data:` URIs.
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    if let Some(path) = uri
+        .strip_prefix("file://")
+        .or_else(|| uri.strip_prefix("file:"))
+    {
+        return Some(path.into());
+    }
+    if uri.contains(':') {
+        return None;
+    }
+
+    // Relative URIs are percent-encoded
+    let mut bytes = Vec::with_capacity(uri.len());
+    let mut rest = uri.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (byte, escaped) {
+            (b'%', Some(escaped)) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned().into())
+}
+
+/// Convert an image of any format supported by glTF to 8-bit RGBA.
+fn convert_image(image: image::Data) -> Result<Image> {
+    use image::Format;
+
+    let channels = match image.format {
+        Format::R8 | Format::R16 => 1,
+        Format::R8G8 | Format::R16G16 => 2,
+        Format::R8G8B8 | Format::R16G16B16 | Format::R32G32B32FLOAT => 3,
+        Format::R8G8B8A8 | Format::R16G16B16A16 | Format::R32G32B32A32FLOAT => 4,
+    };
+    let values: Vec<u8> = match image.format {
+        Format::R8 | Format::R8G8 | Format::R8G8B8 | Format::R8G8B8A8 => image.pixels,
+        Format::R16 | Format::R16G16 | Format::R16G16B16 | Format::R16G16B16A16 => image
+            .pixels
+            .chunks_exact(2)
+            .map(|c| (u16::from_ne_bytes([c[0], c[1]]) >> 8) as u8)
+            .collect(),
+        Format::R32G32B32FLOAT | Format::R32G32B32A32FLOAT => image
+            .pixels
+            .chunks_exact(4)
+            .map(|c| (f32::from_ne_bytes([c[0], c[1], c[2], c[3]]).clamp(0.0, 1.0) * 255.0) as u8)
+            .collect(),
+    };
+
+    if values.len() != (image.width * image.height) as usize * channels {
+        return Err(eyre!("glTF image data doesn't match its size and format"));
+    }
+
+    // Widen each pixel to RGBA, treating one or two channels as grayscale
+    let pixels = values
+        .chunks_exact(channels)
+        .flat_map(|c| match *c {
+            [r] => [r, r, r, 255],
+            [r, a] => [r, r, r, a],
+            [r, g, b] => [r, g, b, 255],
+            [r, g, b, a] => [r, g, b, a],
+            _ => unreachable!(),
+        })
+        .collect();
+
+    Ok(Image {
+        width: image.width,
+        height: image.height,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pack glTF JSON and a binary buffer into a `.glb` file.
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut bin = bin.to_vec();
+        bin.resize(bin.len().next_multiple_of(4), 0);
+
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut glb = Vec::with_capacity(length);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+        glb
+    }
+
+    /// A single triangle with normals, drawn by a child node that's scaled by
+    /// -2 along x inside a parent node that's moved up by 1 along z.
+    fn triangle_glb() -> Vec<u8> {
+        let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let normals: [f32; 9] = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+        let indices: [u16; 3] = [0, 1, 2];
+
+        let mut bin = Vec::new();
+        bin.extend(positions.iter().flat_map(|f| f.to_le_bytes()));
+        bin.extend(normals.iter().flat_map(|f| f.to_le_bytes()));
+        bin.extend(indices.iter().flat_map(|i| i.to_le_bytes()));
+
+        glb(
+            r#"{
+                "asset": { "version": "2.0" },
+                "scene": 0,
+                "scenes": [{ "nodes": [0] }],
+                "nodes": [
+                    { "translation": [0, 0, 1], "children": [1] },
+                    { "scale": [-2, 1, 1], "mesh": 0 }
+                ],
+                "meshes": [{
+                    "primitives": [{
+                        "attributes": { "POSITION": 0, "NORMAL": 1 },
+                        "indices": 2,
+                        "material": 0
+                    }]
+                }],
+                "materials": [{
+                    "name": "red",
+                    "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1] }
+                }],
+                "buffers": [{ "byteLength": 78 }],
+                "bufferViews": [
+                    { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                    { "buffer": 0, "byteOffset": 36, "byteLength": 36 },
+                    { "buffer": 0, "byteOffset": 72, "byteLength": 6 }
+                ],
+                "accessors": [
+                    { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                      "min": [0, 0, 0], "max": [1, 1, 0] },
+                    { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" },
+                    { "bufferView": 2, "componentType": 5123, "count": 3, "type": "SCALAR" }
+                ]
+            }"#,
+            &bin,
+        )
+    }
+
+    #[test]
+    fn loads_glb_with_node_transforms_and_materials() {
+        let (document, buffers, images) = ::gltf::import_slice(triangle_glb()).unwrap();
+        let mesh = convert_gltf(&document, &buffers, images).unwrap();
+
+        let positions: Vec<_> = mesh.vertices.iter().map(|v| v.pos).collect();
+        assert_eq!(
+            positions,
+            vec![
+                glm::vec3(0.0, 0.0, 1.0),
+                glm::vec3(-2.0, 0.0, 1.0),
+                glm::vec3(0.0, 1.0, 1.0)
+            ]
+        );
+
+        // Mirroring flips the winding order, but not the normals
+        assert_eq!(mesh.indices, vec![0, 2, 1]);
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|v| v.normal == glm::vec3(0.0, 0.0, 1.0)));
+
+        assert_eq!(
+            mesh.submeshes,
+            vec![Submesh {
+                first_index: 0,
+                index_count: 3,
+                material: Some(0),
+            }]
+        );
+        assert_eq!(mesh.materials[0].name.as_deref(), Some("red"));
+        assert_eq!(mesh.materials[0].base_color, [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    /// A triangle skinned to a hip joint and a knee joint, inside an armature
+    /// that's moved up by 1 along z. The skin lists the knee first, and the
+    /// hip rotates a quarter turn around z over one second.
+    fn skinned_glb() -> Vec<u8> {
+        let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let joints: [u8; 12] = [1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0];
+        let weights: [f32; 12] = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0];
+        let inverse_bind_matrices: [glm::Mat4; 2] = [
+            glm::translation(&glm::vec3(-1.0, 0.0, -1.0)),
+            glm::translation(&glm::vec3(0.0, 0.0, -1.0)),
+        ];
+        let times: [f32; 2] = [0.0, 1.0];
+        let rotations: [glm::Quat; 2] = [
+            glm::Quat::identity(),
+            glm::quat_angle_axis(std::f32::consts::FRAC_PI_2, &glm::Vec3::z()),
+        ];
+        let indices: [u16; 3] = [0, 1, 2];
+
+        let mut bin = Vec::new();
+        bin.extend(positions.iter().flat_map(|f| f.to_le_bytes()));
+        bin.extend(joints);
+        bin.extend(weights.iter().flat_map(|f| f.to_le_bytes()));
+        bin.extend(
+            inverse_bind_matrices
+                .iter()
+                .flat_map(|m| m.as_slice().iter().flat_map(|f| f.to_le_bytes())),
+        );
+        bin.extend(times.iter().flat_map(|f| f.to_le_bytes()));
+        bin.extend(
+            rotations
+                .iter()
+                .flat_map(|q| q.coords.iter().flat_map(|f| f.to_le_bytes())),
+        );
+        bin.extend(indices.iter().flat_map(|i| i.to_le_bytes()));
+
+        glb(
+            r#"{
+                "asset": { "version": "2.0" },
+                "scene": 0,
+                "scenes": [{ "nodes": [0] }],
+                "nodes": [
+                    { "name": "armature", "translation": [0, 0, 1], "children": [1, 3] },
+                    { "name": "hip", "children": [2] },
+                    { "name": "knee", "translation": [1, 0, 0] },
+                    { "translation": [5, 0, 0], "mesh": 0, "skin": 0 }
+                ],
+                "meshes": [{
+                    "primitives": [{
+                        "attributes": { "POSITION": 0, "JOINTS_0": 1, "WEIGHTS_0": 2 },
+                        "indices": 6
+                    }]
+                }],
+                "skins": [{ "joints": [2, 1], "inverseBindMatrices": 3 }],
+                "animations": [{
+                    "name": "swing",
+                    "channels": [{ "sampler": 0, "target": { "node": 1, "path": "rotation" } }],
+                    "samplers": [{ "input": 4, "output": 5, "interpolation": "LINEAR" }]
+                }],
+                "buffers": [{ "byteLength": 270 }],
+                "bufferViews": [
+                    { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                    { "buffer": 0, "byteOffset": 36, "byteLength": 12 },
+                    { "buffer": 0, "byteOffset": 48, "byteLength": 48 },
+                    { "buffer": 0, "byteOffset": 96, "byteLength": 128 },
+                    { "buffer": 0, "byteOffset": 224, "byteLength": 8 },
+                    { "buffer": 0, "byteOffset": 232, "byteLength": 32 },
+                    { "buffer": 0, "byteOffset": 264, "byteLength": 6 }
+                ],
+                "accessors": [
+                    { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                      "min": [0, 0, 0], "max": [1, 1, 0] },
+                    { "bufferView": 1, "componentType": 5121, "count": 3, "type": "VEC4" },
+                    { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC4" },
+                    { "bufferView": 3, "componentType": 5126, "count": 2, "type": "MAT4" },
+                    { "bufferView": 4, "componentType": 5126, "count": 2, "type": "SCALAR",
+                      "min": [0], "max": [1] },
+                    { "bufferView": 5, "componentType": 5126, "count": 2, "type": "VEC4" },
+                    { "bufferView": 6, "componentType": 5123, "count": 3, "type": "SCALAR" }
+                ]
+            }"#,
+            &bin,
+        )
+    }
+
+    #[test]
+    fn loads_skins_and_animations() {
+        let (document, buffers, images) = ::gltf::import_slice(skinned_glb()).unwrap();
+        let mesh = convert_gltf(&document, &buffers, images).unwrap();
+
+        // The mesh node's transform is ignored
+        assert_eq!(mesh.vertices[1].pos, glm::vec3(1.0, 0.0, 0.0));
+
+        // Joints are in hierarchy order, so the skin's joints are swapped
+        let skin = mesh.skin.as_ref().unwrap();
+        let names: Vec<_> = skin.joints.iter().map(|j| j.name.as_deref()).collect();
+        assert_eq!(names, vec![Some("hip"), Some("knee")]);
+        assert_eq!(skin.joints[1].parent, Some(0));
+        assert_eq!(
+            mesh.skin_weights,
+            vec![
+                JointWeights {
+                    joints: [0, 0, 0, 0],
+                    weights: [1.0, 0.0, 0.0, 0.0],
+                },
+                JointWeights {
+                    joints: [1, 0, 0, 0],
+                    weights: [1.0, 0.0, 0.0, 0.0],
+                },
+                JointWeights {
+                    joints: [1, 0, 0, 0],
+                    weights: [0.5, 0.5, 0.0, 0.0],
+                },
+            ]
+        );
+
+        // The inverse bind matrices undo the rest pose, armature included
+        for matrix in skin.joint_matrices(&skin.rest_pose()) {
+            assert!((matrix - glm::Mat4::identity()).abs().max() < 1e-6);
+        }
+
+        let clip = &mesh.animations[0];
+        assert_eq!(clip.name.as_deref(), Some("swing"));
+        assert_eq!(clip.duration, 1.0);
+        assert_eq!(clip.channels[0].joint, 0);
+        assert_eq!(clip.channels[0].property, Property::Rotation);
+    }
+
+    #[test]
+    fn converts_images_to_rgba8() {
+        let image = convert_image(image::Data {
+            pixels: vec![10, 20, 30, 40],
+            format: image::Format::R8G8,
+            width: 2,
+            height: 1,
+        })
+        .unwrap();
+
+        assert_eq!(image.pixels, vec![10, 10, 10, 20, 30, 30, 30, 40]);
+    }
+
+    #[test]
+    fn external_files_are_decoded_and_embedded_data_skipped() {
+        assert_eq!(
+            uri_path("textures/Wood%20Planks.png"),
+            Some(PathBuf::from("textures/Wood Planks.png"))
+        );
+        assert_eq!(
+            uri_path("file:///tmp/a.bin"),
+            Some(PathBuf::from("/tmp/a.bin"))
+        );
+        assert_eq!(uri_path("data:application/octet-stream;base64,AAAA"), None);
+
+        // Everything in a .glb file is embedded
+        let (document, _, _) = ::gltf::import_slice(triangle_glb()).unwrap();
+        assert_eq!(external_files(&document).count(), 0);
+    }
+}
@@ -0,0 +1,186 @@
+//! Generating simplified levels of detail for meshes, and choosing between
+//! them.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Mesh, Submesh};
+
+/// How many times fewer triangles each level of detail aims for than the one
+/// before it.
+const LOD_REDUCTION: u32 = 2;
+
+/// The most that a level of detail may stray from the full-detail mesh,
+/// relative to the size of the mesh.
+const MAX_LOD_ERROR: f32 = 0.05;
+
+/// Levels of detail that don't remove at least this fraction of the previous
+/// level's triangles aren't worth keeping.
+const MIN_LOD_SAVINGS: f32 = 0.1;
+
+/// How many pixels of error a level of detail may show on screen before a
+/// more detailed one is used instead.
+const MAX_PIXEL_ERROR: f32 = 1.0;
+
+/// A simplified version of a [`Mesh`], drawn in place of it when it's far
+/// away. It shares the mesh's vertices, but has its own indices.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Lod {
+    /// Ranges of [`Mesh::indices`], one for each of the mesh's submeshes, in
+    /// the same order and with the same materials.
+    pub submeshes: Vec<Submesh>,
+    /// How far the simplified surface strays from the full-detail one, relative
+    /// to the size of the mesh.
+    pub error: f32,
+}
+
+/// Generate up to `max_lods` levels of detail for a mesh, each with about half
+/// as many triangles as the last, and add them to [`Mesh::lods`]. The new
+/// indices are appended to [`Mesh::indices`].
+///
+/// Simplification collapses edges in order of quadric error. It won't collapse
+/// edges across UV seams or other attribute discontinuities, and each submesh
+/// is simplified separately without moving the borders between them, so
+/// nothing tears apart.
+pub(super) fn generate_lods(mesh: &mut Mesh, max_lods: usize) {
+    if mesh.submeshes.is_empty() || mesh.indices.is_empty() {
+        return;
+    }
+
+    let positions: Vec<[f32; 3]> = mesh.vertices.iter().map(|v| v.pos.into()).collect();
+    let options = if mesh.submeshes.len() > 1 {
+        meshopt::SimplifyOptions::LockBorder
+    } else {
+        meshopt::SimplifyOptions::None
+    };
+
+    let full_detail = mesh.submeshes.clone();
+    let mut previous_count: u32 = full_detail.iter().map(|s| s.index_count).sum();
+
+    // Each LOD is simplified from the full-detail mesh, so that its error is
+    // relative to that rather than to the previous LOD
+    for level in 1..=max_lods as u32 {
+        let reduction = LOD_REDUCTION.saturating_pow(level);
+        let mut lod = Lod {
+            submeshes: Vec::with_capacity(full_detail.len()),
+            error: 0.0,
+        };
+        let mut indices = Vec::new();
+
+        for submesh in &full_detail {
+            let range =
+                submesh.first_index as usize..(submesh.first_index + submesh.index_count) as usize;
+            let target_count = (submesh.index_count / 3 / reduction) as usize * 3;
+
+            let mut error = 0.0;
+            let mut simplified = meshopt::simplify_decoder(
+                &mesh.indices[range],
+                &positions,
+                target_count,
+                MAX_LOD_ERROR,
+                options,
+                Some(&mut error),
+            );
+            meshopt::optimize_vertex_cache_in_place(&mut simplified, mesh.vertices.len());
+
+            lod.submeshes.push(Submesh {
+                first_index: (mesh.indices.len() + indices.len()) as u32,
+                index_count: simplified.len() as u32,
+                material: submesh.material,
+            });
+            lod.error = lod.error.max(error);
+            indices.extend(simplified);
+        }
+
+        if (indices.len() as f32) > previous_count as f32 * (1.0 - MIN_LOD_SAVINGS) {
+            break;
+        }
+
+        previous_count = indices.len() as u32;
+        mesh.indices.extend(indices);
+        mesh.lods.push(lod);
+    }
+}
+
+/// Choose which level of detail to draw a mesh with, given the errors of its
+/// [`Lod`]s from most to least detailed, and how big the mesh is on screen in
+/// pixels. Returns 0 for the full-detail mesh, or `i + 1` for the `i`th LOD.
+pub fn select_lod<I>(lod_errors: I, screen_size: f32) -> usize
+where
+    I: IntoIterator<Item = f32>,
+{
+    lod_errors
+        .into_iter()
+        .take_while(|error| error * screen_size <= MAX_PIXEL_ERROR)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm as glm;
+
+    use super::*;
+    use crate::vertex::Vertex;
+
+    /// A flat, finely-tessellated square, which simplifies down to almost
+    /// nothing without any error.
+    fn grid(size: u32) -> Mesh {
+        let mut mesh = Mesh::default();
+        for y in 0..=size {
+            for x in 0..=size {
+                let (u, v) = (x as f32 / size as f32, y as f32 / size as f32);
+                mesh.vertices.push(Vertex::new(
+                    glm::vec3(u, v, 0.0),
+                    glm::vec3(1.0, 1.0, 1.0),
+                    glm::vec2(u, v),
+                    glm::Vec3::z(),
+                ));
+            }
+        }
+        for y in 0..size {
+            for x in 0..size {
+                let corner = y * (size + 1) + x;
+                let above = corner + size + 1;
+                mesh.indices
+                    .extend([corner, corner + 1, above + 1, corner, above + 1, above]);
+            }
+        }
+        mesh.submeshes.push(Submesh {
+            first_index: 0,
+            index_count: mesh.indices.len() as u32,
+            material: None,
+        });
+        mesh
+    }
+
+    #[test]
+    fn lods_get_smaller_and_keep_their_own_index_ranges() {
+        let mut mesh = grid(16);
+        let full_count = mesh.indices.len() as u32;
+
+        generate_lods(&mut mesh, 3);
+
+        assert!(!mesh.lods.is_empty());
+        let mut expected_first = full_count;
+        let mut previous_count = full_count;
+        for lod in &mesh.lods {
+            let submesh = lod.submeshes[0];
+            assert_eq!(submesh.first_index, expected_first);
+            assert!(submesh.index_count < previous_count);
+            assert!(lod.error <= MAX_LOD_ERROR);
+
+            expected_first += submesh.index_count;
+            previous_count = submesh.index_count;
+        }
+        assert_eq!(mesh.indices.len() as u32, expected_first);
+    }
+
+    #[test]
+    fn select_lod_uses_coarser_lods_for_smaller_meshes() {
+        let errors = [0.001, 0.01, 0.1];
+
+        assert_eq!(select_lod(errors, 10_000.0), 0);
+        assert_eq!(select_lod(errors, 500.0), 1);
+        assert_eq!(select_lod(errors, 50.0), 2);
+        assert_eq!(select_lod(errors, 5.0), 3);
+    }
+}
@@ -0,0 +1,230 @@
+//! Tools for loading models.
+
+mod gltf;
+mod lod;
+mod normals;
+mod obj;
+mod optimize;
+mod ply;
+pub mod primitives;
+mod stl;
+mod tangents;
+
+pub use lod::{select_lod, Lod};
+pub use normals::NormalGeneration;
+pub use obj::ObjError;
+
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+
+use ahash::AHashMap;
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    animation::{AnimationClip, Skin},
+    bounds::Bounds,
+    vertex::{JointWeights, Vertex},
+};
+
+/// The vertices and indices of a triangle mesh or point cloud, ready to be
+/// uploaded to the GPU, along with the materials to draw it with.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    /// Whether `indices` make up triangles or points.
+    pub topology: Topology,
+    /// Ranges of `indices` that are each drawn with a single material.
+    pub submeshes: Vec<Submesh>,
+    /// Simplified versions of the mesh for drawing from further away, from
+    /// most to least detailed. Their indices come after the submeshes'.
+    pub lods: Vec<Lod>,
+    /// Bounds around all of the vertices, in model space.
+    pub bounds: Bounds,
+    pub materials: Vec<Material>,
+    /// Images embedded in or referenced by the model file, for materials to
+    /// use as textures.
+    pub images: Vec<Image>,
+    /// The skeleton that moves the mesh, for skinned meshes.
+    pub skin: Option<Skin>,
+    /// Which joints of `skin` move each vertex, in the same order as
+    /// `vertices`. Empty unless the mesh is skinned.
+    pub skin_weights: Vec<JointWeights>,
+    /// Clips that animate `skin`.
+    pub animations: Vec<AnimationClip>,
+    /// Other files that the model file refers to, such as material libraries
+    /// and textures, which the mesh needs loading again if they change.
+    pub dependencies: Vec<PathBuf>,
+}
+
+impl Mesh {
+    pub fn is_skinned(&self) -> bool {
+        self.skin.is_some()
+    }
+}
+
+/// What the indices of a [`Mesh`] make up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Topology {
+    /// Every three indices make a triangle.
+    #[default]
+    Triangles,
+    /// Every index is a point on its own, as in a point cloud.
+    Points,
+}
+
+/// A range of a [`Mesh`]'s indices that is drawn with a single material.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Submesh {
+    pub first_index: u32,
+    pub index_count: u32,
+    /// Index into [`Mesh::materials`], or `None` to use a default material.
+    pub material: Option<usize>,
+}
+
+/// How the surface of a submesh looks.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Material {
+    pub name: Option<String>,
+    /// Linear RGBA color that the base color texture is multiplied by, or that
+    /// is used as-is if there is no texture.
+    pub base_color: [f32; 4],
+    /// Index into [`Mesh::images`].
+    pub base_color_texture: Option<usize>,
+    /// Tangent-space normal map. Index into [`Mesh::images`].
+    pub normal_texture: Option<usize>,
+    /// Index into [`Mesh::images`].
+    pub specular_texture: Option<usize>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: None,
+            base_color: [1.0; 4],
+            base_color_texture: None,
+            normal_texture: None,
+            specular_texture: None,
+        }
+    }
+}
+
+/// A decoded image, as tightly-packed rows of 8-bit RGBA pixels. Color
+/// textures are in the sRGB color space, while normal maps and the like are
+/// linear.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Options controlling how models are loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ModelOptions {
+    /// How to generate normals for vertices the model file doesn't give
+    /// normals for.
+    pub normals: NormalGeneration,
+    /// The most simplified levels of detail to generate for the mesh.
+    pub max_lods: usize,
+}
+
+impl Default for ModelOptions {
+    fn default() -> Self {
+        Self {
+            normals: NormalGeneration::default(),
+            max_lods: 3,
+        }
+    }
+}
+
+/// Load a model from an OBJ, glTF (`.gltf` or `.glb`), PLY, or STL file,
+/// depending on the file extension. Missing normals and tangents are
+/// generated, the mesh is reordered for faster drawing, levels of detail are
+/// generated, and finally the mesh's bounds are computed. Point clouds only
+/// have their bounds computed, since the rest only make sense for triangles.
+#[tracing::instrument(level = "DEBUG", skip_all, fields(path = ?path))]
+pub fn load_model<P>(path: P, options: &ModelOptions) -> Result<Mesh>
+where
+    P: AsRef<Path> + Debug,
+{
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+
+    let mut mesh = match extension.as_deref() {
+        Some("obj") => obj::load_obj(path)?,
+        Some("gltf" | "glb") => gltf::load_gltf(path)?,
+        Some("ply") => ply::load_ply(path)?,
+        Some("stl") => stl::load_stl(path)?,
+        _ => return Err(eyre!("Unsupported model format for {path:?}")),
+    };
+
+    // Empty vertex or index buffers can't be created, so there'd be nothing to
+    // draw anyway
+    if mesh.vertices.is_empty() || mesh.indices.is_empty() {
+        return Err(eyre!("Model {path:?} has nothing to draw"));
+    }
+
+    if mesh.topology == Topology::Triangles {
+        normals::generate_normals(&mut mesh, options.normals);
+        tangents::generate_tangents(&mut mesh);
+        optimize::optimize_mesh(&mut mesh);
+        lod::generate_lods(&mut mesh, options.max_lods);
+    }
+    mesh.bounds = Bounds::from_points(mesh.vertices.iter().map(|v| &v.pos));
+
+    debug!(
+        vertex_count = mesh.vertices.len(),
+        index_count = mesh.indices.len(),
+        topology = ?mesh.topology,
+        submesh_count = mesh.submeshes.len(),
+        lod_count = mesh.lods.len(),
+        material_count = mesh.materials.len(),
+        bounds = ?mesh.bounds,
+        "Successfully loaded model"
+    );
+
+    Ok(mesh)
+}
+
+/// Replace a mesh's vertices and indices with a list of triangle corners,
+/// where corner `i` was made from vertex `mesh.indices[i]`, merging identical
+/// corners. Skin weights stay with the vertices they belong to.
+fn weld_corners(mesh: &mut Mesh, corners: Vec<Vertex>) {
+    if mesh.skin_weights.is_empty() {
+        (mesh.vertices, mesh.indices) = dedup_vertices(corners);
+        return;
+    }
+
+    let weights = mesh.indices.iter().map(|&i| mesh.skin_weights[i as usize]);
+    let (vertices, indices) = dedup_vertices(corners.into_iter().zip(weights));
+    (mesh.vertices, mesh.skin_weights) = vertices.into_iter().unzip();
+    mesh.indices = indices;
+}
+
+/// Index a list of triangle corners, merging identical vertices. Each corner
+/// gets exactly one index, in order.
+fn dedup_vertices<V>(corners: impl IntoIterator<Item = V>) -> (Vec<V>, Vec<u32>)
+where
+    V: Copy + Eq + Hash,
+{
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut unique_vertices = AHashMap::new();
+
+    for vertex in corners {
+        let index = *unique_vertices.entry(vertex).or_insert_with(|| {
+            vertices.push(vertex);
+            vertices.len() as u32 - 1
+        });
+        indices.push(index);
+    }
+
+    (vertices, indices)
+}
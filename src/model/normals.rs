@@ -0,0 +1,228 @@
+//! Generating vertex normals for meshes that don't come with any.
+
+use std::{fmt, str::FromStr};
+
+use ahash::AHashMap;
+use nalgebra_glm as glm;
+
+use super::{weld_corners, Mesh};
+use crate::vertex::Vertex;
+
+/// How to generate normals for vertices that a model file doesn't give normals
+/// for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalGeneration {
+    /// Average the normals of the triangles around each vertex position,
+    /// weighted by the angle of each triangle's corner there.
+    #[default]
+    Smooth,
+    /// Give each triangle its own vertices, with the triangle's normal.
+    Flat,
+}
+
+impl FromStr for NormalGeneration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "smooth" => Ok(Self::Smooth),
+            "flat" => Ok(Self::Flat),
+            _ => Err(format!(
+                "Unknown normal generation {s:?}, expected one of: smooth, flat"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for NormalGeneration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Smooth => "smooth",
+            Self::Flat => "flat",
+        })
+    }
+}
+
+/// Fill in the normals of any vertices whose normal is zero, which is how the
+/// loaders mark missing normals. Vertices that already have normals are left
+/// alone, and every submesh keeps its index range.
+pub(super) fn generate_normals(mesh: &mut Mesh, mode: NormalGeneration) {
+    if !mesh.vertices.iter().any(is_missing_normal) {
+        return;
+    }
+
+    match mode {
+        NormalGeneration::Smooth => generate_smooth_normals(mesh),
+        NormalGeneration::Flat => generate_flat_normals(mesh),
+    }
+}
+
+fn is_missing_normal(vertex: &Vertex) -> bool {
+    vertex.normal == glm::Vec3::zeros()
+}
+
+/// Vertices that only differ by their texture coordinates, like those along a
+/// UV seam, should still get the same normal, so normals are accumulated by
+/// position.
+fn position_key(vertex: &Vertex) -> [u32; 3] {
+    [
+        vertex.pos.x.to_bits(),
+        vertex.pos.y.to_bits(),
+        vertex.pos.z.to_bits(),
+    ]
+}
+
+/// The unit normal of a triangle, following the counter-clockwise winding
+/// order, or zero if the triangle is degenerate.
+fn face_normal(a: &glm::Vec3, b: &glm::Vec3, c: &glm::Vec3) -> glm::Vec3 {
+    let normal = (b - a).cross(&(c - a));
+    if normal.norm_squared() > 0.0 {
+        normal.normalize()
+    } else {
+        glm::Vec3::zeros()
+    }
+}
+
+/// The angle at corner `a` of the triangle `abc`, in radians.
+fn corner_angle(a: &glm::Vec3, b: &glm::Vec3, c: &glm::Vec3) -> f32 {
+    let (ab, ac) = (b - a, c - a);
+    if ab.norm_squared() > 0.0 && ac.norm_squared() > 0.0 {
+        glm::angle(&ab, &ac)
+    } else {
+        0.0
+    }
+}
+
+fn generate_smooth_normals(mesh: &mut Mesh) {
+    let mut sums: AHashMap<[u32; 3], glm::Vec3> = AHashMap::new();
+
+    for triangle in mesh.indices.chunks_exact(3) {
+        let corners = [0, 1, 2].map(|k| mesh.vertices[triangle[k] as usize]);
+        let [a, b, c] = corners.map(|v| v.pos);
+        let normal = face_normal(&a, &b, &c);
+
+        let angles = [
+            corner_angle(&a, &b, &c),
+            corner_angle(&b, &c, &a),
+            corner_angle(&c, &a, &b),
+        ];
+        for (vertex, angle) in corners.iter().zip(angles) {
+            *sums.entry(position_key(vertex)).or_default() += normal * angle;
+        }
+    }
+
+    for vertex in mesh.vertices.iter_mut().filter(|v| is_missing_normal(v)) {
+        if let Some(sum) = sums.get(&position_key(vertex)) {
+            if sum.norm_squared() > 0.0 {
+                vertex.normal = sum.normalize();
+            }
+        }
+    }
+}
+
+fn generate_flat_normals(mesh: &mut Mesh) {
+    let corners = mesh
+        .indices
+        .chunks_exact(3)
+        .flat_map(|triangle| {
+            let mut corners = [0, 1, 2].map(|k| mesh.vertices[triangle[k] as usize]);
+
+            if corners.iter().any(is_missing_normal) {
+                let [a, b, c] = corners.map(|v| v.pos);
+                let normal = face_normal(&a, &b, &c);
+                corners.iter_mut().for_each(|v| v.normal = normal);
+            }
+
+            corners
+        })
+        .collect();
+
+    // Every index maps to exactly one new index, so the submeshes' index
+    // ranges stay the same
+    weld_corners(mesh, corners);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(x: f32, y: f32, z: f32, u: f32) -> Vertex {
+        Vertex::new(
+            glm::vec3(x, y, z),
+            glm::vec3(1.0, 1.0, 1.0),
+            glm::vec2(u, 0.0),
+            glm::Vec3::zeros(),
+        )
+    }
+
+    /// Two triangles meeting at a right angle along the y-axis, one facing +z
+    /// and the other facing +x. The shared edge is split by a UV seam.
+    fn folded_quad() -> Mesh {
+        Mesh {
+            vertices: vec![
+                vertex(0.0, 0.0, 0.0, 0.0),
+                vertex(1.0, 0.0, 0.0, 0.0),
+                vertex(0.0, 1.0, 0.0, 0.0),
+                vertex(0.0, 0.0, 0.0, 1.0),
+                vertex(0.0, 1.0, 0.0, 1.0),
+                vertex(0.0, 0.0, 1.0, 1.0),
+            ],
+            indices: vec![0, 1, 2, 3, 4, 5],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn smooth_normals_are_shared_across_uv_seams() {
+        let mut mesh = folded_quad();
+        generate_normals(&mut mesh, NormalGeneration::Smooth);
+
+        // Both triangles have a right angle at the origin, so the normal there
+        // is an even blend of the two
+        let diagonal = glm::vec3(1.0, 0.0, 1.0).normalize();
+        assert!((mesh.vertices[0].normal - diagonal).norm() < 1e-5);
+        assert_eq!(mesh.vertices[0].normal, mesh.vertices[3].normal);
+        assert!((mesh.vertices[1].normal - glm::Vec3::z()).norm() < 1e-5);
+        assert!((mesh.vertices[5].normal - glm::Vec3::x()).norm() < 1e-5);
+    }
+
+    #[test]
+    fn flat_normals_split_vertices_between_faces() {
+        let mut mesh = Mesh {
+            vertices: vec![
+                vertex(0.0, 0.0, 0.0, 0.0),
+                vertex(1.0, 0.0, 0.0, 0.0),
+                vertex(0.0, 1.0, 0.0, 0.0),
+                vertex(0.0, 0.0, 1.0, 0.0),
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3],
+            ..Default::default()
+        };
+
+        generate_normals(&mut mesh, NormalGeneration::Flat);
+
+        // The two shared corners are duplicated, one copy for each face
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 3, 4, 5]);
+        assert!(mesh.vertices[..3]
+            .iter()
+            .all(|v| (v.normal - glm::Vec3::z()).norm() < 1e-5));
+        assert!(mesh.vertices[3..]
+            .iter()
+            .all(|v| (v.normal - glm::Vec3::x()).norm() < 1e-5));
+    }
+
+    #[test]
+    fn existing_normals_are_kept() {
+        let mut mesh = folded_quad();
+        for vertex in &mut mesh.vertices {
+            vertex.normal = glm::Vec3::y();
+        }
+        let before = mesh.clone();
+
+        generate_normals(&mut mesh, NormalGeneration::Flat);
+
+        assert_eq!(mesh.vertices, before.vertices);
+        assert_eq!(mesh.indices, before.indices);
+    }
+}
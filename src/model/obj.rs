@@ -0,0 +1,516 @@
+//! Loading Wavefront OBJ models, along with their MTL materials.
+
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use ahash::AHashMap;
+use nalgebra_glm as glm;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use super::{Image, Material, Mesh, Submesh};
+use crate::{bounds::Aabb, vertex::Vertex};
+
+/// For when an OBJ file can't be turned into a mesh.
+#[derive(Debug, Error)]
+pub enum ObjError {
+    #[error("Error parsing OBJ file {path:?}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: tobj::LoadError,
+    },
+    /// Some of a model's vertices have an attribute and others don't, so the
+    /// attribute can't be matched up with the vertices.
+    #[error("OBJ model {model:?} in {path:?} only has {attribute} for some of its vertices")]
+    PartialAttribute {
+        path: PathBuf,
+        model: String,
+        attribute: &'static str,
+    },
+}
+
+/// Load a model from an OBJ file, along with the materials in any MTL files
+/// it refers to.
+///
+/// Each model in the OBJ file becomes its own [`Submesh`], but they all share
+/// a single set of deduplicated vertices. Texture paths in MTL files are
+/// resolved relative to the OBJ file.
+///
+/// Vertex colors are read if the file has them, and are white otherwise.
+/// Models without texture coordinates get them from a planar projection, so
+/// that a texture still shows up on them.
+pub(super) fn load_obj(path: &Path) -> Result<Mesh, ObjError> {
+    let parse_error = |source| ObjError::Parse {
+        path: path.to_owned(),
+        source,
+    };
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let file = File::open(path).map_err(|_| parse_error(tobj::LoadError::OpenFileFailed))?;
+
+    // Load MTL files the same way tobj::load_obj() does, but keep track of
+    // them so that the mesh can be reloaded when they change
+    let mtl_paths = RefCell::new(Vec::new());
+    let (models, materials) = tobj::load_obj_buf(
+        &mut BufReader::new(file),
+        &tobj::LoadOptions {
+            single_index: true,
+            triangulate: true,
+            ..Default::default()
+        },
+        |mtl_path| {
+            let mtl_path = base_dir.join(mtl_path);
+            let result = tobj::load_mtl(&mtl_path);
+            mtl_paths.borrow_mut().push(mtl_path);
+            result
+        },
+    )
+    .map_err(parse_error)?;
+
+    // Missing or broken MTL files shouldn't stop the geometry from loading
+    let materials = materials.unwrap_or_else(|e| {
+        warn!(?path, "Failed to load OBJ materials, using defaults: {e}");
+        Vec::new()
+    });
+
+    let mut images = TextureImages::new(base_dir);
+    let mut mesh = Mesh {
+        materials: materials
+            .iter()
+            .map(|m| convert_material(m, &mut images))
+            .collect(),
+        images: Vec::new(),
+        ..Default::default()
+    };
+    mesh.images = images.images;
+    mesh.dependencies = mtl_paths
+        .into_inner()
+        .into_iter()
+        .chain(images.paths)
+        .collect();
+
+    // Shared by every model, so that models next to each other get texture
+    // coordinates that line up
+    let projection = PlanarProjection::new(
+        models
+            .iter()
+            .flat_map(|model| model.mesh.positions.chunks_exact(3))
+            .map(|pos| glm::vec3(pos[0], pos[1], pos[2])),
+    );
+
+    let mut unique_vertices = AHashMap::new();
+
+    for model in &models {
+        let first_index = mesh.indices.len() as u32;
+
+        let partial = |attribute| ObjError::PartialAttribute {
+            path: path.to_owned(),
+            model: model.name.clone(),
+            attribute,
+        };
+        let has_tex_coords = has_attribute(model, &model.mesh.texcoords, 2)
+            .ok_or_else(|| partial("texture coordinates"))?;
+        let has_normals =
+            has_attribute(model, &model.mesh.normals, 3).ok_or_else(|| partial("normals"))?;
+        let has_colors = has_attribute(model, &model.mesh.vertex_color, 3)
+            .ok_or_else(|| partial("vertex colors"))?;
+
+        if !has_tex_coords {
+            debug!(
+                ?path,
+                model = model.name,
+                "Generating planar texture coordinates"
+            );
+        }
+
+        for &index in &model.mesh.indices {
+            let index = index as usize;
+            let vec3 = |values: &[f32]| {
+                glm::vec3(
+                    values[3 * index],
+                    values[3 * index + 1],
+                    values[3 * index + 2],
+                )
+            };
+
+            let pos = vec3(&model.mesh.positions);
+            let color = if has_colors {
+                vec3(&model.mesh.vertex_color)
+            } else {
+                glm::vec3(1.0, 1.0, 1.0)
+            };
+            let tex_coord = if has_tex_coords {
+                glm::vec2(
+                    model.mesh.texcoords[2 * index],
+                    1.0 - model.mesh.texcoords[2 * index + 1],
+                )
+            } else {
+                projection.tex_coord(&pos)
+            };
+            let normal = if has_normals {
+                vec3(&model.mesh.normals)
+            } else {
+                glm::Vec3::zeros()
+            };
+
+            let vertex = Vertex::new(pos, color, tex_coord, normal);
+
+            if let Some(index) = unique_vertices.get(&vertex) {
+                mesh.indices.push(*index as u32);
+            } else {
+                let index = mesh.vertices.len();
+                unique_vertices.insert(vertex, index);
+                mesh.vertices.push(vertex);
+                mesh.indices.push(index as u32);
+            }
+        }
+
+        mesh.submeshes.push(Submesh {
+            first_index,
+            index_count: mesh.indices.len() as u32 - first_index,
+            material: model
+                .mesh
+                .material_id
+                .filter(|&id| id < mesh.materials.len()),
+        });
+    }
+
+    Ok(mesh)
+}
+
+/// Whether every vertex of a model has an attribute with `size` components,
+/// or `None` if only some of them do.
+fn has_attribute(model: &tobj::Model, values: &[f32], size: usize) -> Option<bool> {
+    let vertex_count = model.mesh.positions.len() / 3;
+    match values.len() {
+        0 => Some(false),
+        len if len == vertex_count * size => Some(true),
+        _ => None,
+    }
+}
+
+/// Projects positions onto the plane of the two longest sides of their
+/// bounding box, stretched to fit texture coordinates from 0 to 1.
+struct PlanarProjection {
+    bounds: Aabb,
+    axes: [usize; 2],
+}
+
+impl PlanarProjection {
+    fn new(positions: impl IntoIterator<Item = glm::Vec3>) -> Self {
+        let positions: Vec<_> = positions.into_iter().collect();
+        let bounds = Aabb::from_points(&positions);
+
+        // Face the plane along the shortest side
+        let size = bounds.size();
+        let normal_axis = size.imin();
+        let axes = match normal_axis {
+            0 => [2, 1],
+            1 => [0, 2],
+            _ => [0, 1],
+        };
+
+        Self { bounds, axes }
+    }
+
+    fn tex_coord(&self, pos: &glm::Vec3) -> glm::Vec2 {
+        let size = self.bounds.size();
+        let [u, v] = self.axes.map(|axis| {
+            if size[axis] > 0.0 {
+                (pos[axis] - self.bounds.min[axis]) / size[axis]
+            } else {
+                0.0
+            }
+        });
+        glm::vec2(u, 1.0 - v)
+    }
+}
+
+fn convert_material(material: &tobj::Material, images: &mut TextureImages) -> Material {
+    let base_color_texture = images.load(&material.diffuse_texture);
+
+    // A diffuse texture replaces the diffuse color, rather than being tinted
+    // by it, because plenty of MTL files leave the color at black.
+    let [r, g, b] = if base_color_texture.is_some() {
+        [1.0; 3]
+    } else {
+        material.diffuse
+    };
+
+    Material {
+        name: Some(material.name.clone()),
+        base_color: [r, g, b, material.dissolve],
+        base_color_texture,
+        // Nothing draws with normal or specular maps yet, so there's no point
+        // decoding them
+        normal_texture: None,
+        specular_texture: None,
+    }
+}
+
+/// The file name in an MTL texture statement, after any options like
+/// `-bm 0.5` or `-s 1 1 1`. File names can contain spaces, so everything
+/// after the options is part of it.
+fn texture_statement_path(statement: &str) -> Option<&str> {
+    let mut rest = statement.trim_start();
+    while rest.starts_with('-') {
+        let (option, after) = split_word(rest);
+        // How many arguments each option takes. Offsets, scales, and
+        // turbulence take between one and three numbers.
+        let (min_args, max_args) = match option {
+            "-blendu" | "-blendv" | "-bm" | "-boost" | "-cc" | "-clamp" | "-imfchan"
+            | "-texres" | "-type" => (1, 1),
+            "-mm" => (2, 2),
+            "-o" | "-s" | "-t" => (1, 3),
+            _ => break,
+        };
+
+        rest = after;
+        for i in 0..max_args {
+            let (arg, after) = split_word(rest);
+            if i >= min_args && arg.parse::<f32>().is_err() {
+                break;
+            }
+            rest = after;
+        }
+    }
+
+    let path = rest.trim();
+    (!path.is_empty()).then_some(path)
+}
+
+/// Split the first whitespace-separated word off `s`, returning the word and
+/// what's left after it with leading whitespace removed.
+fn split_word(s: &str) -> (&str, &str) {
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    (&s[..end], s[end..].trim_start())
+}
+
+/// Decodes the texture images that MTL materials refer to, loading each file
+/// only once.
+struct TextureImages<'a> {
+    base_dir: &'a Path,
+    images: Vec<Image>,
+    indices: AHashMap<PathBuf, usize>,
+    /// Every texture file the materials refer to, including ones that failed
+    /// to load.
+    paths: Vec<PathBuf>,
+}
+
+impl<'a> TextureImages<'a> {
+    fn new(base_dir: &'a Path) -> Self {
+        Self {
+            base_dir,
+            images: Vec::new(),
+            indices: AHashMap::new(),
+            paths: Vec::new(),
+        }
+    }
+
+    /// Load the texture given by an MTL texture statement, if there is one.
+    /// Returns the index of the image in `images`.
+    fn load(&mut self, texture: &str) -> Option<usize> {
+        let path = self.base_dir.join(texture_statement_path(texture)?);
+
+        if let Some(&index) = self.indices.get(&path) {
+            return Some(index);
+        }
+        if !self.paths.contains(&path) {
+            self.paths.push(path.clone());
+        }
+
+        let image = match ::image::open(&path) {
+            Ok(image) => image.into_rgba8(),
+            Err(e) => {
+                warn!(?path, "Failed to load OBJ texture: {e}");
+                return None;
+            }
+        };
+
+        let index = self.images.len();
+        self.images.push(Image {
+            width: image.width(),
+            height: image.height(),
+            pixels: image.into_raw(),
+        });
+        self.indices.insert(path, index);
+
+        Some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    const TWO_MATERIAL_OBJ: &str = "\
+mtllib two.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+vt 0 0
+vt 1 0
+vt 0 1
+vt 1 1
+o first
+usemtl textured
+f 1/1 2/2 3/3
+o second
+usemtl plain
+f 2/2 4/4 3/3
+";
+
+    const TWO_MATERIAL_MTL: &str = "\
+newmtl textured
+Kd 0 0 0
+map_Kd -bm 1.0 textures/pixel.png
+newmtl plain
+Kd 0.5 0.25 1.0
+d 0.5
+";
+
+    #[test]
+    fn each_model_keeps_its_index_range_and_material() {
+        let dir = std::env::temp_dir().join(format!("vk-tut-obj-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("textures")).unwrap();
+        fs::write(dir.join("two.obj"), TWO_MATERIAL_OBJ).unwrap();
+        fs::write(dir.join("two.mtl"), TWO_MATERIAL_MTL).unwrap();
+        ::image::RgbaImage::from_pixel(1, 1, ::image::Rgba([1, 2, 3, 4]))
+            .save(dir.join("textures/pixel.png"))
+            .unwrap();
+
+        let mesh = load_obj(&dir.join("two.obj"));
+        fs::remove_dir_all(&dir).unwrap();
+        let mesh = mesh.unwrap();
+
+        // The two triangles share two of their vertices
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(
+            mesh.submeshes,
+            vec![
+                Submesh {
+                    first_index: 0,
+                    index_count: 3,
+                    material: Some(0),
+                },
+                Submesh {
+                    first_index: 3,
+                    index_count: 3,
+                    material: Some(1),
+                },
+            ]
+        );
+
+        assert_eq!(mesh.materials[0].base_color, [1.0; 4]);
+        assert_eq!(mesh.materials[0].base_color_texture, Some(0));
+        assert_eq!(mesh.images[0].pixels, vec![1, 2, 3, 4]);
+
+        assert_eq!(mesh.materials[1].name.as_deref(), Some("plain"));
+        assert_eq!(mesh.materials[1].base_color, [0.5, 0.25, 1.0, 0.5]);
+        assert_eq!(mesh.materials[1].base_color_texture, None);
+
+        assert_eq!(
+            mesh.dependencies,
+            vec![dir.join("two.mtl"), dir.join("textures/pixel.png")]
+        );
+    }
+
+    /// Write an OBJ file to a temporary directory and load it.
+    fn load_obj_str(name: &str, obj: &str) -> Result<Mesh, ObjError> {
+        let path = std::env::temp_dir().join(format!("vk-tut-{}-{name}", std::process::id()));
+        fs::write(&path, obj).unwrap();
+
+        let mesh = load_obj(&path);
+        fs::remove_file(&path).unwrap();
+        mesh
+    }
+
+    #[test]
+    fn models_without_faces_are_rejected() {
+        let path = std::env::temp_dir().join(format!("vk-tut-{}-no-faces.obj", std::process::id()));
+        fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\n").unwrap();
+
+        let result = crate::model::load_model(&path, &Default::default());
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn vertex_colors_are_read_and_missing_uvs_are_generated() {
+        let mesh = load_obj_str(
+            "colors.obj",
+            "\
+v 0 0 0 1 0 0
+v 2 0 0 0 1 0
+v 0 1 0 0 0 1
+f 1 2 3
+",
+        )
+        .unwrap();
+
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.vertices[0].color, glm::vec3(1.0, 0.0, 0.0));
+        assert_eq!(mesh.vertices[2].color, glm::vec3(0.0, 0.0, 1.0));
+
+        // Projected onto the xy plane, with v flipped like loaded coordinates
+        assert_eq!(mesh.vertices[0].tex_coord, glm::vec2(0.0, 1.0));
+        assert_eq!(mesh.vertices[1].tex_coord, glm::vec2(1.0, 1.0));
+        assert_eq!(mesh.vertices[2].tex_coord, glm::vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        let error = load_obj_str("bad-face.obj", "v 0 0 0\nf 1 2 3\n").unwrap_err();
+        assert!(matches!(
+            error,
+            ObjError::Parse {
+                source: tobj::LoadError::FaceVertexOutOfBounds,
+                ..
+            }
+        ));
+
+        let error = load_obj_str(
+            "partial-uvs.obj",
+            "\
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+vt 0 0
+vt 1 0
+vt 0 1
+f 1/1 2/2 3/3
+f 2 4 3
+",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            ObjError::PartialAttribute {
+                attribute: "texture coordinates",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn texture_options_are_skipped_and_spaces_kept() {
+        assert_eq!(
+            texture_statement_path("-bm 1.0 textures/pixel.png"),
+            Some("textures/pixel.png")
+        );
+        assert_eq!(
+            texture_statement_path("-s 2 2 -o 0.5 -clamp on my textures/wood grain.png"),
+            Some("my textures/wood grain.png")
+        );
+        assert_eq!(texture_statement_path("  wood.png "), Some("wood.png"));
+        assert_eq!(texture_statement_path("-bm 1.0"), None);
+    }
+}
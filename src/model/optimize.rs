@@ -0,0 +1,198 @@
+//! Reordering meshes so that the GPU draws them faster, using
+//! [meshoptimizer](https://github.com/zeux/meshoptimizer).
+
+use super::Mesh;
+
+/// How much worse the overdraw optimizer may make vertex cache efficiency, in
+/// exchange for less overdraw. 1.05 allows up to 5% more vertex shader
+/// invocations.
+const OVERDRAW_THRESHOLD: f32 = 1.05;
+
+/// Reorder a mesh's triangles and vertices for the GPU, without changing what
+/// it looks like:
+///
+/// 1. Triangles are reordered to make good use of the post-transform vertex
+///    cache, and then to draw front-most triangles first, reducing overdraw.
+///    This happens within each submesh, so their index ranges stay the same.
+/// 2. Vertices are reordered into the order that triangles first use them,
+///    so that vertex fetches are close together in memory. Any vertices that
+///    no triangle uses are dropped.
+pub(super) fn optimize_mesh(mesh: &mut Mesh) {
+    if mesh.indices.is_empty() {
+        return;
+    }
+
+    let vertex_count = mesh.vertices.len();
+    let positions: Vec<[f32; 3]> = mesh.vertices.iter().map(|v| v.pos.into()).collect();
+
+    let whole_mesh = 0..mesh.indices.len() as u32;
+    let ranges: Vec<_> = if mesh.submeshes.is_empty() {
+        vec![whole_mesh]
+    } else {
+        mesh.submeshes
+            .iter()
+            .map(|s| s.first_index..s.first_index + s.index_count)
+            .collect()
+    };
+
+    for range in ranges {
+        let indices = &mut mesh.indices[range.start as usize..range.end as usize];
+        meshopt::optimize_vertex_cache_in_place(indices, vertex_count);
+        meshopt::optimize_overdraw_in_place_decoder(indices, &positions, OVERDRAW_THRESHOLD);
+    }
+
+    // Work out the new order of the vertices once, and move the vertices and
+    // their skin weights the same way. The `meshopt` wrapper for this cuts the
+    // remap table short whenever some vertices are unused, so call it directly.
+    let mut remap = vec![0u32; vertex_count];
+    let used_count = unsafe {
+        meshopt::ffi::meshopt_optimizeVertexFetchRemap(
+            remap.as_mut_ptr(),
+            mesh.indices.as_ptr(),
+            mesh.indices.len(),
+            vertex_count,
+        )
+    };
+
+    for index in &mut mesh.indices {
+        *index = remap[*index as usize];
+    }
+    mesh.vertices = remap_vertices(&mesh.vertices, &remap, used_count);
+    if !mesh.skin_weights.is_empty() {
+        mesh.skin_weights = remap_vertices(&mesh.skin_weights, &remap, used_count);
+    }
+}
+
+/// Move per-vertex data into the order given by a remap table, which maps old
+/// vertex indices to new ones, or to `u32::MAX` for vertices that are dropped.
+fn remap_vertices<T: Copy>(vertices: &[T], remap: &[u32], new_count: usize) -> Vec<T> {
+    let Some(&first) = vertices.first() else {
+        return Vec::new();
+    };
+    let mut remapped = vec![first; new_count];
+    for (&new_index, &vertex) in remap.iter().zip(vertices) {
+        if new_index != u32::MAX {
+            remapped[new_index as usize] = vertex;
+        }
+    }
+    remapped
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm as glm;
+
+    use super::*;
+    use crate::{
+        model::Submesh,
+        vertex::{JointWeights, Vertex},
+    };
+
+    fn vertex(x: f32, y: f32) -> Vertex {
+        Vertex::new(
+            glm::vec3(x, y, 0.0),
+            glm::vec3(1.0, 1.0, 1.0),
+            glm::vec2(x, y),
+            glm::Vec3::z(),
+        )
+    }
+
+    /// The triangles of a mesh, as sets of vertex positions.
+    fn triangles(mesh: &Mesh, range: std::ops::Range<usize>) -> Vec<[[u32; 3]; 3]> {
+        let mut triangles: Vec<_> = mesh.indices[range]
+            .chunks_exact(3)
+            .map(|t| {
+                let mut corners = [0, 1, 2].map(|k| {
+                    let pos = mesh.vertices[t[k] as usize].pos;
+                    [pos.x.to_bits(), pos.y.to_bits(), pos.z.to_bits()]
+                });
+                // Rotating a triangle's corners doesn't change it
+                let first = (0..3).min_by_key(|&k| corners[k]).unwrap();
+                corners.rotate_left(first);
+                corners
+            })
+            .collect();
+        triangles.sort();
+        triangles
+    }
+
+    #[test]
+    fn keeps_submesh_triangles_and_drops_unused_vertices() {
+        // A 3x3 grid of vertices, plus one that no triangle uses
+        let mut vertices: Vec<_> = (0..9)
+            .map(|i| vertex((i % 3) as f32, (i / 3) as f32))
+            .collect();
+        vertices.insert(4, vertex(10.0, 10.0));
+        let grid = |i: u32| if i >= 4 { i + 1 } else { i };
+
+        // The bottom-left corners of the four quads in the grid
+        let quads = [0, 1, 3, 4].map(|corner| {
+            [
+                corner,
+                corner + 1,
+                corner + 4,
+                corner,
+                corner + 4,
+                corner + 3,
+            ]
+            .map(grid)
+        });
+        let mut mesh = Mesh {
+            vertices,
+            indices: quads.concat(),
+            submeshes: vec![
+                Submesh {
+                    first_index: 0,
+                    index_count: 12,
+                    material: Some(0),
+                },
+                Submesh {
+                    first_index: 12,
+                    index_count: 12,
+                    material: Some(1),
+                },
+            ],
+            ..Default::default()
+        };
+        let before = mesh.clone();
+
+        optimize_mesh(&mut mesh);
+
+        assert_eq!(mesh.vertices.len(), 9);
+        assert_eq!(mesh.submeshes, before.submeshes);
+        assert_eq!(triangles(&mesh, 0..12), triangles(&before, 0..12));
+        assert_eq!(triangles(&mesh, 12..24), triangles(&before, 12..24));
+
+        // Vertices are in the order they're first used
+        let mut seen = 0;
+        for &index in &mesh.indices {
+            assert!(index <= seen);
+            seen = seen.max(index + 1);
+        }
+    }
+
+    #[test]
+    fn skin_weights_follow_their_vertices() {
+        let vertices: Vec<_> = (0..6).map(|i| vertex(i as f32, (i % 2) as f32)).collect();
+        let skin_weights = (0..6)
+            .map(|i| JointWeights {
+                joints: [i, 0, 0, 0],
+                weights: [1.0, 0.0, 0.0, 0.0],
+            })
+            .collect();
+        let mut mesh = Mesh {
+            vertices,
+            // Uses the vertices backwards, and never uses vertex 2
+            indices: vec![5, 4, 3, 3, 1, 0],
+            skin_weights,
+            ..Default::default()
+        };
+
+        optimize_mesh(&mut mesh);
+
+        assert_eq!(mesh.skin_weights.len(), mesh.vertices.len());
+        for (vertex, weights) in mesh.vertices.iter().zip(&mesh.skin_weights) {
+            assert_eq!(vertex.pos.x, weights.joints[0] as f32);
+        }
+    }
+}
@@ -0,0 +1,574 @@
+//! Loading Stanford PLY models, in either the ASCII or binary encodings.
+//!
+//! PLY files are mostly used for scanned data, so a file without any faces,
+//! including one with an empty `face` element, is loaded as a point cloud
+//! rather than rejected.
+
+use std::{fs, path::Path, str::SplitAsciiWhitespace};
+
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use nalgebra_glm as glm;
+
+use super::{Mesh, Topology};
+use crate::vertex::Vertex;
+
+/// Load a model from a PLY file.
+///
+/// Vertex positions, normals, colors, and texture coordinates are read from
+/// the `vertex` element, and polygons from the `face` element are split into
+/// triangles. Any other elements are skipped.
+pub(super) fn load_ply(path: &Path) -> Result<Mesh> {
+    let bytes = fs::read(path)?;
+    parse_ply(&bytes).wrap_err_with(|| format!("Error parsing PLY file {path:?}"))
+}
+
+fn parse_ply(bytes: &[u8]) -> Result<Mesh> {
+    let (header, body) = parse_header(bytes)?;
+    let mut body = match header.format {
+        Format::Ascii => Body::Ascii(std::str::from_utf8(body)?.split_ascii_whitespace()),
+        Format::BinaryLittleEndian => Body::Binary {
+            bytes: body,
+            big_endian: false,
+        },
+        Format::BinaryBigEndian => Body::Binary {
+            bytes: body,
+            big_endian: true,
+        },
+    };
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for element in &header.elements {
+        match element.name.as_str() {
+            "vertex" => {
+                let properties = VertexProperties::new(element)?;
+                let mut row = Vec::with_capacity(element.properties.len());
+                for _ in 0..element.count {
+                    body.read_row(element, &mut row)?;
+                    vertices.push(properties.vertex(&row));
+                }
+            }
+            "face" => {
+                let list = element
+                    .properties
+                    .iter()
+                    .position(|p| {
+                        matches!(p.name.as_str(), "vertex_indices" | "vertex_index")
+                            && matches!(p.kind, PropertyKind::List { .. })
+                    })
+                    .ok_or_else(|| eyre!("PLY faces have no vertex_indices list"))?;
+
+                let mut polygon = Vec::new();
+                for _ in 0..element.count {
+                    for (i, property) in element.properties.iter().enumerate() {
+                        if i == list {
+                            body.read_list(property, &mut polygon)?;
+                        } else {
+                            body.skip(property)?;
+                        }
+                    }
+
+                    // Split the polygon into a fan of triangles around its
+                    // first vertex
+                    for i in 2..polygon.len() {
+                        indices.extend([polygon[0], polygon[i - 1], polygon[i]]);
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        body.skip(property)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if vertices.is_empty() {
+        return Err(eyre!("PLY file has no vertices"));
+    }
+
+    // Faces that don't make any triangles leave nothing to draw but points
+    let topology = if !indices.is_empty() {
+        if let Some(&index) = indices.iter().find(|&&i| i as usize >= vertices.len()) {
+            return Err(eyre!(
+                "PLY face refers to vertex {index}, but there are only {} vertices",
+                vertices.len()
+            ));
+        }
+        Topology::Triangles
+    } else {
+        indices = (0..vertices.len() as u32).collect();
+        Topology::Points
+    };
+
+    Ok(Mesh {
+        vertices,
+        indices,
+        topology,
+        ..Default::default()
+    })
+}
+
+/// How the body of a PLY file is encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// The numeric types a PLY property can have.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return Err(eyre!("Unknown PLY property type {name:?}")),
+        })
+    }
+
+    /// The size of the type in binary PLY files, in bytes.
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// The value that an integer color channel of this type uses for full
+    /// intensity. Floating point channels already go from 0 to 1.
+    fn color_max(self) -> f64 {
+        match self {
+            Self::I8 => i8::MAX.into(),
+            Self::U8 => u8::MAX.into(),
+            Self::I16 => i16::MAX.into(),
+            Self::U16 => u16::MAX.into(),
+            Self::I32 => i32::MAX.into(),
+            Self::U32 => u32::MAX.into(),
+            Self::F32 | Self::F64 => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PropertyKind {
+    Scalar(Scalar),
+    /// A variable length list, prefixed by its length.
+    List {
+        count: Scalar,
+        item: Scalar,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+/// A kind of thing stored in a PLY file, like vertices or faces, and how many
+/// of them there are.
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Debug)]
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+}
+
+/// Parse the header of a PLY file, returning it along with the rest of the
+/// file.
+fn parse_header(bytes: &[u8]) -> Result<(Header, &[u8])> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+    let mut first_line = true;
+
+    loop {
+        let end = bytes[offset..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| eyre!("PLY header doesn't end"))?;
+        let line = std::str::from_utf8(&bytes[offset..offset + end])?;
+        offset += end + 1;
+
+        let mut words = line.split_ascii_whitespace();
+        let keyword = words.next();
+        if first_line {
+            if keyword != Some("ply") {
+                return Err(eyre!("Not a PLY file"));
+            }
+            first_line = false;
+            continue;
+        }
+
+        match keyword {
+            Some("format") => {
+                format = Some(match (words.next(), words.next()) {
+                    (Some("ascii"), Some("1.0")) => Format::Ascii,
+                    (Some("binary_little_endian"), Some("1.0")) => Format::BinaryLittleEndian,
+                    (Some("binary_big_endian"), Some("1.0")) => Format::BinaryBigEndian,
+                    _ => return Err(eyre!("Unsupported PLY format {line:?}")),
+                });
+            }
+            Some("element") => {
+                let (Some(name), Some(count)) = (words.next(), words.next()) else {
+                    return Err(eyre!("Invalid PLY element {line:?}"));
+                };
+                elements.push(Element {
+                    name: name.to_owned(),
+                    count: count.parse()?,
+                    properties: Vec::new(),
+                });
+            }
+            Some("property") => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| eyre!("PLY property {line:?} isn't in an element"))?;
+                let words: Vec<_> = words.collect();
+                let (kind, name) = match words[..] {
+                    ["list", count, item, name] => (
+                        PropertyKind::List {
+                            count: Scalar::parse(count)?,
+                            item: Scalar::parse(item)?,
+                        },
+                        name,
+                    ),
+                    [scalar, name] => (PropertyKind::Scalar(Scalar::parse(scalar)?), name),
+                    _ => return Err(eyre!("Invalid PLY property {line:?}")),
+                };
+                element.properties.push(Property {
+                    name: name.to_owned(),
+                    kind,
+                });
+            }
+            Some("end_header") => break,
+            Some("comment" | "obj_info") | None => (),
+            Some(keyword) => return Err(eyre!("Unknown PLY header keyword {keyword:?}")),
+        }
+    }
+
+    let format = format.ok_or_else(|| eyre!("PLY header has no format"))?;
+    Ok((Header { format, elements }, &bytes[offset..]))
+}
+
+/// The data following a PLY header, read one value at a time.
+enum Body<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl Body<'_> {
+    fn read(&mut self, scalar: Scalar) -> Result<f64> {
+        match self {
+            Self::Ascii(words) => {
+                let word = words
+                    .next()
+                    .ok_or_else(|| eyre!("PLY data ends too early"))?;
+                word.parse()
+                    .map_err(|_| eyre!("Invalid PLY value {word:?}"))
+            }
+            Self::Binary { bytes, big_endian } => {
+                let size = scalar.size();
+                if bytes.len() < size {
+                    return Err(eyre!("PLY data ends too early"));
+                }
+                let (value, rest) = bytes.split_at(size);
+                *bytes = rest;
+
+                let mut le = [0; 8];
+                le[..size].copy_from_slice(value);
+                if *big_endian {
+                    le[..size].reverse();
+                }
+                let [b0, b1, b2, b3, ..] = le;
+
+                Ok(match scalar {
+                    Scalar::I8 => (b0 as i8).into(),
+                    Scalar::U8 => b0.into(),
+                    Scalar::I16 => i16::from_le_bytes([b0, b1]).into(),
+                    Scalar::U16 => u16::from_le_bytes([b0, b1]).into(),
+                    Scalar::I32 => i32::from_le_bytes([b0, b1, b2, b3]).into(),
+                    Scalar::U32 => u32::from_le_bytes([b0, b1, b2, b3]).into(),
+                    Scalar::F32 => f32::from_le_bytes([b0, b1, b2, b3]).into(),
+                    Scalar::F64 => f64::from_le_bytes(le),
+                })
+            }
+        }
+    }
+
+    /// Read the length of a list property.
+    fn read_count(&mut self, count: Scalar) -> Result<usize> {
+        let value = self.read(count)?;
+        if value < 0.0 || value.fract() != 0.0 {
+            return Err(eyre!("Invalid PLY list length {value}"));
+        }
+        Ok(value as usize)
+    }
+
+    /// Read one of an element's rows. Lists are skipped and read as zero,
+    /// since vertices are only made of scalars.
+    fn read_row(&mut self, element: &Element, row: &mut Vec<f64>) -> Result<()> {
+        row.clear();
+        for property in &element.properties {
+            match property.kind {
+                PropertyKind::Scalar(scalar) => row.push(self.read(scalar)?),
+                PropertyKind::List { .. } => {
+                    self.skip(property)?;
+                    row.push(0.0);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a list of vertex indices.
+    fn read_list(&mut self, property: &Property, list: &mut Vec<u32>) -> Result<()> {
+        let PropertyKind::List { count, item } = property.kind else {
+            return Err(eyre!("PLY property {:?} isn't a list", property.name));
+        };
+
+        list.clear();
+        for _ in 0..self.read_count(count)? {
+            let index = self.read(item)?;
+            if index < 0.0 || index > u32::MAX.into() || index.fract() != 0.0 {
+                return Err(eyre!("Invalid PLY vertex index {index}"));
+            }
+            list.push(index as u32);
+        }
+        Ok(())
+    }
+
+    fn skip(&mut self, property: &Property) -> Result<()> {
+        match property.kind {
+            PropertyKind::Scalar(scalar) => {
+                self.read(scalar)?;
+            }
+            PropertyKind::List { count, item } => {
+                for _ in 0..self.read_count(count)? {
+                    self.read(item)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where each part of a [`Vertex`] is in a row of the `vertex` element.
+struct VertexProperties {
+    position: [usize; 3],
+    normal: Option<[usize; 3]>,
+    /// The index and type of each color channel.
+    color: Option<[(usize, Scalar); 3]>,
+    tex_coord: Option<[usize; 2]>,
+}
+
+impl VertexProperties {
+    fn new(element: &Element) -> Result<Self> {
+        let find = |names: &[&str]| {
+            element
+                .properties
+                .iter()
+                .position(|p| names.contains(&p.name.as_str()))
+        };
+        let find_all = |names: [&str; 3]| -> Option<[usize; 3]> {
+            Some([find(&[names[0]])?, find(&[names[1]])?, find(&[names[2]])?])
+        };
+
+        let position =
+            find_all(["x", "y", "z"]).ok_or_else(|| eyre!("PLY vertices have no position"))?;
+        let color = find_all(["red", "green", "blue"]).map(|indices| {
+            indices.map(|i| match element.properties[i].kind {
+                PropertyKind::Scalar(scalar) => (i, scalar),
+                PropertyKind::List { item, .. } => (i, item),
+            })
+        });
+        let tex_coord = find(&["u", "s", "texture_u"])
+            .zip(find(&["v", "t", "texture_v"]))
+            .map(|(u, v)| [u, v]);
+
+        Ok(Self {
+            position,
+            normal: find_all(["nx", "ny", "nz"]),
+            color,
+            tex_coord,
+        })
+    }
+
+    fn vertex(&self, row: &[f64]) -> Vertex {
+        let vec3 = |[x, y, z]: [usize; 3]| glm::vec3(row[x] as f32, row[y] as f32, row[z] as f32);
+
+        let color = self.color.map_or(glm::vec3(1.0, 1.0, 1.0), |channels| {
+            let [r, g, b] = channels.map(|(i, scalar)| {
+                let value = (row[i] / scalar.color_max()).clamp(0.0, 1.0) as f32;
+                // Integer colors are stored in sRGB, but vertex colors are
+                // multiplied into linear texture samples
+                if scalar.color_max() > 1.0 {
+                    srgb_to_linear(value)
+                } else {
+                    value
+                }
+            });
+            glm::vec3(r, g, b)
+        });
+        let tex_coord = self.tex_coord.map_or(glm::Vec2::zeros(), |[u, v]| {
+            glm::vec2(row[u] as f32, 1.0 - row[v] as f32)
+        });
+        let normal = self.normal.map_or(glm::Vec3::zeros(), vec3);
+
+        Vertex::new(vec3(self.position), color, tex_coord, normal)
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_without_faces_is_a_colored_point_cloud() {
+        let ply = "\
+ply
+format ascii 1.0
+comment made by hand
+element vertex 3
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element camera 1
+property float view_px
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+0 1 0.5 0 0 0
+4.5
+";
+        let mesh = parse_ply(ply.as_bytes()).unwrap();
+
+        assert_eq!(mesh.topology, Topology::Points);
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+        assert_eq!(mesh.vertices[2].pos, glm::vec3(0.0, 1.0, 0.5));
+        assert_eq!(mesh.vertices[0].color, glm::vec3(1.0, 0.0, 0.0));
+        assert_eq!(mesh.vertices[1].color, glm::vec3(0.0, 1.0, 0.0));
+        assert_eq!(mesh.vertices[2].normal, glm::Vec3::zeros());
+    }
+
+    #[test]
+    fn an_empty_face_element_is_a_point_cloud() {
+        let ply = "\
+ply
+format ascii 1.0
+element vertex 2
+property float x
+property float y
+property float z
+element face 0
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+";
+        let mesh = parse_ply(ply.as_bytes()).unwrap();
+
+        assert_eq!(mesh.topology, Topology::Points);
+        assert_eq!(mesh.indices, vec![0, 1]);
+    }
+
+    #[test]
+    fn binary_faces_are_triangulated() {
+        let mut ply = b"\
+ply
+format binary_big_endian 1.0
+element vertex 4
+property float x
+property float y
+property float z
+element face 1
+property uchar intensity
+property list uchar int vertex_indices
+end_header
+"
+        .to_vec();
+        for pos in [
+            [0.0f32, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ] {
+            for coord in pos {
+                ply.extend_from_slice(&coord.to_be_bytes());
+            }
+        }
+        ply.extend_from_slice(&[7, 4]);
+        for index in [0i32, 1, 2, 3] {
+            ply.extend_from_slice(&index.to_be_bytes());
+        }
+
+        let mesh = parse_ply(&ply).unwrap();
+
+        assert_eq!(mesh.topology, Topology::Triangles);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.vertices[2].pos, glm::vec3(1.0, 1.0, 0.0));
+        assert_eq!(mesh.vertices[0].color, glm::vec3(1.0, 1.0, 1.0));
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn out_of_range_indices_are_rejected() {
+        let ply = "\
+ply
+format ascii 1.0
+element vertex 1
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+3 0 0 1
+";
+        assert!(parse_ply(ply.as_bytes()).is_err());
+    }
+}
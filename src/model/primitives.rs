@@ -0,0 +1,412 @@
+//! Built-in meshes that don't need a model file, for tests, debug scenes, and
+//! placeholders.
+//!
+//! Every primitive is centered on the origin with +z up, has outward-facing
+//! normals and counter-clockwise front faces, and is textured with UVs in the
+//! same top-left-origin convention that the loaders use. Curved surfaces are
+//! smooth-shaded, and have a UV seam where their `u` coordinate wraps around.
+
+use std::f32::consts::{PI, TAU};
+
+use ahash::AHashMap;
+use nalgebra_glm as glm;
+
+use super::{dedup_vertices, optimize, tangents, Mesh, Submesh};
+use crate::{bounds::Bounds, vertex::Vertex};
+
+/// A cube with sides `size` long. Each face has its own vertices, so that its
+/// edges are sharp, and is textured with the whole texture.
+pub fn cube(size: f32) -> Mesh {
+    let half = size / 2.0;
+    let (x, y, z) = (glm::Vec3::x(), glm::Vec3::y(), glm::Vec3::z());
+
+    // Each face's normal, and the directions of increasing u and v across it
+    let faces = [
+        (x, y, -z),
+        (-x, -y, -z),
+        (y, -x, -z),
+        (-y, x, -z),
+        (z, x, -y),
+        (-z, x, y),
+    ];
+
+    let mut builder = Builder::default();
+    for (normal, east, south) in faces {
+        builder.add_grid(1, 1, |u, v| {
+            let pos = (normal + east * (2.0 * u - 1.0) + south * (2.0 * v - 1.0)) * half;
+            (pos, normal)
+        });
+    }
+    builder.finish()
+}
+
+/// A flat square in the xy-plane facing +z, with sides `size` long, divided
+/// into `subdivisions` by `subdivisions` quads.
+pub fn grid(size: f32, subdivisions: u32) -> Mesh {
+    let subdivisions = subdivisions.max(1);
+
+    let mut builder = Builder::default();
+    builder.add_grid(subdivisions, subdivisions, |u, v| {
+        let pos = glm::vec3(u - 0.5, 0.5 - v, 0.0) * size;
+        (pos, glm::Vec3::z())
+    });
+    builder.finish()
+}
+
+/// A sphere made of `segments` slices from pole to pole, and `rings` stacks
+/// from the +z pole to the -z pole. The texture wraps around it once, like a
+/// map of the world.
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Mesh {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+
+    let mut builder = Builder::default();
+    builder.add_grid(segments, rings, |u, v| {
+        let (sin_theta, cos_theta) = (PI * v).sin_cos();
+        let (sin_phi, cos_phi) = (TAU * u).sin_cos();
+        let normal = glm::vec3(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta);
+        (normal * radius, normal)
+    });
+    builder.finish()
+}
+
+/// A sphere made by splitting each triangle of an icosahedron into four,
+/// `subdivisions` times. Its triangles are much more even than a UV sphere's.
+/// It's textured like a UV sphere, so the texture gets pinched at the poles.
+pub fn icosphere(radius: f32, subdivisions: u32) -> Mesh {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut positions: Vec<glm::Vec3> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .into_iter()
+    .map(|(x, y, z)| glm::vec3(x, y, z).normalize())
+    .collect();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // Neighboring triangles share the midpoints of their shared edges
+        let mut midpoints = AHashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let pos = (positions[a as usize] + positions[b as usize]).normalize();
+                positions.push(pos);
+                positions.len() as u32 - 1
+            })
+        };
+
+        triangles = triangles
+            .into_iter()
+            .flat_map(|[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut builder = Builder::default();
+    for triangle in triangles {
+        let normals = triangle.map(|i| positions[i as usize]);
+        let mut tex_coords = normals.map(|n| {
+            glm::vec2(
+                n.y.atan2(n.x).rem_euclid(TAU) / TAU,
+                n.z.clamp(-1.0, 1.0).acos() / PI,
+            )
+        });
+
+        // Triangles that straddle the seam would otherwise stretch across
+        // the whole texture, so move their corners on the u = 0 side past 1
+        let us = tex_coords.map(|t| t.x);
+        if us.iter().copied().fold(0.0, f32::max) - us.iter().copied().fold(1.0, f32::min) > 0.5 {
+            for tex_coord in &mut tex_coords {
+                if tex_coord.x < 0.5 {
+                    tex_coord.x += 1.0;
+                }
+            }
+        }
+
+        builder.add_triangle(
+            [0, 1, 2].map(|k| vertex(normals[k] * radius, tex_coords[k], normals[k])),
+        );
+    }
+    builder.finish()
+}
+
+/// A cylinder around the z-axis, with flat caps.
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> Mesh {
+    let segments = segments.max(3);
+    let half_height = height / 2.0;
+
+    let mut builder = Builder::default();
+    builder.add_grid(segments, 1, |u, v| {
+        let (sin_phi, cos_phi) = (TAU * u).sin_cos();
+        let normal = glm::vec3(cos_phi, sin_phi, 0.0);
+        let pos = normal * radius + glm::vec3(0.0, 0.0, half_height - height * v);
+        (pos, normal)
+    });
+    builder.add_disc(half_height, radius, segments, true);
+    builder.add_disc(-half_height, radius, segments, false);
+    builder.finish()
+}
+
+/// A cone around the z-axis, pointing up, with a flat base.
+pub fn cone(radius: f32, height: f32, segments: u32) -> Mesh {
+    let segments = segments.max(3);
+    let half_height = height / 2.0;
+
+    let mut builder = Builder::default();
+    builder.add_grid(segments, 1, |u, v| {
+        let (sin_phi, cos_phi) = (TAU * u).sin_cos();
+        let pos = glm::vec3(
+            cos_phi * radius * v,
+            sin_phi * radius * v,
+            half_height - height * v,
+        );
+        // The side slopes in by radius for every height it goes up
+        let normal = glm::vec3(cos_phi * height, sin_phi * height, radius).normalize();
+        (pos, normal)
+    });
+    builder.add_disc(-half_height, radius, segments, false);
+    builder.finish()
+}
+
+/// A ring around the z-axis. `major_radius` is the distance from the center
+/// to the middle of the tube, and `minor_radius` is the radius of the tube.
+/// The tube is made of `segments` sections around the ring, each with `sides`
+/// sides.
+pub fn torus(major_radius: f32, minor_radius: f32, segments: u32, sides: u32) -> Mesh {
+    let (segments, sides) = (segments.max(3), sides.max(3));
+
+    let mut builder = Builder::default();
+    builder.add_grid(segments, sides, |u, v| {
+        let (sin_phi, cos_phi) = (TAU * u).sin_cos();
+        let (sin_theta, cos_theta) = (TAU * v).sin_cos();
+        let outward = glm::vec3(cos_phi, sin_phi, 0.0);
+
+        // v starts on the outside of the ring and goes down first
+        let normal = outward * cos_theta - glm::Vec3::z() * sin_theta;
+        (outward * major_radius + normal * minor_radius, normal)
+    });
+    builder.finish()
+}
+
+fn vertex(pos: glm::Vec3, tex_coord: glm::Vec2, normal: glm::Vec3) -> Vertex {
+    Vertex::new(pos, glm::vec3(1.0, 1.0, 1.0), tex_coord, normal)
+}
+
+/// Collects triangles for a primitive, as a list of corners.
+#[derive(Default)]
+struct Builder {
+    corners: Vec<Vertex>,
+}
+
+impl Builder {
+    /// Add a triangle, unless its corners are (nearly) in a line, like the
+    /// triangles that meet at the poles of a sphere.
+    fn add_triangle(&mut self, corners: [Vertex; 3]) {
+        let [a, b, c] = corners.map(|c| c.pos);
+        let (ab, ac) = (b - a, c - a);
+        if ab.cross(&ac).norm() <= 1e-6 * ab.norm() * ac.norm() {
+            return;
+        }
+
+        self.corners.extend(corners);
+    }
+
+    /// Add a `columns` by `rows` grid of quads, with `surface` giving the
+    /// position and normal at each texture coordinate. Increasing `u` and `v`
+    /// have to go right and down when looking at the front of the surface.
+    fn add_grid<F>(&mut self, columns: u32, rows: u32, surface: F)
+    where
+        F: Fn(f32, f32) -> (glm::Vec3, glm::Vec3),
+    {
+        let corner = |column: u32, row: u32| {
+            let tex_coord = glm::vec2(column as f32 / columns as f32, row as f32 / rows as f32);
+            let (pos, normal) = surface(tex_coord.x, tex_coord.y);
+            vertex(pos, tex_coord, normal)
+        };
+
+        for row in 0..rows {
+            for column in 0..columns {
+                let top_left = corner(column, row);
+                let top_right = corner(column + 1, row);
+                let bottom_left = corner(column, row + 1);
+                let bottom_right = corner(column + 1, row + 1);
+
+                self.add_triangle([top_left, bottom_left, bottom_right]);
+                self.add_triangle([top_left, bottom_right, top_right]);
+            }
+        }
+    }
+
+    /// Add a flat disc at height `z`, facing up or down. The texture is
+    /// stretched over the square around the disc.
+    fn add_disc(&mut self, z: f32, radius: f32, segments: u32, facing_up: bool) {
+        let normal = if facing_up {
+            glm::Vec3::z()
+        } else {
+            -glm::Vec3::z()
+        };
+        let rim = |segment: u32| {
+            let (sin_phi, cos_phi) = (TAU * segment as f32 / segments as f32).sin_cos();
+            // Seen from below, +y is down in the texture rather than up
+            let v_sign = if facing_up { -1.0 } else { 1.0 };
+            vertex(
+                glm::vec3(cos_phi * radius, sin_phi * radius, z),
+                glm::vec2(0.5 + cos_phi / 2.0, 0.5 + v_sign * sin_phi / 2.0),
+                normal,
+            )
+        };
+        let center = vertex(glm::vec3(0.0, 0.0, z), glm::vec2(0.5, 0.5), normal);
+
+        for segment in 0..segments {
+            let (a, b) = (rim(segment), rim(segment + 1));
+            if facing_up {
+                self.add_triangle([center, a, b]);
+            } else {
+                self.add_triangle([center, b, a]);
+            }
+        }
+    }
+
+    /// Index the triangles, generate tangents, and optimize the mesh the same
+    /// way as loaded models.
+    fn finish(self) -> Mesh {
+        let (vertices, indices) = dedup_vertices(self.corners);
+        let mut mesh = Mesh {
+            submeshes: vec![Submesh {
+                first_index: 0,
+                index_count: indices.len() as u32,
+                material: None,
+            }],
+            vertices,
+            indices,
+            ..Default::default()
+        };
+
+        tangents::generate_tangents(&mut mesh);
+        optimize::optimize_mesh(&mut mesh);
+        mesh.bounds = Bounds::from_points(mesh.vertices.iter().map(|v| &v.pos));
+
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_primitives() -> Vec<(&'static str, Mesh)> {
+        vec![
+            ("cube", cube(2.0)),
+            ("grid", grid(2.0, 4)),
+            ("uv_sphere", uv_sphere(1.0, 16, 8)),
+            ("icosphere", icosphere(1.0, 2)),
+            ("cylinder", cylinder(1.0, 2.0, 16)),
+            ("cone", cone(1.0, 2.0, 16)),
+            ("torus", torus(1.0, 0.25, 16, 8)),
+        ]
+    }
+
+    #[test]
+    fn triangles_face_the_same_way_as_their_normals() {
+        for (name, mesh) in all_primitives() {
+            assert!(!mesh.indices.is_empty(), "{name}");
+            assert_eq!(mesh.submeshes[0].index_count as usize, mesh.indices.len());
+
+            for triangle in mesh.indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|k| mesh.vertices[triangle[k] as usize]);
+                let face_normal = (b.pos - a.pos).cross(&(c.pos - a.pos)).normalize();
+
+                for corner in [a, b, c] {
+                    assert!(
+                        face_normal.dot(&corner.normal) > 0.0,
+                        "{name}: {face_normal:?} vs {:?}",
+                        corner.normal
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vertices_have_unit_normals_and_tangents() {
+        for (name, mesh) in all_primitives() {
+            for vertex in &mesh.vertices {
+                assert!((vertex.normal.norm() - 1.0).abs() < 1e-4, "{name}");
+
+                let tangent = vertex.tangent.xyz();
+                assert!((tangent.norm() - 1.0).abs() < 1e-3, "{name}: {tangent:?}");
+                assert!(tangent.dot(&vertex.normal).abs() < 1e-3, "{name}");
+                assert_eq!(vertex.tangent.w.abs(), 1.0, "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn primitives_have_the_right_size() {
+        let bounds = |mesh: Mesh| (mesh.bounds.aabb.min, mesh.bounds.aabb.max);
+        let close = |(min, max): (glm::Vec3, glm::Vec3), expected: glm::Vec3| {
+            (min + expected).norm() < 1e-4 && (max - expected).norm() < 1e-4
+        };
+
+        assert!(close(bounds(cube(2.0)), glm::vec3(1.0, 1.0, 1.0)));
+        assert!(close(bounds(grid(2.0, 3)), glm::vec3(1.0, 1.0, 0.0)));
+        assert!(close(
+            bounds(uv_sphere(2.0, 16, 8)),
+            glm::vec3(2.0, 2.0, 2.0)
+        ));
+        assert!(close(
+            bounds(cylinder(1.0, 4.0, 16)),
+            glm::vec3(1.0, 1.0, 2.0)
+        ));
+        assert!(close(bounds(cone(1.0, 4.0, 16)), glm::vec3(1.0, 1.0, 2.0)));
+        assert!(close(
+            bounds(torus(2.0, 0.5, 16, 8)),
+            glm::vec3(2.5, 2.5, 0.5)
+        ));
+
+        let icosphere = icosphere(3.0, 1);
+        for vertex in &icosphere.vertices {
+            assert!((vertex.pos.norm() - 3.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn cube_faces_keep_their_own_vertices() {
+        let cube = cube(1.0);
+
+        assert_eq!(cube.vertices.len(), 24);
+        assert_eq!(cube.indices.len(), 36);
+    }
+}
@@ -0,0 +1,244 @@
+//! Loading STL models, in either the ASCII or binary encodings.
+
+use std::{fs, path::Path};
+
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use nalgebra_glm as glm;
+
+use super::{dedup_vertices, Mesh};
+use crate::vertex::Vertex;
+
+/// The size of a binary STL header and triangle count.
+const BINARY_HEADER_SIZE: usize = 84;
+/// The size of each triangle in a binary STL file: a normal, three positions,
+/// and a two byte attribute count.
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+/// Load a model from an STL file.
+///
+/// STL only stores positions and facet normals, so the vertices are white and
+/// each triangle is flat-shaded with its facet normal. Facet normals are often
+/// missing, so triangles without one get the normal their corners' winding
+/// gives them. Vertices are only shared between triangles facing the same way,
+/// which keeps the edges between facets sharp.
+pub(super) fn load_stl(path: &Path) -> Result<Mesh> {
+    let bytes = fs::read(path)?;
+    parse_stl(&bytes).wrap_err_with(|| format!("Error parsing STL file {path:?}"))
+}
+
+/// A triangle of an STL file.
+struct Facet {
+    /// Zero if the file doesn't give one.
+    normal: glm::Vec3,
+    corners: [glm::Vec3; 3],
+}
+
+fn parse_stl(bytes: &[u8]) -> Result<Mesh> {
+    // Plenty of binary files start with "solid" too, so the size is a more
+    // reliable way to tell the two encodings apart
+    let facets = match binary_triangle_count(bytes) {
+        Some(count) => parse_binary(bytes, count),
+        None => parse_ascii(bytes)?,
+    };
+
+    if facets.is_empty() {
+        return Err(eyre!("STL file has no triangles"));
+    }
+
+    let corners = facets.into_iter().flat_map(|facet| {
+        let normal = facet_normal(&facet);
+        facet
+            .corners
+            .map(|pos| Vertex::new(pos, glm::vec3(1.0, 1.0, 1.0), glm::Vec2::zeros(), normal))
+    });
+    let (vertices, indices) = dedup_vertices(corners);
+
+    Ok(Mesh {
+        vertices,
+        indices,
+        ..Default::default()
+    })
+}
+
+/// The normal to shade a facet with: its own normal if it has a usable one,
+/// or else the normal of its counter-clockwise winding. Degenerate triangles
+/// without a normal are left with a zero normal, for one to be generated.
+fn facet_normal(facet: &Facet) -> glm::Vec3 {
+    let normal = facet.normal;
+    if normal != glm::Vec3::zeros() && normal.iter().all(|n| n.is_finite()) {
+        return normal.normalize();
+    }
+
+    let [a, b, c] = facet.corners;
+    let normal = (b - a).cross(&(c - a));
+    if normal == glm::Vec3::zeros() {
+        normal
+    } else {
+        normal.normalize()
+    }
+}
+
+/// The number of triangles in a binary STL file, or `None` if the file isn't
+/// the size a binary file with its triangle count would be.
+fn binary_triangle_count(bytes: &[u8]) -> Option<usize> {
+    let count = bytes.get(80..BINARY_HEADER_SIZE)?;
+    let count = u32::from_le_bytes(count.try_into().ok()?) as usize;
+    let size = count
+        .checked_mul(BINARY_TRIANGLE_SIZE)?
+        .checked_add(BINARY_HEADER_SIZE)?;
+    (size == bytes.len()).then_some(count)
+}
+
+fn parse_binary(bytes: &[u8], count: usize) -> Vec<Facet> {
+    let read_f32 =
+        |offset: usize| f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    let read_vec3 =
+        |offset: usize| glm::vec3(read_f32(offset), read_f32(offset + 4), read_f32(offset + 8));
+
+    (0..count)
+        .map(|triangle| {
+            // The facet normal comes first, then the corners
+            let start = BINARY_HEADER_SIZE + triangle * BINARY_TRIANGLE_SIZE;
+            Facet {
+                normal: read_vec3(start),
+                corners: [1, 2, 3].map(|corner| read_vec3(start + corner * 12)),
+            }
+        })
+        .collect()
+}
+
+fn parse_ascii(bytes: &[u8]) -> Result<Vec<Facet>> {
+    let text = std::str::from_utf8(bytes).map_err(|_| eyre!("Not an STL file"))?;
+    let mut words = text.split_ascii_whitespace();
+    if words.next() != Some("solid") {
+        return Err(eyre!("Not an STL file"));
+    }
+
+    let mut normals = Vec::new();
+    let mut positions = Vec::new();
+    while let Some(word) = words.next() {
+        let mut vec3 = || -> Result<glm::Vec3> {
+            let mut coord = || -> Result<f32> {
+                let word = words
+                    .next()
+                    .ok_or_else(|| eyre!("STL {word} ends too early"))?;
+                word.parse()
+                    .map_err(|_| eyre!("Invalid STL coordinate {word:?}"))
+            };
+            Ok(glm::vec3(coord()?, coord()?, coord()?))
+        };
+
+        match word {
+            "facet" => normals.push(glm::Vec3::zeros()),
+            "normal" => {
+                let normal = vec3()?;
+                if let Some(last) = normals.last_mut() {
+                    *last = normal;
+                }
+            }
+            "vertex" => positions.push(vec3()?),
+            _ => (),
+        }
+    }
+
+    if positions.len() != normals.len() * 3 {
+        return Err(eyre!(
+            "STL file has {} vertices for {} facets, which doesn't make whole triangles",
+            positions.len(),
+            normals.len()
+        ));
+    }
+
+    Ok(normals
+        .into_iter()
+        .zip(positions.chunks_exact(3))
+        .map(|(normal, corners)| Facet {
+            normal,
+            corners: [corners[0], corners[1], corners[2]],
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_vertices_are_shared() {
+        let stl = "\
+solid square
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 1 1 0
+    endloop
+  endfacet
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 1 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid square
+";
+        let mesh = parse_stl(stl.as_bytes()).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertices[3].pos, glm::vec3(0.0, 1.0, 0.0));
+        assert_eq!(mesh.vertices[3].normal, glm::Vec3::z());
+    }
+
+    #[test]
+    fn facets_facing_different_ways_keep_their_own_vertices() {
+        // Two sides of a box sharing an edge, the second without a normal
+        let stl = "\
+solid corner
+  facet normal 0 0 2
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+  facet normal 0 0 0
+    outer loop
+      vertex 0 0 0
+      vertex 0 1 0
+      vertex 0 0 1
+    endloop
+  endfacet
+endsolid corner
+";
+        let mesh = parse_stl(stl.as_bytes()).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.vertices[0].normal, glm::Vec3::z());
+        assert_eq!(mesh.vertices[3].normal, glm::Vec3::x());
+    }
+
+    #[test]
+    fn binary_files_starting_with_solid_are_binary() {
+        let mut stl = b"solid but actually binary".to_vec();
+        stl.resize(80, 0);
+        stl.extend_from_slice(&1u32.to_le_bytes());
+        for value in [
+            0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 3.0, 0.0,
+        ] {
+            stl.extend_from_slice(&value.to_le_bytes());
+        }
+        stl.extend_from_slice(&[0, 0]);
+
+        let mesh = parse_stl(&stl).unwrap();
+
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+        assert_eq!(mesh.vertices[1].pos, glm::vec3(2.0, 0.0, 0.0));
+        assert_eq!(mesh.vertices[2].pos, glm::vec3(0.0, 3.0, 0.0));
+        assert_eq!(mesh.vertices[0].color, glm::vec3(1.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,180 @@
+//! Generating MikkTSpace tangents for normal-mapped meshes.
+//!
+//! MikkTSpace is the tangent space that Blender, Substance, xNormal, and most
+//! other tools bake normal maps against, so generating tangents the same way
+//! makes baked normal maps line up exactly. Like glTF, tangents are computed
+//! against texture coordinates with their origin in the top-left corner.
+
+use nalgebra_glm as glm;
+use tracing::warn;
+
+use super::{weld_corners, Mesh};
+use crate::vertex::Vertex;
+
+/// Fill in the tangents of any vertices whose tangent is zero, which is how
+/// the loaders mark missing tangents. Vertices that already have tangents are
+/// left alone, and every submesh keeps its index range.
+///
+/// This has to run after normals are generated, since tangents are built
+/// around the normals.
+pub(super) fn generate_tangents(mesh: &mut Mesh) {
+    if !mesh.vertices.iter().any(is_missing_tangent) {
+        return;
+    }
+
+    // MikkTSpace works on the corners of each triangle, and corners that
+    // share a vertex can end up with different tangents along UV seams and
+    // mirrored UVs. So unweld the mesh, then weld it back together afterwards.
+    let mut corners = Corners {
+        vertices: mesh
+            .indices
+            .iter()
+            .map(|&i| mesh.vertices[i as usize])
+            .collect(),
+    };
+    let missing: Vec<bool> = corners.vertices.iter().map(is_missing_tangent).collect();
+    let originals = corners.vertices.clone();
+
+    if !mikktspace::generate_tangents(&mut corners) {
+        warn!("Failed to generate tangents for mesh");
+        return;
+    }
+
+    let vertices = corners
+        .vertices
+        .into_iter()
+        .zip(originals)
+        .zip(missing)
+        .map(|((generated, original), missing)| if missing { generated } else { original })
+        .collect();
+
+    weld_corners(mesh, vertices);
+}
+
+fn is_missing_tangent(vertex: &Vertex) -> bool {
+    vertex.tangent == glm::Vec4::zeros()
+}
+
+/// An unindexed triangle list, as MikkTSpace wants to see it.
+struct Corners {
+    vertices: Vec<Vertex>,
+}
+
+impl Corners {
+    fn vertex(&self, face: usize, vert: usize) -> &Vertex {
+        &self.vertices[face * 3 + vert]
+    }
+}
+
+impl mikktspace::Geometry for Corners {
+    fn num_faces(&self) -> usize {
+        self.vertices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).pos.into()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).normal.into()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.vertex(face, vert).tex_coord.into()
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.vertices[face * 3 + vert].tangent = tangent.into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(x: f32, y: f32, u: f32, v: f32) -> Vertex {
+        Vertex::new(
+            glm::vec3(x, y, 0.0),
+            glm::vec3(1.0, 1.0, 1.0),
+            glm::vec2(u, v),
+            glm::Vec3::z(),
+        )
+    }
+
+    #[test]
+    fn tangents_follow_the_u_direction() {
+        // A quad facing +z, with u increasing along +x and v along -y
+        let mut mesh = Mesh {
+            vertices: vec![
+                vertex(0.0, 0.0, 0.0, 1.0),
+                vertex(1.0, 0.0, 1.0, 1.0),
+                vertex(1.0, 1.0, 1.0, 0.0),
+                vertex(0.0, 1.0, 0.0, 0.0),
+            ],
+            indices: vec![0, 1, 2, 2, 3, 0],
+            ..Default::default()
+        };
+
+        generate_tangents(&mut mesh);
+
+        // The corners weld back into the original four vertices
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 2, 3, 0]);
+        for vertex in &mesh.vertices {
+            assert!(
+                (vertex.tangent.xyz() - glm::Vec3::x()).norm() < 1e-5,
+                "{:?}",
+                vertex.tangent
+            );
+            // The bitangent, cross(normal, tangent) * w, points along +v
+            assert_eq!(vertex.tangent.w, -1.0);
+        }
+    }
+
+    #[test]
+    fn mirrored_uvs_get_opposite_signs() {
+        // Two triangles side by side, the second with its UVs mirrored in u
+        let mut mesh = Mesh {
+            vertices: vec![
+                vertex(0.0, 0.0, 0.0, 0.0),
+                vertex(1.0, 0.0, 1.0, 0.0),
+                vertex(0.0, 1.0, 0.0, 1.0),
+                vertex(2.0, 0.0, 0.0, 0.0),
+                vertex(2.0, 1.0, 0.0, 1.0),
+            ],
+            indices: vec![0, 1, 2, 1, 3, 4],
+            ..Default::default()
+        };
+
+        generate_tangents(&mut mesh);
+
+        let sign_of = |x: f32| {
+            let i = mesh.vertices.iter().position(|v| v.pos.x == x).unwrap();
+            mesh.vertices[i].tangent.w
+        };
+        assert_eq!(sign_of(0.0), -sign_of(2.0));
+    }
+
+    #[test]
+    fn existing_tangents_are_kept() {
+        let mut mesh = Mesh {
+            vertices: vec![
+                vertex(0.0, 0.0, 0.0, 0.0),
+                vertex(1.0, 0.0, 1.0, 0.0),
+                vertex(0.0, 1.0, 0.0, 1.0),
+            ],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        mesh.vertices[0].tangent = glm::vec4(0.0, 1.0, 0.0, 1.0);
+
+        generate_tangents(&mut mesh);
+
+        assert_eq!(mesh.vertices[0].tangent, glm::vec4(0.0, 1.0, 0.0, 1.0));
+        assert!(mesh.vertices[1].tangent.w != 0.0);
+    }
+}
@@ -2,6 +2,8 @@
 
 use nalgebra_glm as glm;
 
+use crate::bounds::BoundingSphere;
+
 /// A model-view-projection matrix, to be used for implementing things like
 /// 3D cameras.
 ///
@@ -9,13 +11,11 @@ use nalgebra_glm as glm;
 /// a struct.
 #[derive(Clone, Copy, Debug)]
 pub struct MvpMat {
-    /// This should probably be sent as a push constant, _not_ in a uniform.
+    /// Only used on the CPU, to work out how big objects look on screen.
+    /// Objects are placed on the GPU by their instance data.
     model: glm::Mat4,
     pub view: glm::Mat4,
     pub projection: glm::Mat4,
-
-    /// The position of this model in 3D space, relative to the origin.
-    pub model_position: glm::Vec3,
 }
 
 impl MvpMat {
@@ -28,7 +28,6 @@ impl MvpMat {
                 &glm::vec3(0.0, 0.0, 1.0),
             ),
             projection: glm::perspective(16.0 / 9.0, glm::radians(&glm::vec1(45.0))[0], 0.1, 10.0),
-            model_position: glm::vec3(0.0, 0.0, 0.0),
         };
 
         // Vulkan's Y axis is flipped compared to OpenGL, which GLM was originally
@@ -39,16 +38,9 @@ impl MvpMat {
         this
     }
 
-    /// Rotate the model by `a` radians about its z-axis.
-    pub fn model_rotate_z(&mut self, a: f32) -> &mut Self {
-        self.model = glm::rotate(&self.model, a, &glm::vec3(0.0, 0.0, 1.0));
-        self
-    }
-
-    /// Set the model's position in 3D space.
-    pub fn model_set_position(&mut self, position: &glm::Vec3) -> &mut Self {
-        self.model_position = *position;
-
+    /// Set the model matrix, which places the model being drawn in the world.
+    pub fn set_model(&mut self, model: glm::Mat4) -> &mut Self {
+        self.model = model;
         self
     }
 
@@ -71,6 +63,28 @@ impl MvpMat {
         self
     }
 
+    /// Roughly how many pixels tall a sphere in model space appears on
+    /// screen, for a viewport `viewport_height` pixels tall. This is infinite
+    /// if the camera is inside the sphere.
+    pub fn projected_size(&self, sphere: &BoundingSphere, viewport_height: f32) -> f32 {
+        let BoundingSphere { center, radius } = sphere.transform(&(self.view * self.model));
+
+        // The camera looks down the negative z-axis in view space
+        let distance = -center.z;
+        if distance <= radius {
+            return f32::INFINITY;
+        }
+
+        // projection[(1, 1)] is 1 / tan(fovy / 2), flipped for Vulkan
+        radius / distance * self.projection[(1, 1)].abs() * viewport_height
+    }
+
+    /// How far in front of the camera a point in model space is.
+    pub fn view_depth(&self, point: &glm::Vec3) -> f32 {
+        // The camera looks down the negative z-axis in view space
+        -(self.view * self.model * point.push(1.0)).z
+    }
+
     /// Copy view and projection into a struct ready for sending to the GPU
     /// as a uniform buffer object.
     pub const fn as_ubo(&self) -> MvpMatUBO {
@@ -79,14 +93,6 @@ impl MvpMat {
             projection: self.projection,
         }
     }
-
-    /// Copy model matrix into a struct ready for sending to the GPU in a
-    /// push constant.
-    pub fn as_push_constants(&self) -> MvpMatPushConstants {
-        MvpMatPushConstants {
-            model: glm::translate(&glm::identity(), &self.model_position) * self.model,
-        }
-    }
 }
 
 impl Default for MvpMat {
@@ -104,32 +110,40 @@ pub struct MvpMatUBO {
     pub projection: glm::Mat4,
 }
 
-/// This is intended to be sent to the GPU within a push constant,
-/// which is why it's `#[repr(C)]`.
-#[repr(C)]
-#[derive(Clone, Copy, Debug)]
-pub struct MvpMatPushConstants {
-    pub model: glm::Mat4,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn mvp_mat_push_constants_has_correct_size() {
-        assert_eq!(
-            std::mem::size_of::<MvpMatPushConstants>(),
-            64, // 16 × 4-byte floats
-            "Size of overall struct is wrong"
+    fn projected_size_shrinks_with_distance() {
+        let unit_sphere = BoundingSphere {
+            center: glm::Vec3::zeros(),
+            radius: 1.0,
+        };
+        let mut mvp_mat = MvpMat::new();
+        mvp_mat
+            .look_at(
+                &glm::vec3(0.0, 0.0, 0.0),
+                &glm::vec3(1.0, 0.0, 0.0),
+                &glm::vec3(0.0, 0.0, 1.0),
+            )
+            .perspective(1.0, std::f32::consts::FRAC_PI_2, 0.1, 100.0);
+
+        // With a 90 degree FOV, a sphere of radius 1 at distance 10 covers
+        // about a tenth of the screen
+        mvp_mat.set_model(glm::translation(&glm::vec3(10.0, 0.0, 0.0)));
+        let size = mvp_mat.projected_size(&unit_sphere, 1000.0);
+        assert!((size - 100.0).abs() < 1e-3, "{size}");
+
+        // Scaling the model scales its size on screen
+        mvp_mat.set_model(
+            glm::translation(&glm::vec3(10.0, 0.0, 0.0)) * glm::scaling(&glm::vec3(2.0, 2.0, 2.0)),
         );
+        let size = mvp_mat.projected_size(&unit_sphere, 1000.0);
+        assert!((size - 200.0).abs() < 1e-3, "{size}");
 
-        let mvp_mat = MvpMat::default();
-        let pcs = mvp_mat.as_push_constants();
-        assert_eq!(
-            std::mem::size_of_val(&pcs.model),
-            64, // 16 × 4-byte floats
-            "Size of model field is wrong"
-        );
+        mvp_mat.set_model(glm::identity());
+        assert_eq!(mvp_mat.projected_size(&unit_sphere, 1000.0), f32::INFINITY);
+        assert_eq!(mvp_mat.view_depth(&glm::vec3(3.0, 1.0, 0.0)), 3.0);
     }
 }
@@ -1,129 +1,152 @@
 //! Functions for dealing with vertex buffers, index buffers, and so on.
 
-use std::{mem::size_of, ptr};
+use std::mem::size_of_val;
 
 use ash::{vk, Device, Instance};
 use color_eyre::Result;
 
-use crate::{app::AppData, vertex::Vertex};
+use crate::app::AppData;
 
 use super::{
-    commands::{begin_transient_commands, end_transient_commands},
     memory::get_memory_type_index,
+    uploads::{create_staging_buffer, Upload},
 };
 
-/// Create vertex buffers for use by the app.
+/// Vertex and index buffers for a single mesh on the GPU.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MeshBuffers {
+    pub vertex_buffer: vk::Buffer,
+    pub vertex_buffer_memory: vk::DeviceMemory,
+    pub index_buffer: vk::Buffer,
+    pub index_buffer_memory: vk::DeviceMemory,
+    /// Whether `index_buffer` holds 16- or 32-bit indices.
+    pub index_type: vk::IndexType,
+    /// The number of indices to draw.
+    pub index_count: u32,
+}
+
+/// Upload a mesh's vertices and indices to the GPU. Indices are stored as 16-bit
+/// integers if the mesh has few enough vertices, halving the index buffer's
+/// size. Vertices can be of any `#[repr(C)]` vertex type, like
+/// [`Vertex`](crate::vertex::Vertex).
+///
+/// The buffers are filled by `upload`, and can't be drawn until it finishes.
+/// Destroy them with [`destroy_mesh_buffers()`] when done with them.
 #[tracing::instrument(level = "DEBUG", skip_all)]
-pub unsafe fn create_vertex_buffer(
+pub unsafe fn create_mesh_buffers<V>(
     instance: &Instance,
     device: &Device,
-    data: &mut AppData,
-) -> Result<()> {
-    // Create a vertex buffer for our static set of vertices (in lieu of proper model loading).
-    let size = (size_of::<Vertex>() * data.vertices.len()) as u64;
+    data: &AppData,
+    upload: &mut Upload,
+    vertices: &[V],
+    indices: &[u32],
+) -> Result<MeshBuffers> {
+    let (vertex_buffer, vertex_buffer_memory) =
+        create_vertex_buffer(instance, device, data, upload, vertices)?;
+    // Primitive restart is disabled, so every 16-bit value is a valid index
+    let (index_buffer, index_buffer_memory, index_type) = if vertices.len() <= u16::MAX as usize + 1
+    {
+        let indices: Vec<u16> = indices.iter().map(|&i| i as u16).collect();
+        let (buffer, memory) = create_index_buffer(instance, device, data, upload, &indices)?;
+        (buffer, memory, vk::IndexType::UINT16)
+    } else {
+        let (buffer, memory) = create_index_buffer(instance, device, data, upload, indices)?;
+        (buffer, memory, vk::IndexType::UINT32)
+    };
+
+    Ok(MeshBuffers {
+        vertex_buffer,
+        vertex_buffer_memory,
+        index_buffer,
+        index_buffer_memory,
+        index_type,
+        index_count: indices.len() as u32,
+    })
+}
 
-    // First copy the vertices to a host-visible staging buffer
-    let (staging_buffer, staging_buffer_memory) = create_buffer(
-        instance,
-        device,
-        data,
-        size,
-        vk::BufferUsageFlags::TRANSFER_SRC,
-        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
-    )?;
+/// Destroy the buffers created in [`create_mesh_buffers()`].
+pub unsafe fn destroy_mesh_buffers(device: &Device, mesh: &MeshBuffers) {
+    device.destroy_buffer(mesh.vertex_buffer, None);
+    device.free_memory(mesh.vertex_buffer_memory, None);
+    device.destroy_buffer(mesh.index_buffer, None);
+    device.free_memory(mesh.index_buffer_memory, None);
+}
 
-    {
-        // keep the memory map pointer inside this scope to avoid use-after-free
-        let memory =
-            device.map_memory(staging_buffer_memory, 0, size, vk::MemoryMapFlags::empty())?;
-        ptr::copy_nonoverlapping(data.vertices.as_ptr(), memory.cast(), data.vertices.len());
-        device.unmap_memory(staging_buffer_memory);
-    }
-
-    // Copy the vertices from the staging buffer to the highest-performance memory
-    // buffer the GPU will give us
-    let (vertex_buffer, vertex_buffer_memory) = create_buffer(
+/// Create a vertex buffer that `upload` fills with `vertices`.
+#[tracing::instrument(level = "DEBUG", skip_all)]
+pub unsafe fn create_vertex_buffer<V>(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    upload: &mut Upload,
+    vertices: &[V],
+) -> Result<(vk::Buffer, vk::DeviceMemory)> {
+    create_device_local_buffer(
         instance,
         device,
         data,
-        size,
-        vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::VERTEX_BUFFER,
-        vk::MemoryPropertyFlags::DEVICE_LOCAL,
-    )?;
-
-    data.vertex_buffer = vertex_buffer;
-    data.vertex_buffer_memory = vertex_buffer_memory;
-
-    copy_buffer(device, data, staging_buffer, vertex_buffer, size)?;
-
-    // remember to free the staging buffer
-    device.destroy_buffer(staging_buffer, None);
-    device.free_memory(staging_buffer_memory, None);
-
-    Ok(())
-}
-
-/// Destroy the vertex buffer created in [`create_vertex_buffer()`].
-pub unsafe fn destroy_vertex_buffer(device: &Device, data: &AppData) {
-    device.destroy_buffer(data.vertex_buffer, None);
-    device.free_memory(data.vertex_buffer_memory, None);
+        upload,
+        vertices,
+        vk::BufferUsageFlags::VERTEX_BUFFER,
+    )
 }
 
-/// Create index buffers for use by the app.
+/// Create an index buffer that `upload` fills with `indices`, which should be
+/// either `u16`s or `u32`s.
 #[tracing::instrument(level = "DEBUG", skip_all)]
-pub unsafe fn create_index_buffer(
+pub unsafe fn create_index_buffer<T>(
     instance: &Instance,
     device: &Device,
-    data: &mut AppData,
-) -> Result<()> {
-    // Create an index buffer for our static set of vertex indices (in lieu of proper model loading)
-    let size = (size_of::<u32>() * data.indices.len()) as u64;
-
-    // First copy the indices to a host-visible staging buffer
-    let (staging_buffer, staging_buffer_memory) = create_buffer(
+    data: &AppData,
+    upload: &mut Upload,
+    indices: &[T],
+) -> Result<(vk::Buffer, vk::DeviceMemory)> {
+    create_device_local_buffer(
         instance,
         device,
         data,
-        size,
-        vk::BufferUsageFlags::TRANSFER_SRC,
-        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
-    )?;
+        upload,
+        indices,
+        vk::BufferUsageFlags::INDEX_BUFFER,
+    )
+}
 
-    {
-        // keep the memory map pointer inside this scope to avoid use-after-free
-        let memory =
-            device.map_memory(staging_buffer_memory, 0, size, vk::MemoryMapFlags::empty())?;
-        ptr::copy_nonoverlapping(data.indices.as_ptr(), memory.cast(), data.indices.len());
-        device.unmap_memory(staging_buffer_memory);
-    }
-
-    // Copy the indices from the staging buffer to the highest-performance memory
-    // buffer the GPU will give us
-    let (index_buffer, index_buffer_memory) = create_buffer(
+/// Create a buffer in the highest-performance memory the GPU will give us,
+/// and record commands in `upload` that fill it with `contents` via a staging
+/// buffer.
+unsafe fn create_device_local_buffer<T>(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    upload: &mut Upload,
+    contents: &[T],
+    usage: vk::BufferUsageFlags,
+) -> Result<(vk::Buffer, vk::DeviceMemory)> {
+    let size = size_of_val(contents) as u64;
+
+    // First copy the contents to a host-visible staging buffer
+    let staging_buffer = create_staging_buffer(instance, device, data, upload, contents)?;
+
+    // Copy the contents from the staging buffer to the highest-performance
+    // memory buffer the GPU will give us
+    let (buffer, buffer_memory) = create_buffer(
         instance,
         device,
         data,
         size,
-        vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::INDEX_BUFFER,
+        vk::BufferUsageFlags::TRANSFER_DST | usage,
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
     )?;
 
-    data.index_buffer = index_buffer;
-    data.index_buffer_memory = index_buffer_memory;
-
-    copy_buffer(device, data, staging_buffer, index_buffer, size)?;
-
-    // remember to free the staging buffer
-    device.destroy_buffer(staging_buffer, None);
-    device.free_memory(staging_buffer_memory, None);
-
-    Ok(())
-}
+    let region = vk::BufferCopy::builder().size(size);
+    device.cmd_copy_buffer(
+        upload.command_buffer,
+        staging_buffer,
+        buffer,
+        std::slice::from_ref(&region),
+    );
 
-/// Destroy the index buffer created in [`create_index_buffer()`].
-pub unsafe fn destroy_index_buffer(device: &Device, data: &AppData) {
-    device.destroy_buffer(data.index_buffer, None);
-    device.free_memory(data.index_buffer_memory, None);
+    Ok((buffer, buffer_memory))
 }
 
 /// Create some type of buffer.
@@ -158,34 +181,3 @@ pub unsafe fn create_buffer(
 
     Ok((buffer, buffer_memory))
 }
-
-/// Copy data from one buffer to another.
-///
-/// - The source buffer must have been created with the [`vk::BufferUsageFlags::TRANSFER_SRC`]
-///   flag.
-/// - The destination buffer must have been created with the
-///   [`vk::BufferUsageFlags::TRANSFER_DST`] flag.
-pub unsafe fn copy_buffer(
-    device: &Device,
-    data: &AppData,
-    source: vk::Buffer,
-    destination: vk::Buffer,
-    size: vk::DeviceSize,
-) -> Result<()> {
-    // Start recording commands
-    let command_buffer = begin_transient_commands(device, data)?;
-
-    // Perform the copy
-    let regions = vk::BufferCopy::builder().size(size);
-    device.cmd_copy_buffer(
-        command_buffer,
-        source,
-        destination,
-        std::slice::from_ref(&regions),
-    );
-
-    // End recording commands and execute
-    end_transient_commands(device, data, command_buffer)?;
-
-    Ok(())
-}
@@ -0,0 +1,217 @@
+//! Copying rendered images back to the CPU.
+
+use std::ptr;
+
+use ash::{vk, Device, Instance};
+use color_eyre::Result;
+use tracing::debug;
+
+use crate::app::AppData;
+
+use super::{
+    buffers::create_buffer,
+    commands::{begin_transient_commands, end_transient_commands},
+};
+
+/// A copy of a rendered image into a host-visible buffer, which can be read
+/// back with [`read_capture()`] once the commands copying it have finished.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ImageCapture {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+}
+
+/// Record copying a color image with a 4-byte pixel format (e.g. a swapchain
+/// image) into a new host-visible buffer.
+///
+/// The image must have been created with the `TRANSFER_SRC` usage flag, and
+/// must be in the layout `layout` by the time the copy runs, which is right
+/// after anything recorded before it in `command_buffer` has written to the
+/// image. It is temporarily transitioned to `TRANSFER_SRC_OPTIMAL` for the
+/// copy if needed, and transitioned back afterwards.
+///
+/// Recording the copy into a frame's command buffer, rather than copying a
+/// frame after it has been presented, means the image is still owned by the
+/// app when it's copied.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(level = "DEBUG", skip(instance, device, data, command_buffer, image))]
+pub(crate) unsafe fn record_image_capture(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    format: vk::Format,
+    layout: vk::ImageLayout,
+    extent: vk::Extent2D,
+) -> Result<ImageCapture> {
+    let size = (extent.width * extent.height * 4) as u64;
+
+    // Create a host-visible buffer to copy the image into
+    let (buffer, memory) = create_buffer(
+        instance,
+        device,
+        data,
+        size,
+        vk::BufferUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+    )?;
+
+    let subresource_range = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1);
+
+    // Wait for rendering to the image to finish before copying it
+    let barrier = vk::ImageMemoryBarrier::builder()
+        .old_layout(layout)
+        .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(*subresource_range)
+        .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+        .dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &[*barrier],
+    );
+
+    let subresource = vk::ImageSubresourceLayers::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .mip_level(0)
+        .base_array_layer(0)
+        .layer_count(1);
+
+    let region = vk::BufferImageCopy::builder()
+        .buffer_offset(0)
+        .buffer_row_length(0)
+        .buffer_image_height(0)
+        .image_subresource(*subresource)
+        .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+        .image_extent(vk::Extent3D {
+            width: extent.width,
+            height: extent.height,
+            depth: 1,
+        });
+
+    device.cmd_copy_image_to_buffer(
+        command_buffer,
+        image,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        buffer,
+        &[*region],
+    );
+
+    // Put the image back the way it was, e.g. ready to be presented, and make
+    // the copy visible to the host
+    let barrier = vk::ImageMemoryBarrier::builder()
+        .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+        .new_layout(layout)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(*subresource_range)
+        .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+        .dst_access_mask(vk::AccessFlags::empty());
+    let host_barrier = vk::MemoryBarrier::builder()
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(vk::AccessFlags::HOST_READ);
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::PipelineStageFlags::BOTTOM_OF_PIPE | vk::PipelineStageFlags::HOST,
+        vk::DependencyFlags::empty(),
+        &[*host_barrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &[*barrier],
+    );
+
+    Ok(ImageCapture {
+        buffer,
+        memory,
+        format,
+        extent,
+    })
+}
+
+/// Read back the pixels copied by a capture, and destroy it. The commands
+/// recorded by [`record_image_capture()`] must have finished.
+///
+/// Returns tightly-packed rows of pixels in the image's own format.
+pub(crate) unsafe fn read_capture(device: &Device, capture: ImageCapture) -> Result<Vec<u8>> {
+    let extent = capture.extent;
+    let size = (extent.width * extent.height * 4) as u64;
+    let mut pixels = vec![0u8; size as usize];
+
+    let result = device
+        .map_memory(capture.memory, 0, size, vk::MemoryMapFlags::empty())
+        .map(|memory| {
+            // scope the mapped memory handle for safety
+            ptr::copy_nonoverlapping(memory.cast(), pixels.as_mut_ptr(), pixels.len());
+            device.unmap_memory(capture.memory);
+        });
+    destroy_capture(device, &capture);
+    result?;
+
+    debug!(
+        width = extent.width,
+        height = extent.height,
+        size,
+        "Read back image"
+    );
+
+    Ok(pixels)
+}
+
+/// Destroy a capture without reading it back.
+pub(crate) unsafe fn destroy_capture(device: &Device, capture: &ImageCapture) {
+    device.destroy_buffer(capture.buffer, None);
+    device.free_memory(capture.memory, None);
+}
+
+/// Copy the contents of a color image with a 4-byte pixel format into CPU
+/// memory right away, like [`record_image_capture()`] followed by
+/// [`read_capture()`]. Make sure rendering to the image has finished before
+/// calling this.
+///
+/// Returns tightly-packed rows of pixels in the image's own format.
+pub(crate) unsafe fn read_image_pixels(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    image: vk::Image,
+    format: vk::Format,
+    layout: vk::ImageLayout,
+    extent: vk::Extent2D,
+) -> Result<Vec<u8>> {
+    let command_buffer = begin_transient_commands(device, data)?;
+    let capture = record_image_capture(
+        instance,
+        device,
+        data,
+        command_buffer,
+        image,
+        format,
+        layout,
+        extent,
+    );
+    let ended = end_transient_commands(device, data, command_buffer);
+
+    let capture = capture?;
+    if let Err(e) = ended {
+        destroy_capture(device, &capture);
+        return Err(e);
+    }
+
+    read_capture(device, capture)
+}
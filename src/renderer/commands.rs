@@ -76,8 +76,12 @@ pub unsafe fn begin_transient_commands(
 }
 
 /// Stop recording a transient command buffer, submit it to the GPU for immediate
-/// execution, wait for the GPU to catch up, and then deallocate the command
+/// execution, wait for the GPU to finish it, and then deallocate the command
 /// buffer.
+///
+/// Only this command buffer is waited on, rather than the whole queue, so
+/// rendering and [uploads](super::uploads) that are already in flight carry
+/// on. To not wait at all, use an [`Upload`](super::uploads::Upload) instead.
 pub unsafe fn end_transient_commands(
     device: &Device,
     data: &AppData,
@@ -89,12 +93,15 @@ pub unsafe fn end_transient_commands(
     // Immediately execute the commands, and wait for completion
     let command_buffers = &[command_buffer];
     let info = vk::SubmitInfo::builder().command_buffers(command_buffers);
+    let fence = device.create_fence(&vk::FenceCreateInfo::default(), None)?;
 
-    device.queue_submit(data.graphics_queue, &[*info], vk::Fence::null())?;
-    device.queue_wait_idle(data.graphics_queue)?;
+    let result = device
+        .queue_submit(data.graphics_queue, &[*info], fence)
+        .and_then(|_| device.wait_for_fences(&[fence], true, u64::MAX));
 
-    // Free the command buffer
+    // Free the fence and command buffer
+    device.destroy_fence(fence, None);
     device.free_command_buffers(data.transient_command_pool, command_buffers);
 
-    Ok(())
+    Ok(result?)
 }
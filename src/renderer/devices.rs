@@ -3,20 +3,96 @@
 //! Also includes some queue family-related stuff.
 
 use super::{
-    extensions::REQUIRED_DEVICE_EXTENSIONS,
-    swapchain::SwapchainSupport,
-    validation::{should_enable_validation_layers, VALIDATION_LAYER},
+    extensions::required_device_extensions, swapchain::SwapchainSupport,
+    validation::VALIDATION_LAYER,
 };
 use crate::{
     app::AppData,
-    renderer::multisampling::get_max_msaa_samples,
+    renderer::multisampling::{choose_msaa_samples, get_max_msaa_samples},
+    settings::AppSettings,
     util::{PhysicalDeviceName, VkExtensionName},
 };
 use ash::{extensions::khr as vk_khr, vk, Device, Entry, Instance};
 use color_eyre::{eyre::eyre, Result};
-use std::collections::HashSet;
+use std::{collections::HashSet, fmt, str::FromStr};
 use thiserror::Error;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+/// The environment variable that can be set to force the use of a particular
+/// physical device. See [`DeviceSelector`] for the accepted values.
+pub const DEVICE_OVERRIDE_ENV_VAR: &str = "VK_TUT_DEVICE";
+
+/// Selects a specific physical device, overriding the automatic choice.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceSelector {
+    /// The device at this index in the order Vulkan enumerates devices.
+    Index(usize),
+    /// The first device whose name contains this string, ignoring case.
+    Name(String),
+}
+
+impl DeviceSelector {
+    /// Read a device override from the [`DEVICE_OVERRIDE_ENV_VAR`] environment
+    /// variable, if it is set. Invalid values are ignored with a warning.
+    pub fn from_env() -> Option<Self> {
+        let value = match std::env::var(DEVICE_OVERRIDE_ENV_VAR) {
+            Ok(value) => value,
+            Err(std::env::VarError::NotPresent) => return None,
+            Err(e) => {
+                warn!(var = DEVICE_OVERRIDE_ENV_VAR, error = %e, "Ignoring device override");
+                return None;
+            }
+        };
+
+        value
+            .parse()
+            .map_err(|e| {
+                warn!(
+                    var = DEVICE_OVERRIDE_ENV_VAR,
+                    ?value,
+                    error = e,
+                    "Ignoring device override"
+                )
+            })
+            .ok()
+    }
+
+    /// Returns true if the device at `index` with name `name` is selected.
+    fn matches(&self, index: usize, name: &PhysicalDeviceName) -> bool {
+        match self {
+            Self::Index(i) => *i == index,
+            Self::Name(n) => name
+                .to_string_lossy()
+                .to_lowercase()
+                .contains(&n.to_lowercase()),
+        }
+    }
+}
+
+impl FromStr for DeviceSelector {
+    type Err = &'static str;
+
+    /// Parses a device index if the string is a number, or a device name otherwise.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            Err("Device selector must not be empty")
+        } else if let Ok(index) = s.parse() {
+            Ok(Self::Index(index))
+        } else {
+            Ok(Self::Name(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for DeviceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Index(i) => write!(f, "device #{i}"),
+            Self::Name(n) => write!(f, "device named {n:?}"),
+        }
+    }
+}
 
 /// For when a physical device does not satisfy some requirement of the application.
 #[derive(Debug, Error)]
@@ -30,20 +106,40 @@ pub(crate) enum PhysicalDeviceSuitabilityError {
 }
 
 /// Picks a physical device to use for rendering.
+///
+/// The highest-scoring suitable device is chosen, unless a device is forced
+/// through [`AppSettings::device`] (which defaults to the value of the
+/// [`DEVICE_OVERRIDE_ENV_VAR`] environment variable).
 #[tracing::instrument(level = "DEBUG", skip_all)]
 pub(crate) unsafe fn pick_physical_device(
     entry: &Entry,
     instance: &Instance,
     data: &mut AppData,
+    settings: &AppSettings,
 ) -> Result<()> {
+    let selector = settings.device.as_ref();
     let mut valid_devices = Vec::new();
 
-    for physical_device in instance.enumerate_physical_devices()? {
+    for (index, physical_device) in instance
+        .enumerate_physical_devices()?
+        .into_iter()
+        .enumerate()
+    {
         let properties = instance.get_physical_device_properties(physical_device);
         let device_name = PhysicalDeviceName::from(properties.device_name);
 
+        if let Some(selector) = selector {
+            if !selector.matches(index, &device_name) {
+                debug!(device_name = %device_name, index, %selector, "Skipping physical device not matching override");
+                continue;
+            }
+        }
+
         match check_physical_device(entry, instance, data, physical_device) {
             Ok(score) => valid_devices.push((physical_device, score, device_name, properties)),
+            Err(err) if selector.is_some() => {
+                warn!(device_name = %device_name, reason = %err, "Requested physical device is unsuitable")
+            }
             Err(err) => {
                 debug!(device_name = %device_name, reason = %err, "Skipping physical device")
             }
@@ -51,21 +147,49 @@ pub(crate) unsafe fn pick_physical_device(
     }
 
     if valid_devices.is_empty() {
-        return Err(eyre!(
-            "Failed to find suitable physical device for rendering."
-        ));
+        return Err(match selector {
+            Some(selector) => {
+                eyre!("Failed to find a suitable physical device matching {selector}")
+            }
+            None => eyre!("Failed to find suitable physical device for rendering."),
+        });
     }
 
     // Choose the highest-scoring device
-    valid_devices.sort_unstable_by(|(_, score_a, _, _), (_, score_b, _, _)| score_a.cmp(score_b));
+    valid_devices.sort_unstable_by_key(|(_, score, _, _)| *score);
     let (physical_device, _, device_name, properties) = valid_devices.last().unwrap();
 
     data.physical_device = *physical_device;
-    data.msaa_samples = get_max_msaa_samples(instance, data);
+    data.msaa_samples =
+        choose_msaa_samples(get_max_msaa_samples(instance, data), settings.msaa_samples)?;
+
+    // Anisotropic filtering is nice to have, but not required.
+    let features = instance.get_physical_device_features(*physical_device);
+    data.max_sampler_anisotropy = (features.sampler_anisotropy == vk::TRUE)
+        .then(|| properties.limits.max_sampler_anisotropy.min(16.0));
+
+    // So are points bigger than a pixel
+    let max_point_size = if features.large_points == vk::TRUE {
+        properties.limits.point_size_range[1]
+    } else {
+        1.0
+    };
+    data.point_size = settings.point_size.clamp(1.0, max_point_size);
+    if data.point_size != settings.point_size {
+        warn!(
+            requested = settings.point_size,
+            point_size = data.point_size,
+            "Requested point size isn't supported"
+        );
+    }
+
     info!(
         device_name = %device_name,
         device_id = properties.device_id,
+        device_type = ?properties.device_type,
         msaa_samples = ?data.msaa_samples,
+        max_sampler_anisotropy = ?data.max_sampler_anisotropy,
+        point_size = data.point_size,
         "Selected physical device for rendering"
     );
 
@@ -74,6 +198,9 @@ pub(crate) unsafe fn pick_physical_device(
 
 /// Check if a physical device satisfies all the requirements of this application.
 /// Returns a score based on its properties and available features.
+///
+/// Any kind of device is accepted, but real GPUs score far higher than virtual
+/// GPUs or software implementations like lavapipe or SwiftShader.
 unsafe fn check_physical_device(
     entry: &Entry,
     instance: &Instance,
@@ -86,26 +213,16 @@ unsafe fn check_physical_device(
     match properties.device_type {
         vk::PhysicalDeviceType::DISCRETE_GPU => score += 200,
         vk::PhysicalDeviceType::INTEGRATED_GPU => score += 100,
-        _ => {
-            return Err(PhysicalDeviceSuitabilityError::Unsuitable(
-                "Only discrete and integrated GPUs are supported",
-            ))
-        }
+        vk::PhysicalDeviceType::VIRTUAL_GPU => score += 50,
+        vk::PhysicalDeviceType::CPU => score += 10,
+        _ => {}
     }
 
+    // Anisotropic filtering is used for texture sampling if it's available,
+    // so prefer devices that support it.
     let features = instance.get_physical_device_features(physical_device);
-    if features.geometry_shader != vk::TRUE {
-        return Err(PhysicalDeviceSuitabilityError::Unsuitable(
-            "Missing geometry shader support",
-        ));
-    }
-
-    // We require anisotropy support, because almost every modern graphics card
-    // we care about supports it.
-    if features.sampler_anisotropy != vk::TRUE {
-        return Err(PhysicalDeviceSuitabilityError::Unsuitable(
-            "Missing sampler anisotropy support.",
-        ));
+    if features.sampler_anisotropy == vk::TRUE {
+        score += 5;
     }
 
     // if the following function call doesn't panic, then the device supports
@@ -115,18 +232,21 @@ unsafe fn check_physical_device(
 
     // check if this device has all the extensions we care about. If this doesn't
     // panic, then we're all good.
-    check_physical_device_extensions(instance, physical_device)?;
+    check_physical_device_extensions(instance, data, physical_device)?;
 
     // Check swapchain support. We only care if there's at least one supported
     // image format and one supported presentation mode, given our window surface.
     // Note that we can only query for swapchain support after checking if the
     // VK_KHR_swapchain extension is available - so this must be done after
-    // checking for physical device extensions.
-    let swapchain_support = SwapchainSupport::get(entry, instance, data, physical_device)?;
-    if swapchain_support.formats.is_empty() || swapchain_support.present_modes.is_empty() {
-        return Err(PhysicalDeviceSuitabilityError::Unsuitable(
-            "Insufficient swapchain support.",
-        ));
+    // checking for physical device extensions. There's no swapchain at all
+    // when rendering headlessly.
+    if !data.is_headless() {
+        let swapchain_support = SwapchainSupport::get(entry, instance, data, physical_device)?;
+        if swapchain_support.formats.is_empty() || swapchain_support.present_modes.is_empty() {
+            return Err(PhysicalDeviceSuitabilityError::Unsuitable(
+                "Insufficient swapchain support.",
+            ));
+        }
     }
 
     Ok(score)
@@ -134,6 +254,7 @@ unsafe fn check_physical_device(
 
 unsafe fn check_physical_device_extensions(
     instance: &Instance,
+    data: &AppData,
     physical_device: vk::PhysicalDevice,
 ) -> Result<(), PhysicalDeviceSuitabilityError> {
     let extensions = instance
@@ -144,7 +265,7 @@ unsafe fn check_physical_device_extensions(
 
     let mut missing_extensions = Vec::new();
 
-    for ext in REQUIRED_DEVICE_EXTENSIONS.iter() {
+    for ext in required_device_extensions(data) {
         if !extensions.contains(ext) {
             missing_extensions.push(*ext);
         }
@@ -189,18 +310,20 @@ pub(crate) unsafe fn create_logical_device(
         .collect::<Vec<_>>();
 
     // Setup validation layers (if needed)
-    let layers = if should_enable_validation_layers() {
+    let layers = if data.validation_enabled {
         vec![VALIDATION_LAYER.as_ptr()]
     } else {
         Vec::new()
     };
 
-    // Set up device-specific features
-    let features = vk::PhysicalDeviceFeatures::builder().sampler_anisotropy(true);
+    // Set up device-specific features. Only enable what we actually use.
+    let features = vk::PhysicalDeviceFeatures::builder()
+        .sampler_anisotropy(data.max_sampler_anisotropy.is_some())
+        .large_points(data.point_size > 1.0);
 
     // Convert our list of absolutely-required extensions to a seires of
     // null-terminated string pointers.
-    let extension_names = REQUIRED_DEVICE_EXTENSIONS
+    let extension_names = required_device_extensions(data)
         .iter()
         .map(|ext| ext.as_ptr())
         .collect::<Vec<_>>();
@@ -245,7 +368,11 @@ impl QueueFamilyIndices {
                 graphics = Some(i as u32);
             }
 
-            if surface_ext.get_physical_device_surface_support(
+            if data.is_headless() {
+                // Nothing gets presented when rendering headlessly, so any
+                // queue family will do.
+                present = graphics;
+            } else if surface_ext.get_physical_device_surface_support(
                 physical_device,
                 i as u32,
                 data.surface,
@@ -268,3 +395,29 @@ impl QueueFamilyIndices {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_selector_parses_indices_and_names() {
+        assert_eq!("0".parse(), Ok(DeviceSelector::Index(0)));
+        assert_eq!(" 2 ".parse(), Ok(DeviceSelector::Index(2)));
+        assert_eq!(
+            "llvmpipe".parse(),
+            Ok(DeviceSelector::Name("llvmpipe".to_string()))
+        );
+        assert!("".parse::<DeviceSelector>().is_err());
+    }
+
+    #[test]
+    fn device_selector_matches_names_ignoring_case() {
+        let name = PhysicalDeviceName::from_bytes(b"llvmpipe (LLVM 15.0.7, 256 bits)");
+
+        assert!(DeviceSelector::Name("LLVMpipe".to_string()).matches(3, &name));
+        assert!(!DeviceSelector::Name("SwiftShader".to_string()).matches(3, &name));
+        assert!(DeviceSelector::Index(3).matches(3, &name));
+        assert!(!DeviceSelector::Index(0).matches(3, &name));
+    }
+}
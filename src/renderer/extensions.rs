@@ -1,6 +1,6 @@
 //! Required and optional Vulkan extensions.
 
-use crate::util::VkExtensionName;
+use crate::{app::AppData, util::VkExtensionName};
 use ash::extensions::khr as vk_khr;
 use lazy_static::lazy_static;
 
@@ -15,6 +15,16 @@ lazy_static! {
         .collect();
 }
 
+/// Device extensions required for rendering with the given app data. Rendering
+/// headlessly doesn't need a swapchain, so no extensions are required then.
+pub(crate) fn required_device_extensions(data: &AppData) -> &'static [VkExtensionName] {
+    if data.is_headless() {
+        &[]
+    } else {
+        &REQUIRED_DEVICE_EXTENSIONS
+    }
+}
+
 /// [`ash`] dynamically links to extensions, on the fly. This can be detrimental
 /// to performance if done repeatedly (e.g. in a render loop). This struct can
 /// be used to "cache" the links to the extensions.
@@ -1,6 +1,6 @@
 //! Functions for creating Vulkan instances.
 
-use super::validation::{should_enable_validation_layers, vk_debug_callback, VALIDATION_LAYER};
+use super::validation::{vk_debug_callback, VALIDATION_LAYER};
 use crate::{app::AppData, util::VkExtensionName};
 use ash::{extensions::ext as vk_ext, vk, Entry, Instance};
 use color_eyre::{eyre::eyre, Result};
@@ -8,25 +8,28 @@ use std::{collections::HashSet, ffi::CStr};
 use tracing::debug;
 use winit::window::Window;
 
+const APPLICATION_NAME: &CStr = c"Rusty Vulkan Tutorial";
+const ENGINE_NAME: &CStr = c"Johann's Rust Special";
+
 /// Create a Vulkan instance from an entry point.
 ///
 /// The window handle is required so that we can load the required extensions for
-/// drawing to a window.
+/// drawing to a window. Pass `None` to create an instance for headless rendering.
 #[tracing::instrument(level = "DEBUG", skip_all)]
 pub(crate) unsafe fn create_instance(
-    window: &Window,
+    window: Option<&Window>,
     entry: &Entry,
     data: &mut AppData,
 ) -> Result<Instance> {
     type DebugSeverity = vk::DebugUtilsMessageSeverityFlagsEXT;
     type DebugMsgType = vk::DebugUtilsMessageTypeFlagsEXT;
 
-    let validation_enabled = should_enable_validation_layers();
+    let validation_enabled = data.validation_enabled;
 
     let app_info = vk::ApplicationInfo::builder()
-        .application_name(CStr::from_bytes_with_nul(b"Rusty Vulkan Tutorial\0")?)
+        .application_name(APPLICATION_NAME)
         .application_version(vk::make_api_version(0, 1, 0, 0))
-        .engine_name(CStr::from_bytes_with_nul(b"Johann's Rust Special\0")?)
+        .engine_name(ENGINE_NAME)
         .engine_version(vk::make_api_version(0, 1, 0, 0))
         .api_version(vk::make_api_version(0, 1, 0, 0));
 
@@ -53,7 +56,10 @@ pub(crate) unsafe fn create_instance(
 
     // Load Vulkan extensions
 
-    let mut extensions = Vec::from(ash_window::enumerate_required_extensions(&window)?);
+    let mut extensions = match window {
+        Some(window) => Vec::from(ash_window::enumerate_required_extensions(window)?),
+        None => Vec::new(),
+    };
 
     if validation_enabled {
         debug!(extension = ?vk_ext::DebugUtils::name(), "Enabling extension");
@@ -1,4 +1,5 @@
 pub mod buffers;
+pub mod capture;
 pub mod commands;
 pub mod depth_tests;
 pub mod devices;
@@ -6,9 +7,11 @@ pub mod extensions;
 pub mod instance;
 pub mod memory;
 pub mod multisampling;
+pub mod offscreen;
 pub mod pipeline;
 pub mod swapchain;
 pub mod synchronization;
 pub mod texture;
 pub mod uniforms;
+pub mod uploads;
 pub mod validation;
@@ -1,5 +1,6 @@
 use ash::{vk, Device, Instance};
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Result};
+use tracing::warn;
 
 use crate::app::AppData;
 
@@ -23,6 +24,41 @@ pub unsafe fn get_max_msaa_samples(instance: &Instance, data: &AppData) -> vk::S
     .unwrap_or(vk::SampleCountFlags::TYPE_1)
 }
 
+/// Pick the number of samples to use for multisampling, given the maximum
+/// supported by the device and an optional requested count.
+///
+/// Requested counts above the maximum are clamped to the maximum. Returns an
+/// error if the requested count isn't a valid sample count. A single sample
+/// isn't allowed, since the render pass always resolves the multisampled
+/// color attachment, and Vulkan can't resolve a single-sampled one.
+pub fn choose_msaa_samples(
+    max: vk::SampleCountFlags,
+    requested: Option<u32>,
+) -> Result<vk::SampleCountFlags> {
+    let requested = match requested {
+        None => return Ok(max),
+        Some(n) if n.is_power_of_two() && (2..=64).contains(&n) => {
+            vk::SampleCountFlags::from_raw(n)
+        }
+        Some(n) => {
+            return Err(eyre!(
+                "Invalid MSAA sample count {n}, must be a power of two from 2 to 64"
+            ))
+        }
+    };
+
+    if requested.as_raw() > max.as_raw() {
+        warn!(
+            ?requested,
+            ?max,
+            "Requested MSAA sample count isn't supported, using the maximum"
+        );
+        Ok(max)
+    } else {
+        Ok(requested)
+    }
+}
+
 /// Create a multisampled color attachment.
 #[tracing::instrument(level = "DEBUG", skip_all)]
 pub unsafe fn create_color_objects(
@@ -57,3 +93,23 @@ pub unsafe fn create_color_objects(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn msaa_samples_are_clamped_to_the_maximum() {
+        let max = vk::SampleCountFlags::TYPE_4;
+
+        assert_eq!(choose_msaa_samples(max, None).unwrap(), max);
+        assert_eq!(
+            choose_msaa_samples(max, Some(2)).unwrap(),
+            vk::SampleCountFlags::TYPE_2
+        );
+        assert_eq!(choose_msaa_samples(max, Some(16)).unwrap(), max);
+        assert!(choose_msaa_samples(max, Some(3)).is_err());
+        assert!(choose_msaa_samples(max, Some(0)).is_err());
+        assert!(choose_msaa_samples(max, Some(1)).is_err());
+    }
+}
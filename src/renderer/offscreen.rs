@@ -0,0 +1,69 @@
+//! Offscreen render targets, for rendering without a window or a swapchain.
+//!
+//! In headless mode, the offscreen color image stands in for the (single)
+//! swapchain image, so the rest of the renderer doesn't have to care about
+//! where its frames end up.
+
+use ash::{vk, Device, Instance};
+use color_eyre::Result;
+
+use crate::app::AppData;
+
+use super::texture::{create_image, create_image_view};
+
+/// The color format used for offscreen render targets. Pixels read back from
+/// the target are tightly-packed 8-bit RGBA values in the sRGB color space.
+pub(crate) const OFFSCREEN_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+/// Create a device-local color image of size `extent` to render into, and
+/// register it as the only "swapchain" image.
+#[tracing::instrument(level = "DEBUG", skip_all, fields(?extent))]
+pub(crate) unsafe fn create_offscreen_target(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    extent: vk::Extent2D,
+) -> Result<()> {
+    let (image, image_memory) = create_image(
+        instance,
+        device,
+        data,
+        extent.width,
+        extent.height,
+        1,
+        vk::SampleCountFlags::TYPE_1,
+        OFFSCREEN_FORMAT,
+        vk::ImageTiling::OPTIMAL,
+        vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )?;
+
+    let image_view = create_image_view(
+        device,
+        image,
+        OFFSCREEN_FORMAT,
+        vk::ImageAspectFlags::COLOR,
+        1,
+    )?;
+
+    data.offscreen_image_memory = image_memory;
+    data.swapchain_images = vec![image];
+    data.swapchain_image_views = vec![image_view];
+    data.swapchain_format = OFFSCREEN_FORMAT;
+    data.swapchain_extent = extent;
+    data.swapchain_image_usage =
+        vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC;
+
+    Ok(())
+}
+
+/// Destroy the render target created by [`create_offscreen_target()`].
+pub(crate) unsafe fn destroy_offscreen_target(device: &Device, data: &AppData) {
+    data.swapchain_image_views
+        .iter()
+        .for_each(|v| device.destroy_image_view(*v, None));
+    data.swapchain_images
+        .iter()
+        .for_each(|i| device.destroy_image(*i, None));
+    device.free_memory(data.offscreen_image_memory, None);
+}
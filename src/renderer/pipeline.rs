@@ -1,12 +1,32 @@
 //! Tools for setting up render pipelines.
 
-use crate::{app::AppData, mvp_matrix::MvpMatPushConstants, vertex::Vertex};
+use crate::{
+    app::AppData,
+    vertex::{
+        ColoredVertex, InstanceData, PositionVertex, QuantizedVertex, SkinnedVertex, Vertex,
+        VertexLayout,
+    },
+};
 use ash::{vk, Device, Instance};
 use color_eyre::{eyre::eyre, Result};
-use std::ffi::CStr;
+use nalgebra_glm as glm;
+use std::{ffi::CStr, mem::size_of};
 
 use super::depth_tests::get_depth_format;
 
+/// Per-submesh values for the fragment shader, pushed before each draw.
+/// Everything that varies between objects is in their [`InstanceData`]
+/// instead. This is `#[repr(C)]` to match the shader's layout.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MaterialPushConstants {
+    /// Multiplied with the color sampled from the texture.
+    pub base_color: glm::Vec4,
+}
+
+/// The name of the entry point function in all of our shaders.
+const SHADER_ENTRY_POINT: &CStr = c"main";
+
 /// Create a render pass.
 #[tracing::instrument(level = "DEBUG", skip_all)]
 pub(crate) unsafe fn create_render_pass(
@@ -57,7 +77,13 @@ pub(crate) unsafe fn create_render_pass(
         .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
 
     // Set up a color resolve attachment so our normal multisampled color
-    // attachment can be resolved to a regular image.
+    // attachment can be resolved to a regular image. When rendering headlessly
+    // the resolved image gets copied back to the CPU instead of presented.
+    let resolve_final_layout = if data.is_headless() {
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+    } else {
+        vk::ImageLayout::PRESENT_SRC_KHR
+    };
     let color_resolve_attachment = vk::AttachmentDescription::builder()
         .format(data.swapchain_format)
         .samples(vk::SampleCountFlags::TYPE_1)
@@ -66,7 +92,7 @@ pub(crate) unsafe fn create_render_pass(
         .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
         .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
         .initial_layout(vk::ImageLayout::UNDEFINED)
-        .final_layout(vk::ImageLayout::PRESENT_SRC_KHR);
+        .final_layout(resolve_final_layout);
 
     // The color resolve attachment is available as the 2nd output destination
     let color_resolve_attachment_ref = vk::AttachmentReference::builder()
@@ -105,6 +131,16 @@ pub(crate) unsafe fn create_render_pass(
                 | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
         );
 
+    // When rendering headlessly, make sure that writes to the resolved image
+    // are finished and visible before it gets copied back to the CPU.
+    let readback_dependency = vk::SubpassDependency::builder()
+        .src_subpass(0)
+        .dst_subpass(vk::SUBPASS_EXTERNAL)
+        .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+        .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+        .dst_stage_mask(vk::PipelineStageFlags::TRANSFER)
+        .dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+
     // Finalize the render pass.
     let attachments = &[
         *color_attachment,
@@ -112,49 +148,189 @@ pub(crate) unsafe fn create_render_pass(
         *color_resolve_attachment,
     ];
     let subpasses = &[*subpass];
-    let dependencies = &[*dependency];
+    let dependencies = if data.is_headless() {
+        vec![*dependency, *readback_dependency]
+    } else {
+        vec![*dependency]
+    };
     let info = vk::RenderPassCreateInfo::builder()
         .attachments(attachments)
         .subpasses(subpasses)
-        .dependencies(dependencies);
+        .dependencies(&dependencies);
 
     data.render_pass = device.create_render_pass(&info, None)?;
 
     Ok(())
 }
 
-/// Create a graphics pipeline.
+/// The parts of a graphics pipeline that differ between the pipelines made by
+/// [`create_graphics_pipelines()`]: the vertex shader, the vertex format it
+/// reads, and what the vertices are assembled into.
+pub(crate) struct PipelineDesc<'a> {
+    /// SPIR-V bytecode.
+    vertex_shader: &'a [u8],
+    specialization: Option<&'a vk::SpecializationInfo>,
+    bindings: Vec<vk::VertexInputBindingDescription>,
+    attributes: Vec<vk::VertexInputAttributeDescription>,
+    topology: vk::PrimitiveTopology,
+}
+
+impl<'a> PipelineDesc<'a> {
+    /// A pipeline that draws triangle lists of `V` vertices with a vertex
+    /// shader.
+    pub fn new<V: VertexLayout>(vertex_shader: &'a [u8]) -> Self {
+        Self {
+            vertex_shader,
+            specialization: None,
+            bindings: V::binding_descriptions(),
+            attributes: V::attribute_descriptions(),
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+        }
+    }
+
+    /// Assemble the vertices into something other than a triangle list.
+    pub fn topology(self, topology: vk::PrimitiveTopology) -> Self {
+        Self { topology, ..self }
+    }
+
+    /// Set the vertex shader's specialization constants.
+    pub fn specialization(self, specialization: &'a vk::SpecializationInfo) -> Self {
+        Self {
+            specialization: Some(specialization),
+            ..self
+        }
+    }
+}
+
+/// Create the pipeline layout and the graphics pipelines: one for regular
+/// meshes, one for skinned meshes, one for point clouds, one for meshes with
+/// quantized vertices, one for meshes with only positions, and one for colored
+/// lines. The skinned, quantized, and position-only pipelines differ in their
+/// vertex shaders and vertex formats, and the point and line pipelines in
+/// their topology too.
 #[tracing::instrument(level = "DEBUG", skip_all)]
 pub(crate) unsafe fn create_pipeline(device: &Device, data: &mut AppData) -> Result<()> {
     // Include our pre-compiled shaders.
     let vert = include_bytes!("../../shaders/shader.vert.spv");
+    let skinned_vert = include_bytes!("../../shaders/skinned.vert.spv");
+    let points_vert = include_bytes!("../../shaders/points.vert.spv");
+    let quantized_vert = include_bytes!("../../shaders/quantized.vert.spv");
+    let position_vert = include_bytes!("../../shaders/position.vert.spv");
+    let lines_vert = include_bytes!("../../shaders/lines.vert.spv");
+
+    // Tell the pipeline about our push constants
+    let frag_push_constant_range = vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        .offset(0)
+        .size(std::mem::size_of::<MaterialPushConstants>() as u32);
+
+    // Setup the pipeline layout, including things like shader uniforms
+    let push_constant_ranges = &[*frag_push_constant_range];
+    let set_layouts = &[
+        data.descriptor_set_layout,
+        data.texture_descriptor_set_layout,
+        data.joint_descriptor_set_layout,
+    ];
+    let layout_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(set_layouts)
+        .push_constant_ranges(push_constant_ranges);
+
+    data.pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+
+    // The point size is baked into the point shader as a specialization
+    // constant
+    let point_size_entry = vk::SpecializationMapEntry::builder()
+        .constant_id(0)
+        .offset(0)
+        .size(size_of::<f32>());
+    let point_size_bytes = data.point_size.to_ne_bytes();
+    let points_specialization = vk::SpecializationInfo::builder()
+        .map_entries(std::slice::from_ref(&point_size_entry))
+        .data(&point_size_bytes);
+
+    let pipelines = create_graphics_pipelines(
+        device,
+        data,
+        &[
+            PipelineDesc::new::<(Vertex, InstanceData)>(&vert[..]),
+            // Skinned vertices also say which joints move them
+            PipelineDesc::new::<(SkinnedVertex, InstanceData)>(&skinned_vert[..]),
+            // Point clouds are drawn with one point per index
+            PipelineDesc::new::<(Vertex, InstanceData)>(&points_vert[..])
+                .topology(vk::PrimitiveTopology::POINT_LIST)
+                .specialization(&points_specialization),
+            PipelineDesc::new::<(QuantizedVertex, InstanceData)>(&quantized_vert[..]),
+            PipelineDesc::new::<(PositionVertex, InstanceData)>(&position_vert[..]),
+            // Lines are drawn with one line per pair of indices
+            PipelineDesc::new::<(ColoredVertex, InstanceData)>(&lines_vert[..])
+                .topology(vk::PrimitiveTopology::LINE_LIST),
+        ],
+    )?;
+    data.pipeline = pipelines[0];
+    data.skinned_pipeline = pipelines[1];
+    data.point_pipeline = pipelines[2];
+    data.quantized_pipeline = pipelines[3];
+    data.position_pipeline = pipelines[4];
+    data.line_pipeline = pipelines[5];
+
+    Ok(())
+}
+
+/// Create a graphics pipeline for each description, all using
+/// [`AppData::pipeline_layout`], the same fragment shader, and the same fixed
+/// function state apart from input assembly.
+pub(crate) unsafe fn create_graphics_pipelines(
+    device: &Device,
+    data: &AppData,
+    descs: &[PipelineDesc],
+) -> Result<Vec<vk::Pipeline>> {
     let frag = include_bytes!("../../shaders/shader.frag.spv");
 
     // Wrap the bytecode in shader modules
-    let vert_shader_module = create_shader_module(device, &vert[..])?;
     let frag_shader_module = create_shader_module(device, &frag[..])?;
+    let vert_shader_modules = descs
+        .iter()
+        .map(|desc| create_shader_module(device, desc.vertex_shader))
+        .collect::<Result<Vec<_>>>()?;
 
     // Create shader stages
-    let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
-        .stage(vk::ShaderStageFlags::VERTEX)
-        .module(vert_shader_module)
-        .name(CStr::from_bytes_with_nul_unchecked(b"main\0"));
     let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
         .stage(vk::ShaderStageFlags::FRAGMENT)
         .module(frag_shader_module)
-        .name(CStr::from_bytes_with_nul_unchecked(b"main\0"));
+        .name(SHADER_ENTRY_POINT);
+    let stages: Vec<_> = descs
+        .iter()
+        .zip(&vert_shader_modules)
+        .map(|(desc, &module)| {
+            let mut vert_stage = vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(module)
+                .name(SHADER_ENTRY_POINT);
+            if let Some(specialization) = desc.specialization {
+                vert_stage = vert_stage.specialization_info(specialization);
+            }
+            [*vert_stage, *frag_stage]
+        })
+        .collect();
 
     // Set up vertex buffers, vertex attributes, and so on.
-    let binding_descriptions = &[Vertex::binding_description()];
-    let attribute_descriptions = Vertex::attribute_descriptions();
-    let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
-        .vertex_binding_descriptions(binding_descriptions)
-        .vertex_attribute_descriptions(&attribute_descriptions);
+    let vertex_input_states: Vec<_> = descs
+        .iter()
+        .map(|desc| {
+            vk::PipelineVertexInputStateCreateInfo::builder()
+                .vertex_binding_descriptions(&desc.bindings)
+                .vertex_attribute_descriptions(&desc.attributes)
+        })
+        .collect();
 
-    // Vertices will be assembled into regular-old triangles.
-    let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
-        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
-        .primitive_restart_enable(false);
+    let input_assembly_states: Vec<_> = descs
+        .iter()
+        .map(|desc| {
+            vk::PipelineInputAssemblyStateCreateInfo::builder()
+                .topology(desc.topology)
+                .primitive_restart_enable(false)
+        })
+        .collect();
 
     // Take up the entire rendering surface for the viewport
     let viewport = vk::Viewport::builder()
@@ -216,54 +392,43 @@ pub(crate) unsafe fn create_pipeline(device: &Device, data: &mut AppData) -> Res
         .attachments(std::slice::from_ref(&attachment))
         .blend_constants([0.0, 0.0, 0.0, 0.0]);
 
-    // Tell the pipeline about our push constants
-    let vert_push_constant_range = vk::PushConstantRange::builder()
-        .stage_flags(vk::ShaderStageFlags::VERTEX)
-        .offset(0)
-        .size(std::mem::size_of::<MvpMatPushConstants>() as u32);
-
-    let frag_push_constant_range = vk::PushConstantRange::builder()
-        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
-        .offset(std::mem::size_of::<MvpMatPushConstants>() as u32)
-        .size(std::mem::size_of::<f32>() as u32); // for opacity as a 4-byte float
-
-    // Setup the pipeline layout, including things like shader uniforms
-    let push_constant_ranges = &[*vert_push_constant_range, *frag_push_constant_range];
-    let layout_info = vk::PipelineLayoutCreateInfo::builder()
-        .set_layouts(std::slice::from_ref(&data.descriptor_set_layout))
-        .push_constant_ranges(push_constant_ranges);
-
-    data.pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+    // Finalize the graphics pipelines
+    let infos: Vec<_> = stages
+        .iter()
+        .zip(&vertex_input_states)
+        .zip(&input_assembly_states)
+        .map(|((stages, vertex_input_state), input_assembly_state)| {
+            *vk::GraphicsPipelineCreateInfo::builder()
+                // Shader stages
+                .stages(stages)
+                // Fixed function stage configurations
+                .vertex_input_state(vertex_input_state)
+                .input_assembly_state(input_assembly_state)
+                .viewport_state(&viewport_state)
+                .rasterization_state(&rasterization_state)
+                .multisample_state(&multisample_state)
+                .depth_stencil_state(&depth_stencil_state)
+                .color_blend_state(&color_blend_state)
+                // Pipeline layout
+                .layout(data.pipeline_layout)
+                // Render pass and subpass
+                .render_pass(data.render_pass)
+                .subpass(0)
+        })
+        .collect();
 
-    // Finalize the graphics pipeline
-    let stages = &[*vert_stage, *frag_stage];
-    let info = vk::GraphicsPipelineCreateInfo::builder()
-        // Shader stages
-        .stages(stages)
-        // Fixed function stage configurations
-        .vertex_input_state(&vertex_input_state)
-        .input_assembly_state(&input_assembly_state)
-        .viewport_state(&viewport_state)
-        .rasterization_state(&rasterization_state)
-        .multisample_state(&multisample_state)
-        .depth_stencil_state(&depth_stencil_state)
-        .color_blend_state(&color_blend_state)
-        // Pipeline layout
-        .layout(data.pipeline_layout)
-        // Render pass and subpass
-        .render_pass(data.render_pass)
-        .subpass(0);
-
-    data.pipeline = device
-        .create_graphics_pipelines(vk::PipelineCache::null(), &[*info], None)
+    let pipelines = device
+        .create_graphics_pipelines(vk::PipelineCache::null(), &infos, None)
         // If there's an error code, just get rid of it cause it's *probably* fine
-        .unwrap_or_else(|(p, _)| p)[0];
+        .unwrap_or_else(|(p, _)| p);
 
     // Destroy the shader modules
-    device.destroy_shader_module(vert_shader_module, None);
+    for module in vert_shader_modules {
+        device.destroy_shader_module(module, None);
+    }
     device.destroy_shader_module(frag_shader_module, None);
 
-    Ok(())
+    Ok(pipelines)
 }
 
 /// Create a shader module from SPIR-V shader bytecode and a GPU.
@@ -4,7 +4,7 @@ use super::devices::{PhysicalDeviceSuitabilityError, QueueFamilyIndices};
 use crate::app::AppData;
 use ash::{extensions::khr as vk_khr, vk, Device, Entry, Instance};
 use color_eyre::Result;
-use tracing::debug;
+use tracing::{debug, warn};
 use winit::window::Window;
 
 use super::texture::create_image_view;
@@ -22,7 +22,7 @@ pub(crate) unsafe fn create_swapchain(
     let swapchain_support = SwapchainSupport::get(entry, instance, data, data.physical_device)?;
 
     let surface_format = swapchain_support.get_preferred_surface_format();
-    let present_mode = swapchain_support.get_preferred_present_mode();
+    let present_mode = swapchain_support.get_preferred_present_mode(data.preferred_present_mode);
     let extent = swapchain_support.get_swapchain_extent(window);
 
     // Decide on the number of images to include in the swapchain. We choose
@@ -51,12 +51,24 @@ pub(crate) unsafe fn create_swapchain(
         vk::SharingMode::EXCLUSIVE
     };
 
+    // Allow copying from swapchain images if we can, so that screenshots of
+    // presented frames can be taken.
+    let mut image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT;
+    if swapchain_support
+        .capabilities
+        .supported_usage_flags
+        .contains(vk::ImageUsageFlags::TRANSFER_SRC)
+    {
+        image_usage |= vk::ImageUsageFlags::TRANSFER_SRC;
+    }
+
     debug!(
         image_format = ?surface_format.format,
         image_color_space = ?surface_format.color_space,
         image_extent = ?extent,
         ?present_mode,
         ?image_sharing_mode,
+        ?image_usage,
         "Selected swapchain creation properties"
     );
 
@@ -68,7 +80,7 @@ pub(crate) unsafe fn create_swapchain(
         .image_color_space(surface_format.color_space)
         .image_extent(extent)
         .image_array_layers(1) // 1 layer per image; we aren't doing stereo 3D
-        .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT) // write directly to images
+        .image_usage(image_usage) // write directly to images
         .image_sharing_mode(image_sharing_mode)
         .queue_family_indices(&queue_family_indices)
         .pre_transform(swapchain_support.capabilities.current_transform)
@@ -83,6 +95,7 @@ pub(crate) unsafe fn create_swapchain(
     data.swapchain_images = swapchain_ext.get_swapchain_images(data.swapchain)?;
     data.swapchain_format = surface_format.format;
     data.swapchain_extent = extent;
+    data.swapchain_image_usage = image_usage;
 
     Ok(())
 }
@@ -169,10 +182,21 @@ impl SwapchainSupport {
 
     /// Get the preferred presentation mode.
     ///
-    /// If supported, this function will select VK_PRESENT_MODE_MAILBOX_KHR.
-    /// Otherwise it will select VK_PRESENT_MODE_FIFO_KHR, which is gauranteed
+    /// If a mode is `requested` and supported, this function will select it.
+    /// Otherwise, if supported, it will select VK_PRESENT_MODE_MAILBOX_KHR.
+    /// Failing that it will select VK_PRESENT_MODE_FIFO_KHR, which is gauranteed
     /// to always be available.
-    fn get_preferred_present_mode(&self) -> vk::PresentModeKHR {
+    fn get_preferred_present_mode(
+        &self,
+        requested: Option<vk::PresentModeKHR>,
+    ) -> vk::PresentModeKHR {
+        if let Some(requested) = requested {
+            if self.present_modes.contains(&requested) {
+                return requested;
+            }
+            warn!(?requested, "Requested present mode isn't supported");
+        }
+
         self.present_modes
             .iter()
             .find(|m| **m == vk::PresentModeKHR::MAILBOX)
@@ -1,18 +1,18 @@
 //! Low-level functions for working with textures. This helps drive the material
 //! system.
 
-use std::{fmt::Debug, fs::File, path::Path, ptr};
+use std::{fmt::Debug, fs::File, path::Path};
 
 use ash::{vk, Device, Instance};
 use color_eyre::{eyre::eyre, Result};
 use tracing::debug;
 
-use crate::app::AppData;
+use crate::{app::AppData, model};
 
 use super::{
-    buffers::create_buffer,
     commands::{begin_transient_commands, end_transient_commands},
     memory::get_memory_type_index,
+    uploads::{create_staging_buffer, Upload},
 };
 
 /// Create a view into an image.
@@ -61,12 +61,30 @@ pub unsafe fn create_texture_image_view(
     )
 }
 
-/// Load a PNG image as a texture.
-///
-/// Returns a Vulkan handle to the created image object and a handle to the
-/// device memory used to allocate it, the Vulkan format of the
-/// texture (for later reference), and finally the number of mip levels to
-/// generate for the image.
+/// A texture image on the GPU, along with a view for sampling it in shaders.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Texture {
+    pub image: vk::Image,
+    pub image_memory: vk::DeviceMemory,
+    pub format: vk::Format,
+    pub view: vk::ImageView,
+    /// The count of mip-map levels for the texture.
+    pub mip_levels: u32,
+}
+
+/// A decoded PNG image, ready to be uploaded with
+/// [`create_texture_from_pixels()`].
+#[derive(Clone, Debug)]
+pub struct PngImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: vk::Format,
+    /// Tightly-packed rows of pixels in `format`.
+    pub pixels: Vec<u8>,
+}
+
+/// Read and decode a PNG image. This doesn't touch the GPU, so it can be done
+/// on any thread.
 ///
 /// # Notes
 ///
@@ -82,12 +100,7 @@ pub unsafe fn create_texture_image_view(
 /// images too; it is assumed that the single grayscale format is encoded
 /// nonlinearly as if it were an sRGB image.
 #[tracing::instrument(level = "DEBUG", skip_all, fields(path = ?path))]
-pub unsafe fn create_texture_image<P>(
-    instance: &Instance,
-    device: &Device,
-    data: &mut AppData,
-    path: P,
-) -> Result<(vk::Image, vk::DeviceMemory, vk::Format, u32)>
+pub fn read_png<P>(path: P) -> Result<PngImage>
 where
     P: AsRef<Path> + Debug,
 {
@@ -99,56 +112,89 @@ where
 
     let mut reader = decoder.read_info()?;
     let mut pixels = vec![0; reader.output_buffer_size()];
-    let size = reader.output_buffer_size() as u64;
 
     let img_info = reader.next_frame(&mut pixels)?;
     let vk_format = get_vulkan_image_format(img_info.color_type, img_info.bit_depth);
 
-    // Calculate the number of mip levels for the image based on how many times
-    // the largest dimension can be divded in two.
-    let mip_levels = (img_info.width.max(img_info.height) as f32).log2().floor() as u32 + 1;
-
     debug!(
         ?path,
         width = img_info.width,
         height = img_info.height,
-        size,
+        size = pixels.len(),
         line_size = img_info.line_size,
         color_type = ?img_info.color_type,
         bit_depth = ?img_info.bit_depth,
         vk_format = ?vk_format,
-        mip_levels,
         "Successfully read image"
     );
 
-    // Copy the image into a host-visible staging buffer
-    let (staging_buffer, staging_buffer_memory) = create_buffer(
+    Ok(PngImage {
+        width: img_info.width,
+        height: img_info.height,
+        format: vk_format,
+        pixels,
+    })
+}
+
+/// Upload an image decoded by a model loader as a texture, ready for sampling
+/// in fragment shaders once `upload` finishes. The image is assumed to hold
+/// sRGB colors.
+///
+/// Destroy the texture with [`destroy_texture()`] when done with it.
+#[tracing::instrument(level = "DEBUG", skip_all)]
+pub unsafe fn create_texture_from_image(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    upload: &mut Upload,
+    image: &model::Image,
+) -> Result<Texture> {
+    create_texture_from_pixels(
         instance,
         device,
         data,
-        size,
-        vk::BufferUsageFlags::TRANSFER_SRC,
-        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
-    )?;
+        upload,
+        image.width,
+        image.height,
+        vk::Format::R8G8B8A8_SRGB,
+        &image.pixels,
+    )
+}
 
-    {
-        // scope the mapped memory handle for safety
-        let memory =
-            device.map_memory(staging_buffer_memory, 0, size, vk::MemoryMapFlags::empty())?;
-        ptr::copy_nonoverlapping(pixels.as_ptr(), memory.cast(), pixels.len());
-        device.unmap_memory(staging_buffer_memory);
-    }
+/// Upload a texture from tightly-packed rows of pixels in the given format,
+/// and generate its mipmaps. The texture is ready for sampling in fragment
+/// shaders once `upload` finishes.
+///
+/// Destroy the texture with [`destroy_texture()`] when done with it.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(level = "DEBUG", skip_all)]
+pub unsafe fn create_texture_from_pixels(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    upload: &mut Upload,
+    width: u32,
+    height: u32,
+    format: vk::Format,
+    pixels: &[u8],
+) -> Result<Texture> {
+    // Calculate the number of mip levels for the image based on how many times
+    // the largest dimension can be divded in two.
+    let mip_levels = (width.max(height) as f32).log2().floor() as u32 + 1;
+
+    // Copy the image into a host-visible staging buffer
+    let staging_buffer = create_staging_buffer(instance, device, data, upload, pixels)?;
 
     // Build the image object and allocate memory
-    let (texture_image, texture_image_memory) = create_image(
+    let (image, image_memory) = create_image(
         instance,
         device,
         data,
-        img_info.width,
-        img_info.height,
+        width,
+        height,
         mip_levels,
         vk::SampleCountFlags::TYPE_1,
-        vk_format,
+        format,
         vk::ImageTiling::OPTIMAL,
         vk::ImageUsageFlags::SAMPLED
             | vk::ImageUsageFlags::TRANSFER_DST
@@ -157,11 +203,11 @@ where
     )?;
 
     // Prepare the image to be a copy destination
-    transition_image_layout(
+    record_image_layout_transition(
         device,
-        data,
-        texture_image,
-        vk_format,
+        upload.command_buffer,
+        image,
+        format,
         mip_levels,
         vk::ImageLayout::UNDEFINED,
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
@@ -170,30 +216,135 @@ where
     // Copy data from the staging buffer to the image object
     copy_buffer_to_image(
         device,
-        data,
+        upload.command_buffer,
         staging_buffer,
-        texture_image,
-        img_info.width,
-        img_info.height,
-    )?;
+        image,
+        width,
+        height,
+    );
 
     // Generate image mipmaps and transition image for fragment shader use
     generate_mipmaps(
         instance,
         device,
         data,
-        texture_image,
-        vk_format,
-        img_info.width,
-        img_info.height,
+        upload.command_buffer,
+        image,
+        format,
+        width,
+        height,
         mip_levels,
     )?;
 
-    // Clean up the staging buffer
-    device.destroy_buffer(staging_buffer, None);
-    device.free_memory(staging_buffer_memory, None);
+    let view = create_texture_image_view(device, image, format, mip_levels)?;
 
-    Ok((texture_image, texture_image_memory, vk_format, mip_levels))
+    Ok(Texture {
+        image,
+        image_memory,
+        format,
+        view,
+        mip_levels,
+    })
+}
+
+/// Upload a texture whose mip levels have already been generated, such as one
+/// from a [bundle](crate::bundle), ready for sampling in fragment shaders once
+/// `upload` finishes. Each level must be tightly-packed pixels in `format`,
+/// starting with the full-size image and halving in size down to 1x1.
+///
+/// Destroy the texture with [`destroy_texture()`] when done with it.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(level = "DEBUG", skip_all)]
+pub unsafe fn create_texture_from_mips(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    upload: &mut Upload,
+    width: u32,
+    height: u32,
+    format: vk::Format,
+    levels: &[&[u8]],
+) -> Result<Texture> {
+    let mip_levels = levels.len() as u32;
+
+    // Build the image object and allocate memory
+    let (image, image_memory) = create_image(
+        instance,
+        device,
+        data,
+        width,
+        height,
+        mip_levels,
+        vk::SampleCountFlags::TYPE_1,
+        format,
+        vk::ImageTiling::OPTIMAL,
+        vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )?;
+
+    // Copy every level in from its own staging buffer, and transition the
+    // image for fragment shader use
+    record_image_layout_transition(
+        device,
+        upload.command_buffer,
+        image,
+        format,
+        mip_levels,
+        vk::ImageLayout::UNDEFINED,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+    )?;
+
+    for (level, pixels) in levels.iter().enumerate() {
+        let staging_buffer = create_staging_buffer(instance, device, data, upload, pixels)?;
+
+        let subresource = vk::ImageSubresourceLayers::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .mip_level(level as u32)
+            .base_array_layer(0)
+            .layer_count(1);
+        let region = vk::BufferImageCopy::builder()
+            .image_subresource(*subresource)
+            .image_extent(vk::Extent3D {
+                width: (width >> level).max(1),
+                height: (height >> level).max(1),
+                depth: 1,
+            });
+
+        device.cmd_copy_buffer_to_image(
+            upload.command_buffer,
+            staging_buffer,
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[*region],
+        );
+    }
+
+    record_image_layout_transition(
+        device,
+        upload.command_buffer,
+        image,
+        format,
+        mip_levels,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    )?;
+
+    let view = create_texture_image_view(device, image, format, mip_levels)?;
+
+    Ok(Texture {
+        image,
+        image_memory,
+        format,
+        view,
+        mip_levels,
+    })
+}
+
+/// Destroy a texture created by one of the `create_texture_*()` functions.
+pub unsafe fn destroy_texture(device: &Device, texture: &Texture) {
+    device.destroy_image_view(texture.view, None);
+    device.destroy_image(texture.image, None);
+    device.free_memory(texture.image_memory, None);
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -247,7 +398,8 @@ pub unsafe fn create_image(
     Ok((image, image_memory))
 }
 
-/// Transition an image object from one layout to another.
+/// Transition an image object from one layout to another, and wait for the
+/// transition to finish.
 ///
 /// Returns an error if an unimplemented combination of layout transitions is
 /// requested.
@@ -259,6 +411,39 @@ pub unsafe fn transition_image_layout(
     mip_levels: u32,
     old_layout: vk::ImageLayout,
     new_layout: vk::ImageLayout,
+) -> Result<()> {
+    // Start recording commands
+    let command_buffer = begin_transient_commands(device, data)?;
+
+    let result = record_image_layout_transition(
+        device,
+        command_buffer,
+        image,
+        format,
+        mip_levels,
+        old_layout,
+        new_layout,
+    );
+
+    // End recording commands & immediately execute
+    end_transient_commands(device, data, command_buffer)?;
+
+    result
+}
+
+/// Record a transition of an image object from one layout to another into
+/// `command_buffer`.
+///
+/// Returns an error if an unimplemented combination of layout transitions is
+/// requested.
+pub unsafe fn record_image_layout_transition(
+    device: &Device,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    format: vk::Format,
+    mip_levels: u32,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
 ) -> Result<()> {
     let (src_access_mask, dst_access_mask, src_stage_mask, dst_stage_mask) =
         match (old_layout, new_layout) {
@@ -316,9 +501,6 @@ pub unsafe fn transition_image_layout(
         .src_access_mask(src_access_mask)
         .dst_access_mask(dst_access_mask);
 
-    // Start recording commands
-    let command_buffer = begin_transient_commands(device, data)?;
-
     device.cmd_pipeline_barrier(
         command_buffer,
         src_stage_mask,
@@ -329,21 +511,19 @@ pub unsafe fn transition_image_layout(
         &[*barrier],
     );
 
-    // End recording commands & immediately execute
-    end_transient_commands(device, data, command_buffer)?;
-
     Ok(())
 }
 
-/// Copy data from a staging buffer to an image object.
+/// Record a copy of data from a staging buffer to the first mip level of an
+/// image object into `command_buffer`.
 unsafe fn copy_buffer_to_image(
     device: &Device,
-    data: &AppData,
+    command_buffer: vk::CommandBuffer,
     src_buffer: vk::Buffer,
     dst_image: vk::Image,
     width: u32,
     height: u32,
-) -> Result<()> {
+) {
     let subresource = vk::ImageSubresourceLayers::builder()
         .aspect_mask(vk::ImageAspectFlags::COLOR)
         .mip_level(0)
@@ -362,9 +542,6 @@ unsafe fn copy_buffer_to_image(
             depth: 1,
         });
 
-    // Start recording commands
-    let command_buffer = begin_transient_commands(device, data)?;
-
     device.cmd_copy_buffer_to_image(
         command_buffer,
         src_buffer,
@@ -372,18 +549,18 @@ unsafe fn copy_buffer_to_image(
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
         &[*region],
     );
-
-    // End recording commands & immediately execute
-    end_transient_commands(device, data, command_buffer)?;
-
-    Ok(())
 }
 
+/// Record commands into `cmd_buf` that fill every mip level of an image by
+/// repeatedly blitting the level above it, and transition every level for
+/// fragment shader use. The first level must already be filled, and every
+/// level must be in the `TRANSFER_DST_OPTIMAL` layout.
 #[allow(clippy::too_many_arguments)]
 pub unsafe fn generate_mipmaps(
     instance: &Instance,
     device: &Device,
     data: &AppData,
+    cmd_buf: vk::CommandBuffer,
     image: vk::Image,
     format: vk::Format,
     width: u32,
@@ -401,8 +578,6 @@ pub unsafe fn generate_mipmaps(
         ));
     }
 
-    let cmd_buf = begin_transient_commands(device, data)?;
-
     let subresource = vk::ImageSubresourceRange::builder()
         .aspect_mask(vk::ImageAspectFlags::COLOR)
         .base_array_layer(0)
@@ -532,8 +707,6 @@ pub unsafe fn generate_mipmaps(
         &[*barrier],
     );
 
-    end_transient_commands(device, data, cmd_buf)?;
-
     Ok(())
 }
 
@@ -588,8 +761,8 @@ pub unsafe fn create_texture_sampler(device: &Device, data: &AppData) -> Result<
         .address_mode_u(vk::SamplerAddressMode::REPEAT)
         .address_mode_v(vk::SamplerAddressMode::REPEAT)
         .address_mode_w(vk::SamplerAddressMode::REPEAT)
-        .anisotropy_enable(true)
-        .max_anisotropy(16.0)
+        .anisotropy_enable(data.max_sampler_anisotropy.is_some())
+        .max_anisotropy(data.max_sampler_anisotropy.unwrap_or(1.0))
         .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
         .unnormalized_coordinates(false)
         .compare_enable(false)
@@ -597,7 +770,8 @@ pub unsafe fn create_texture_sampler(device: &Device, data: &AppData) -> Result<
         .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
         .mip_lod_bias(0.0)
         .min_lod(0.0)
-        .max_lod(data.mip_levels as f32);
+        // Textures have differing numbers of mip levels, so don't clamp
+        .max_lod(vk::LOD_CLAMP_NONE);
 
     Ok(device.create_sampler(&info, None)?)
 }
@@ -1,17 +1,22 @@
 //! Functions for binding uniform buffer objects to the GPU context.
 
-use std::mem::size_of;
+use std::{mem::size_of, ops::Range};
 
 use ash::{vk, Device, Instance};
 use color_eyre::Result;
+use nalgebra_glm as glm;
 
-use crate::{app::AppData, mvp_matrix::MvpMatUBO};
+use crate::{app::AppData, mvp_matrix::MvpMatUBO, vertex::InstanceData};
 
 use super::buffers::create_buffer;
 
 /// Create descriptor set layouts, describing how shaders can access things like
 /// uniform buffer objects. Call this before creating the pipeline - it needs
 /// this info.
+///
+/// There are three layouts: set 0 holds per-frame data shared by every object,
+/// set 1 holds the texture of the object being drawn, and set 2 holds the
+/// per-frame joint matrices of every skinned object.
 #[tracing::instrument(level = "DEBUG", skip_all)]
 pub unsafe fn create_descriptor_set_layout(device: &Device, data: &mut AppData) -> Result<()> {
     // Bind the model-view-projection matrix for the vertex shader
@@ -21,23 +26,50 @@ pub unsafe fn create_descriptor_set_layout(device: &Device, data: &mut AppData)
         .descriptor_count(1)
         .stage_flags(vk::ShaderStageFlags::VERTEX);
 
+    let info = vk::DescriptorSetLayoutCreateInfo::builder()
+        .bindings(std::slice::from_ref(&mvp_mat_binding));
+
+    data.descriptor_set_layout = device.create_descriptor_set_layout(&info, None)?;
+
     // Bind a combined image sampler for the fragment shader
     let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
-        .binding(1)
+        .binding(0)
         .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
         .descriptor_count(1)
         .stage_flags(vk::ShaderStageFlags::FRAGMENT);
 
-    let bindings = &[*mvp_mat_binding, *sampler_binding];
-    let info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(bindings);
+    let info = vk::DescriptorSetLayoutCreateInfo::builder()
+        .bindings(std::slice::from_ref(&sampler_binding));
 
-    data.descriptor_set_layout = device.create_descriptor_set_layout(&info, None)?;
+    data.texture_descriptor_set_layout = device.create_descriptor_set_layout(&info, None)?;
+
+    // Bind the joint matrix palette for the skinning vertex shader
+    let joints_binding = vk::DescriptorSetLayoutBinding::builder()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::VERTEX);
+
+    let info = vk::DescriptorSetLayoutCreateInfo::builder()
+        .bindings(std::slice::from_ref(&joints_binding));
+
+    data.joint_descriptor_set_layout = device.create_descriptor_set_layout(&info, None)?;
 
     Ok(())
 }
 
+/// Destroy the descriptor set layouts created by
+/// [`create_descriptor_set_layout()`].
+pub unsafe fn destroy_descriptor_set_layout(device: &Device, data: &AppData) {
+    device.destroy_descriptor_set_layout(data.descriptor_set_layout, None);
+    device.destroy_descriptor_set_layout(data.texture_descriptor_set_layout, None);
+    device.destroy_descriptor_set_layout(data.joint_descriptor_set_layout, None);
+}
+
 /// Create as many uniform buffers as there are swapchain images for sending
-/// uniform buffer objects to the GPU during rendering.
+/// uniform buffer objects to the GPU during rendering, along with the joint
+/// buffers created by [`create_joint_buffers()`] and the instance buffers
+/// created by [`create_instance_buffers()`].
 ///
 /// Uniform buffers must be re-created if the swapchain is re-created to ensure
 /// that the number of buffers matches the number of swapchain images.
@@ -68,7 +100,8 @@ pub unsafe fn create_uniform_buffers(
         data.uniform_buffers_memory.push(uniform_buffer_memory);
     }
 
-    Ok(())
+    create_joint_buffers(instance, device, data)?;
+    create_instance_buffers(instance, device, data)
 }
 
 /// Properly deallocate all uniform buffers created by [`create_uniform_buffers()`].
@@ -79,27 +112,121 @@ pub unsafe fn destroy_uniform_buffers(device: &Device, data: &AppData) {
     data.uniform_buffers_memory
         .iter()
         .for_each(|m| device.free_memory(*m, None));
+    destroy_joint_buffers(device, data);
+    destroy_instance_buffers(device, data);
 }
 
-/// Create a memory pool to allocate descriptor sets from.
+/// Create one buffer per swapchain image with room for
+/// [`AppData::joint_count`] joint matrices. Vulkan doesn't allow empty buffers,
+/// so there's always room for one.
+///
+/// Recreate these whenever `joint_count` changes, and point the joint
+/// descriptor sets at the new buffers with [`update_joint_descriptor_sets()`].
+/// De-allocate the old buffers first using [`destroy_joint_buffers()`].
+pub unsafe fn create_joint_buffers(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    data.joint_buffers.clear();
+    data.joint_buffers_memory.clear();
+
+    for _ in 0..data.swapchain_images.len() {
+        let (joint_buffer, joint_buffer_memory) = create_buffer(
+            instance,
+            device,
+            data,
+            joint_buffer_size(data),
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+
+        data.joint_buffers.push(joint_buffer);
+        data.joint_buffers_memory.push(joint_buffer_memory);
+    }
+
+    Ok(())
+}
+
+/// Deallocate the joint buffers created by [`create_joint_buffers()`].
+pub unsafe fn destroy_joint_buffers(device: &Device, data: &AppData) {
+    data.joint_buffers
+        .iter()
+        .for_each(|b| device.destroy_buffer(*b, None));
+    data.joint_buffers_memory
+        .iter()
+        .for_each(|m| device.free_memory(*m, None));
+}
+
+/// The size of each of the buffers of joint matrices, in bytes.
+pub fn joint_buffer_size(data: &AppData) -> vk::DeviceSize {
+    (data.joint_count.max(1) * size_of::<glm::Mat4>()) as vk::DeviceSize
+}
+
+/// Create one instance-rate vertex buffer per swapchain image with room for
+/// [`AppData::instance_count`] instances, which is enough for every object in
+/// the scene. Like joint buffers, there's always room for one.
+pub unsafe fn create_instance_buffers(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+) -> Result<()> {
+    data.instance_buffers.clear();
+    data.instance_buffers_memory.clear();
+
+    for _ in 0..data.swapchain_images.len() {
+        let (instance_buffer, instance_buffer_memory) = create_buffer(
+            instance,
+            device,
+            data,
+            instance_buffer_size(data),
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )?;
+
+        data.instance_buffers.push(instance_buffer);
+        data.instance_buffers_memory.push(instance_buffer_memory);
+    }
+
+    Ok(())
+}
+
+/// Deallocate the instance buffers created by [`create_instance_buffers()`].
+pub unsafe fn destroy_instance_buffers(device: &Device, data: &AppData) {
+    data.instance_buffers
+        .iter()
+        .for_each(|b| device.destroy_buffer(*b, None));
+    data.instance_buffers_memory
+        .iter()
+        .for_each(|m| device.free_memory(*m, None));
+}
+
+/// The size of each of the instance buffers, in bytes.
+pub fn instance_buffer_size(data: &AppData) -> vk::DeviceSize {
+    (data.instance_count.max(1) * size_of::<InstanceData>()) as vk::DeviceSize
+}
+
+/// Create a memory pool to allocate the per-frame descriptor sets from.
 ///
 /// Dependent on the number of swapchain images created, so recreate this pool
 /// if you recreate the swapchain. Make sure to deallocate the pre-exisiting
 /// descriptor pool with [`destroy_descriptor_pool()`] first.
 #[tracing::instrument(level = "DEBUG", skip_all)]
 pub unsafe fn create_descriptor_pool(device: &Device, data: &mut AppData) -> Result<()> {
+    let ubo_count = data.swapchain_images.len() as u32;
+
     let ubo_size = vk::DescriptorPoolSize::builder()
         .ty(vk::DescriptorType::UNIFORM_BUFFER)
-        .descriptor_count(data.swapchain_images.len() as u32);
+        .descriptor_count(ubo_count);
 
-    let sampler_size = vk::DescriptorPoolSize::builder()
-        .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-        .descriptor_count(data.swapchain_images.len() as u32);
+    let joints_size = vk::DescriptorPoolSize::builder()
+        .ty(vk::DescriptorType::STORAGE_BUFFER)
+        .descriptor_count(ubo_count);
 
-    let pool_sizes = &[*ubo_size, *sampler_size];
+    let pool_sizes = &[*ubo_size, *joints_size];
     let info = vk::DescriptorPoolCreateInfo::builder()
         .pool_sizes(pool_sizes)
-        .max_sets(data.swapchain_images.len() as u32);
+        .max_sets(ubo_count * 2);
 
     data.descriptor_pool = device.create_descriptor_pool(&info, None)?;
 
@@ -114,8 +241,9 @@ pub unsafe fn destroy_descriptor_pool(device: &Device, data: &AppData) {
 /// Create descriptor sets for sending to the GPU. Requires a descriptor pool
 /// allocated by [`create_descriptor_pool()`].
 ///
-/// Creates one descriptor set per swapchain image, all with the same layout.
-/// Descriptor sets must be recreated if the swapchain is recreated.
+/// Creates one descriptor set per swapchain image for the model-view-projection
+/// matrix, and one per swapchain image for the joint matrices. Descriptor sets
+/// must be recreated if the swapchain is recreated.
 ///
 /// Descriptor sets will be automatically freed when the descriptor pool is
 /// freed with [`destroy_descriptor_pool()`].
@@ -144,21 +272,106 @@ pub unsafe fn create_descriptor_sets(device: &Device, data: &mut AppData) -> Res
             .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
             .buffer_info(std::slice::from_ref(&info));
 
+        device.update_descriptor_sets(&[*mvp_mat_write], &[] as _);
+    }
+
+    let layouts = vec![data.joint_descriptor_set_layout; data.swapchain_images.len()];
+    let info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(data.descriptor_pool)
+        .set_layouts(&layouts);
+
+    data.joint_descriptor_sets = device.allocate_descriptor_sets(&info)?;
+    update_joint_descriptor_sets(device, data);
+
+    Ok(())
+}
+
+/// Point each of the joint descriptor sets at its joint buffer. None of the
+/// sets can be in use by the GPU.
+pub unsafe fn update_joint_descriptor_sets(device: &Device, data: &AppData) {
+    for (&buffer, &set) in data.joint_buffers.iter().zip(&data.joint_descriptor_sets) {
+        // Define access to the joint matrices
+        let info = vk::DescriptorBufferInfo::builder()
+            .buffer(buffer)
+            .offset(0)
+            .range(vk::WHOLE_SIZE);
+
+        let joints_write = vk::WriteDescriptorSet::builder()
+            .dst_set(set)
+            .dst_binding(0)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(std::slice::from_ref(&info));
+
+        device.update_descriptor_sets(&[*joints_write], &[] as _);
+    }
+}
+
+/// Create descriptor sets for a range of [`AppData::textures`], once they've
+/// finished uploading, from a new pool that's just big enough for them.
+/// Returns the pool, which is null if the range is empty.
+///
+/// Textures don't depend on the swapchain, so these sets outlive it. The pool
+/// is kept in [`AppData::texture_descriptor_pools`] and destroyed with
+/// [`destroy_texture_descriptor_pools()`]. Textures without a descriptor set
+/// have a null set in [`AppData::texture_descriptor_sets`].
+#[tracing::instrument(level = "DEBUG", skip_all)]
+pub unsafe fn create_texture_descriptor_sets(
+    device: &Device,
+    data: &mut AppData,
+    textures: Range<usize>,
+) -> Result<vk::DescriptorPool> {
+    if textures.end > data.texture_descriptor_sets.len() {
+        data.texture_descriptor_sets
+            .resize(textures.end, vk::DescriptorSet::null());
+    }
+    if textures.is_empty() {
+        return Ok(vk::DescriptorPool::null());
+    }
+
+    let count = textures.len() as u32;
+    let sampler_size = vk::DescriptorPoolSize::builder()
+        .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        .descriptor_count(count);
+    let info = vk::DescriptorPoolCreateInfo::builder()
+        .pool_sizes(std::slice::from_ref(&sampler_size))
+        .max_sets(count);
+
+    let pool = device.create_descriptor_pool(&info, None)?;
+    data.texture_descriptor_pools.push(pool);
+
+    let layouts = vec![data.texture_descriptor_set_layout; textures.len()];
+    let info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(pool)
+        .set_layouts(&layouts);
+
+    let sets = device.allocate_descriptor_sets(&info)?;
+
+    for (i, set) in textures.zip(sets) {
         // Define access to the combined image sampler
         let info = vk::DescriptorImageInfo::builder()
             .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-            .image_view(data.texture_image_view)
+            .image_view(data.textures[i].view)
             .sampler(data.texture_sampler);
 
         let sampler_write = vk::WriteDescriptorSet::builder()
-            .dst_set(data.descriptor_sets[i])
-            .dst_binding(1)
+            .dst_set(set)
+            .dst_binding(0)
             .dst_array_element(0)
             .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
             .image_info(std::slice::from_ref(&info));
 
-        device.update_descriptor_sets(&[*mvp_mat_write, *sampler_write], &[] as _);
+        device.update_descriptor_sets(&[*sampler_write], &[] as _);
+        data.texture_descriptor_sets[i] = set;
     }
 
-    Ok(())
+    Ok(pool)
+}
+
+/// Destroy the pools created by [`create_texture_descriptor_sets()`], freeing
+/// their descriptor sets.
+pub unsafe fn destroy_texture_descriptor_pools(device: &Device, data: &AppData) {
+    data.texture_descriptor_pools
+        .iter()
+        .for_each(|p| device.destroy_descriptor_pool(*p, None));
 }
@@ -0,0 +1,104 @@
+//! Uploading assets to the GPU without waiting for the uploads to finish.
+//!
+//! Each asset's copy commands are recorded into an [`Upload`] with
+//! [`begin_upload()`], and submitted with [`submit_upload()`]. The GPU then
+//! runs them while the CPU carries on rendering. Once [`is_upload_finished()`]
+//! says an upload's fence has been signalled, the asset can be drawn, and the
+//! upload destroyed with [`destroy_upload()`].
+
+use std::{mem::size_of_val, ptr};
+
+use ash::{vk, Device, Instance};
+use color_eyre::Result;
+
+use crate::app::AppData;
+
+use super::{buffers::create_buffer, commands::begin_transient_commands};
+
+/// Commands that copy assets into GPU memory, recorded into a single command
+/// buffer, along with the staging buffers they copy from.
+#[derive(Clone, Debug, Default)]
+pub struct Upload {
+    /// Allocated from [`AppData::transient_command_pool`].
+    pub command_buffer: vk::CommandBuffer,
+    /// Signalled when the GPU has finished the upload. Null until the upload
+    /// is submitted.
+    pub fence: vk::Fence,
+    /// Kept alive until the upload has finished.
+    staging_buffers: Vec<(vk::Buffer, vk::DeviceMemory)>,
+}
+
+/// Start recording an upload.
+pub unsafe fn begin_upload(device: &Device, data: &AppData) -> Result<Upload> {
+    Ok(Upload {
+        command_buffer: begin_transient_commands(device, data)?,
+        ..Default::default()
+    })
+}
+
+/// Copy `contents` into a new host-visible staging buffer, for the upload's
+/// commands to copy from. The buffer is destroyed along with the upload.
+pub unsafe fn create_staging_buffer<T>(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    upload: &mut Upload,
+    contents: &[T],
+) -> Result<vk::Buffer> {
+    let size = size_of_val(contents) as u64;
+
+    let (buffer, memory) = create_buffer(
+        instance,
+        device,
+        data,
+        size,
+        vk::BufferUsageFlags::TRANSFER_SRC,
+        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+    )?;
+    upload.staging_buffers.push((buffer, memory));
+
+    {
+        // keep the memory map pointer inside this scope to avoid use-after-free
+        let mapped = device.map_memory(memory, 0, size, vk::MemoryMapFlags::empty())?;
+        ptr::copy_nonoverlapping(contents.as_ptr(), mapped.cast(), contents.len());
+        device.unmap_memory(memory);
+    }
+
+    Ok(buffer)
+}
+
+/// Stop recording an upload, and submit it to the GPU without waiting for it
+/// to finish.
+pub unsafe fn submit_upload(device: &Device, data: &AppData, upload: &mut Upload) -> Result<()> {
+    device.end_command_buffer(upload.command_buffer)?;
+
+    upload.fence = device.create_fence(&vk::FenceCreateInfo::default(), None)?;
+
+    let command_buffers = &[upload.command_buffer];
+    let info = vk::SubmitInfo::builder().command_buffers(command_buffers);
+    device.queue_submit(data.graphics_queue, &[*info], upload.fence)?;
+
+    Ok(())
+}
+
+/// Whether the GPU has finished a submitted upload.
+pub unsafe fn is_upload_finished(device: &Device, upload: &Upload) -> Result<bool> {
+    Ok(device.get_fence_status(upload.fence)?)
+}
+
+/// Block until the GPU has finished a submitted upload.
+pub unsafe fn wait_for_upload(device: &Device, upload: &Upload) -> Result<()> {
+    device.wait_for_fences(&[upload.fence], true, u64::MAX)?;
+    Ok(())
+}
+
+/// Free an upload's staging buffers, fence, and command buffer. The upload
+/// must have finished, or never have been submitted.
+pub unsafe fn destroy_upload(device: &Device, data: &AppData, upload: &Upload) {
+    for &(buffer, memory) in &upload.staging_buffers {
+        device.destroy_buffer(buffer, None);
+        device.free_memory(memory, None);
+    }
+    device.destroy_fence(upload.fence, None);
+    device.free_command_buffers(data.transient_command_pool, &[upload.command_buffer]);
+}
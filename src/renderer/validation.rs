@@ -5,7 +5,8 @@ use ash::vk;
 use std::{ffi::CStr, os::raw::c_void};
 use tracing::{debug, error, trace, warn};
 
-/// Returns true if Vulkan validation layers should be enabled.
+/// Returns true if Vulkan validation layers should be enabled by default. See
+/// [`AppSettings::validation`](crate::settings::AppSettings::validation).
 ///
 /// Will always return true in builds where `debug_assertions` is enabled.
 /// Otherwise, will only return true if the environment variable
@@ -15,8 +16,7 @@ pub(crate) fn should_enable_validation_layers() -> bool {
     cfg!(debug_assertions) || std::env::var("ENABLE_VULKAN_VALIDATION_LAYERS").is_ok()
 }
 
-/// The default Vulkan validation layer bundle to be used if [`should_enable_validation_layers()`]
-/// returns true.
+/// The default Vulkan validation layer bundle to be used if validation is enabled.
 pub(crate) const VALIDATION_LAYER: VkExtensionName =
     VkExtensionName::from_bytes(b"VK_LAYER_KHRONOS_validation\0");
 
@@ -34,7 +34,7 @@ pub(crate) extern "system" fn vk_debug_callback(
     };
     let message_id = unsafe {
         if data.p_message_id_name.is_null() {
-            CStr::from_bytes_with_nul_unchecked(b"<undefined id>\0")
+            c"<undefined id>"
         } else {
             CStr::from_ptr(data.p_message)
         }
@@ -42,7 +42,7 @@ pub(crate) extern "system" fn vk_debug_callback(
     .to_string_lossy();
     let message = unsafe {
         if data.p_message.is_null() {
-            CStr::from_bytes_with_nul_unchecked(b"<undefined message>\0")
+            c"<undefined message>"
         } else {
             CStr::from_ptr(data.p_message)
         }
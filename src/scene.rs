@@ -0,0 +1,376 @@
+//! Scenes, which describe which meshes and textures to load and where to draw
+//! them.
+//!
+//! Scenes are written in [RON](https://github.com/ron-rs/ron), like so:
+//!
+//! ```ron
+//! Scene(
+//!     camera: (
+//!         eye: (7.0, 0.0, 4.0),
+//!         target: (0.0, 0.0, 0.0),
+//!     ),
+//!     meshes: {
+//!         "room": "../viking-room/viking-room.obj",
+//!     },
+//!     textures: {
+//!         "room": "../viking-room/viking-room.png",
+//!         "statue": "../textures/statue.png",
+//!     },
+//!     objects: [
+//!         (mesh: "room", texture: "room", position: (0.0, -1.25, 0.0), spin: 90.0),
+//!         (mesh: "room", texture: "statue", position: (0.0, 1.25, 0.0), opacity: 0.5),
+//!         (mesh: "room", position: (0.0, 3.75, 0.0), tint: (1.0, 0.5, 0.5)),
+//!     ],
+//! )
+//! ```
+//!
+//! Relative paths are relative to the directory containing the scene file.
+//! The camera and all object fields other than `mesh` are optional. Objects
+//! without a `texture` are drawn with their mesh's own materials, and objects
+//! with a skinned mesh play the clip named by `animation`, or their mesh's
+//! first clip. Without a camera, the scene is framed automatically.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use nalgebra_glm as glm;
+use ron::extensions::Extensions;
+use serde::Deserialize;
+
+use crate::camera::Camera;
+
+/// A scene to render: the meshes and textures to load, the objects to draw
+/// with them, and where to view them from.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scene {
+    /// Where to view the scene from. If `None`, the default camera is moved
+    /// so that it frames the objects.
+    #[serde(default)]
+    pub camera: Option<SceneCamera>,
+    /// Paths to OBJ, glTF, PLY, or STL models, or [bundles](crate::bundle) cooked from
+    /// them, by name.
+    pub meshes: BTreeMap<String, PathBuf>,
+    /// Paths to PNG textures, or [bundles](crate::bundle) cooked from them,
+    /// by name.
+    pub textures: BTreeMap<String, PathBuf>,
+    /// The objects to draw, in drawing order.
+    pub objects: Vec<SceneObject>,
+}
+
+impl Scene {
+    /// Load a scene from a RON file.
+    #[tracing::instrument(level = "DEBUG", skip_all, fields(path = ?path))]
+    pub fn load<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path> + Debug,
+    {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .wrap_err_with(|| format!("Error reading scene file {path:?}"))?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        Self::parse(&source, base_dir)
+            .wrap_err_with(|| format!("Error loading scene file {path:?}"))
+    }
+
+    /// Parse a scene from RON source. Relative mesh and texture paths are
+    /// resolved against `base_dir`.
+    pub fn parse(source: &str, base_dir: &Path) -> Result<Self> {
+        let mut scene: Self = ron::Options::default()
+            .with_default_extension(Extensions::IMPLICIT_SOME)
+            .from_str(source)?;
+
+        for path in scene.meshes.values_mut().chain(scene.textures.values_mut()) {
+            *path = base_dir.join(&path);
+        }
+
+        scene.validate()?;
+
+        Ok(scene)
+    }
+
+    /// A scene with up to four copies of a single model, side by side and
+    /// spinning, each more opaque than the last. This is what the app shows
+    /// when it isn't given a scene file. If `texture_path` is `None`, the
+    /// model is drawn with its own materials.
+    pub fn single_model(model_path: &Path, texture_path: Option<&Path>) -> Self {
+        let objects = (0..4)
+            .map(|i| SceneObject {
+                position: [
+                    0.0,
+                    ((i % 2) as f32 * 2.5) - 1.25,
+                    ((i / 2) as f32 * -2.0) + 1.0,
+                ],
+                opacity: (i + 1) as f32 * 0.25,
+                spin: 90.0,
+                texture: texture_path.map(|_| "model".into()),
+                ..SceneObject::new("model")
+            })
+            .collect();
+
+        Self {
+            camera: None,
+            meshes: BTreeMap::from([("model".into(), model_path.into())]),
+            textures: texture_path
+                .map(|path| ("model".into(), path.into()))
+                .into_iter()
+                .collect(),
+            objects,
+        }
+    }
+
+    /// The position of the mesh called `name` in `meshes`, in iteration order.
+    pub fn mesh_index(&self, name: &str) -> Option<usize> {
+        self.meshes.keys().position(|k| k == name)
+    }
+
+    /// The position of the texture called `name` in `textures`, in iteration
+    /// order.
+    pub fn texture_index(&self, name: &str) -> Option<usize> {
+        self.textures.keys().position(|k| k == name)
+    }
+
+    /// Check that every object refers to meshes and textures that exist.
+    fn validate(&self) -> Result<()> {
+        for (i, object) in self.objects.iter().enumerate() {
+            if !self.meshes.contains_key(&object.mesh) {
+                return Err(eyre!("Object {i} uses unknown mesh {:?}", object.mesh));
+            }
+            if let Some(texture) = &object.texture {
+                if !self.textures.contains_key(texture) {
+                    return Err(eyre!("Object {i} uses unknown texture {texture:?}"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The camera of a [`Scene`]. Fields that are left out take their values from
+/// [`Camera::default()`].
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneCamera {
+    pub eye: [f32; 3],
+    pub target: [f32; 3],
+    pub up: [f32; 3],
+    /// The vertical field of view, in degrees.
+    pub fovy: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for SceneCamera {
+    fn default() -> Self {
+        let camera = Camera::default();
+
+        Self {
+            eye: camera.eye.into(),
+            target: camera.target.into(),
+            up: camera.up.into(),
+            fovy: camera.fovy.to_degrees(),
+            near: camera.near,
+            far: camera.far,
+        }
+    }
+}
+
+impl From<SceneCamera> for Camera {
+    fn from(camera: SceneCamera) -> Self {
+        Self {
+            eye: camera.eye.into(),
+            target: camera.target.into(),
+            up: camera.up.into(),
+            fovy: camera.fovy.to_radians(),
+            near: camera.near,
+            far: camera.far,
+        }
+    }
+}
+
+/// An object in a [`Scene`]: a mesh drawn somewhere in the world.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SceneObject {
+    /// The name of the mesh to draw.
+    pub mesh: String,
+    /// The name of a texture to draw the whole mesh with, instead of the
+    /// mesh's own materials.
+    #[serde(default)]
+    pub texture: Option<String>,
+
+    /// Where to place the object in the world.
+    #[serde(default)]
+    pub position: [f32; 3],
+    /// Rotation about the x, y, and z axes in degrees, applied in that order.
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    /// Scale along the x, y, and z axes, applied before rotating.
+    #[serde(default = "default_scale")]
+    pub scale: [f32; 3],
+
+    /// How opaque the object is, from 0 (invisible) to 1 (fully opaque).
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    /// A color to multiply the object's own colors by.
+    #[serde(default = "default_tint")]
+    pub tint: [f32; 3],
+    /// How fast the object spins about the z-axis, in degrees per second.
+    #[serde(default)]
+    pub spin: f32,
+    /// The name of the animation clip to play, if the mesh is skinned.
+    /// Defaults to the mesh's first clip.
+    #[serde(default)]
+    pub animation: Option<String>,
+}
+
+impl SceneObject {
+    /// An opaque, untransformed object drawn with its mesh's own materials.
+    pub fn new(mesh: &str) -> Self {
+        Self {
+            mesh: mesh.into(),
+            texture: None,
+            position: [0.0; 3],
+            rotation: [0.0; 3],
+            scale: default_scale(),
+            opacity: default_opacity(),
+            tint: default_tint(),
+            spin: 0.0,
+            animation: None,
+        }
+    }
+
+    /// The model matrix placing this object in the world, after it has been
+    /// spinning for `time` seconds.
+    pub fn model_matrix(&self, time: f32) -> glm::Mat4 {
+        let [x, y, z] = self.rotation.map(f32::to_radians);
+        let spin = (self.spin * time).to_radians();
+
+        let rotation = glm::rotation(z + spin, &glm::Vec3::z())
+            * glm::rotation(y, &glm::Vec3::y())
+            * glm::rotation(x, &glm::Vec3::x());
+
+        glm::translation(&self.position.into()) * rotation * glm::scaling(&self.scale.into())
+    }
+}
+
+fn default_scale() -> [f32; 3] {
+    [1.0; 3]
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+fn default_tint() -> [f32; 3] {
+    [1.0; 3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fills_in_defaults_and_resolves_paths() {
+        let scene = Scene::parse(
+            r#"Scene(
+                meshes: { "room": "room.obj", "abs": "/models/abs.obj" },
+                textures: { "room": "textures/room.png" },
+                objects: [(mesh: "room", texture: "room", position: (1.0, 2.0, 3.0))],
+            )"#,
+            Path::new("scenes"),
+        )
+        .unwrap();
+
+        assert_eq!(scene.camera, None);
+        assert_eq!(scene.meshes["room"], Path::new("scenes/room.obj"));
+        assert_eq!(scene.meshes["abs"], Path::new("/models/abs.obj"));
+        assert_eq!(
+            scene.textures["room"],
+            Path::new("scenes/textures/room.png")
+        );
+        assert_eq!(
+            scene.objects,
+            vec![SceneObject {
+                texture: Some("room".into()),
+                position: [1.0, 2.0, 3.0],
+                ..SceneObject::new("room")
+            }]
+        );
+        assert_eq!(scene.mesh_index("room"), Some(1));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_references() {
+        let error = Scene::parse(
+            r#"Scene(
+                meshes: { "room": "room.obj" },
+                textures: { "room": "room.png" },
+                objects: [(mesh: "room", texture: "lava")],
+            )"#,
+            Path::new(""),
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("unknown texture \"lava\""));
+    }
+
+    #[test]
+    fn objects_without_a_texture_use_mesh_materials() {
+        let scene = Scene::parse(
+            r#"Scene(meshes: { "room": "room.obj" }, textures: {}, objects: [(mesh: "room")])"#,
+            Path::new(""),
+        )
+        .unwrap();
+
+        assert_eq!(scene.objects, vec![SceneObject::new("room")]);
+    }
+
+    #[test]
+    fn partial_camera_uses_defaults() {
+        let scene = Scene::parse(
+            "Scene(camera: (fovy: 90.0), meshes: {}, textures: {}, objects: [])",
+            Path::new(""),
+        )
+        .unwrap();
+
+        let camera = Camera::from(scene.camera.unwrap());
+        assert_eq!(camera.eye, Camera::default().eye);
+        assert!((camera.fovy - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn model_matrix_scales_rotates_then_translates() {
+        let object = SceneObject {
+            position: [0.0, 0.0, 1.0],
+            rotation: [0.0, 0.0, 45.0],
+            scale: [2.0, 2.0, 2.0],
+            spin: 45.0,
+            ..SceneObject::new("mesh")
+        };
+
+        // 45 degrees of rotation plus one second of spinning at 45 degrees/s
+        let point = object.model_matrix(1.0) * glm::vec4(1.0, 0.0, 0.0, 1.0);
+        assert!((point - glm::vec4(0.0, 2.0, 1.0, 1.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn example_scene_loads() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/scenes/viking-rooms.ron");
+        let scene = Scene::load(&path).unwrap();
+
+        for path in scene.meshes.values().chain(scene.textures.values()) {
+            assert!(path.exists(), "{path:?} doesn't exist");
+        }
+    }
+}
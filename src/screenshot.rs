@@ -0,0 +1,113 @@
+//! Screenshots of rendered frames, and saving them to PNG files.
+
+use std::{fmt::Debug, fs::File, io::BufWriter, path::Path};
+
+use ash::vk;
+use color_eyre::{eyre::eyre, Result};
+use tracing::debug;
+
+/// A rendered frame, copied back from the GPU.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    /// Tightly-packed rows of 8-bit RGBA pixels in the sRGB color space,
+    /// top row first.
+    pub pixels: Vec<u8>,
+}
+
+impl Screenshot {
+    /// Convert raw pixels read back from an image with the given Vulkan
+    /// `format` into an RGBA screenshot.
+    ///
+    /// Frames are composited opaquely when presented, so the alpha channel of
+    /// every pixel is set to fully opaque.
+    ///
+    /// Only 8-bit RGBA and BGRA formats are supported.
+    pub fn from_raw(
+        width: u32,
+        height: u32,
+        format: vk::Format,
+        mut pixels: Vec<u8>,
+    ) -> Result<Self> {
+        let swizzle = match format {
+            vk::Format::R8G8B8A8_SRGB | vk::Format::R8G8B8A8_UNORM => false,
+            vk::Format::B8G8R8A8_SRGB | vk::Format::B8G8R8A8_UNORM => true,
+            _ => {
+                return Err(eyre!(
+                    "Can't take screenshots of images with format {format:?}"
+                ))
+            }
+        };
+
+        if pixels.len() != (width * height * 4) as usize {
+            return Err(eyre!(
+                "Expected {} bytes of pixel data for a {width}x{height} screenshot, got {}",
+                width * height * 4,
+                pixels.len()
+            ));
+        }
+
+        for pixel in pixels.chunks_exact_mut(4) {
+            if swizzle {
+                pixel.swap(0, 2);
+            }
+            pixel[3] = u8::MAX;
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Write the screenshot to a PNG file.
+    #[tracing::instrument(level = "DEBUG", skip(self))]
+    pub fn save_png<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path> + Debug,
+    {
+        let file = BufWriter::new(File::create(&path)?);
+
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_srgb(png::SrgbRenderingIntent::Perceptual);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+
+        debug!(width = self.width, height = self.height, "Saved screenshot");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bgra_screenshots_are_converted_to_opaque_rgba() {
+        let raw = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let screenshot = Screenshot::from_raw(2, 1, vk::Format::B8G8R8A8_SRGB, raw).unwrap();
+
+        assert_eq!(screenshot.pixels, vec![3, 2, 1, 255, 7, 6, 5, 255]);
+    }
+
+    #[test]
+    fn rgba_screenshots_keep_their_channel_order() {
+        let raw = vec![1, 2, 3, 4];
+        let screenshot = Screenshot::from_raw(1, 1, vk::Format::R8G8B8A8_SRGB, raw).unwrap();
+
+        assert_eq!(screenshot.pixels, vec![1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn unsupported_formats_and_sizes_are_rejected() {
+        assert!(Screenshot::from_raw(1, 1, vk::Format::R16G16B16A16_SFLOAT, vec![0; 8]).is_err());
+        assert!(Screenshot::from_raw(2, 2, vk::Format::R8G8B8A8_SRGB, vec![0; 4]).is_err());
+    }
+}
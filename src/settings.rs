@@ -0,0 +1,146 @@
+//! Settings for creating an [`App`](crate::app::App).
+
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use ash::vk;
+
+use crate::{
+    camera::CameraMode,
+    model::{ModelOptions, NormalGeneration},
+    renderer::validation::should_enable_validation_layers,
+    vertex::VertexFormat,
+};
+
+pub use crate::renderer::devices::DeviceSelector;
+
+/// Options controlling what the app loads and how it renders.
+#[derive(Clone, Debug)]
+pub struct AppSettings {
+    /// Path to a [scene file](crate::scene) describing what to display. If
+    /// set, `model_path` and `texture_path` are ignored.
+    pub scene_path: Option<PathBuf>,
+    /// Path to the OBJ, glTF, PLY, or STL model to display, or a
+    /// [bundle](crate::bundle) cooked from one.
+    pub model_path: PathBuf,
+    /// Path to a PNG texture, or a bundle cooked from one, to apply to the
+    /// whole model. If `None`, the model is drawn with its own materials.
+    pub texture_path: Option<PathBuf>,
+    /// How to generate normals for models that don't have any. Bundles
+    /// already had their normals generated when they were cooked.
+    pub normals: NormalGeneration,
+    /// The most simplified levels of detail to generate for each model, for
+    /// drawing it from further away.
+    pub max_lods: usize,
+    /// How to store the vertices of meshes on the GPU.
+    pub vertex_format: VertexFormat,
+
+    /// Width of the window, or of the offscreen render target when running
+    /// headlessly, in pixels.
+    pub width: u32,
+    /// Height of the window, or of the offscreen render target when running
+    /// headlessly, in pixels.
+    pub height: u32,
+
+    /// The number of samples per pixel to use for multisampled anti-aliasing.
+    /// Must be a power of two of at least 2. If `None`, the maximum supported by the device
+    /// is used. Requests above the device's maximum are clamped.
+    pub msaa_samples: Option<u32>,
+
+    /// The presentation mode to prefer for the swapchain. If `None` or not
+    /// supported, mailbox mode is used if available, and FIFO mode otherwise.
+    pub present_mode: Option<PresentMode>,
+
+    /// The size of the points that point clouds are drawn with, in pixels.
+    /// Clamped to the largest size the device supports, which may be 1.
+    pub point_size: f32,
+
+    /// Enable Vulkan's validation layers.
+    pub validation: bool,
+
+    /// Force the use of a particular physical device, instead of picking the
+    /// best one automatically.
+    pub device: Option<DeviceSelector>,
+
+    /// Reload meshes and textures when their files change on disk.
+    pub watch_assets: bool,
+
+    /// How the camera is moved around with the keyboard and mouse to begin
+    /// with.
+    pub camera_mode: CameraMode,
+}
+
+impl Default for AppSettings {
+    /// Displays the viking room in a 1024x768 window. Validation and device
+    /// selection default to the values given by environment variables.
+    fn default() -> Self {
+        Self {
+            scene_path: None,
+            model_path: "./resources/viking-room/viking-room.obj".into(),
+            texture_path: Some("./resources/viking-room/viking-room.png".into()),
+            normals: NormalGeneration::default(),
+            max_lods: ModelOptions::default().max_lods,
+            vertex_format: VertexFormat::default(),
+            width: 1024,
+            height: 768,
+            msaa_samples: None,
+            present_mode: None,
+            point_size: 1.0,
+            validation: should_enable_validation_layers(),
+            device: DeviceSelector::from_env(),
+            watch_assets: false,
+            camera_mode: CameraMode::default(),
+        }
+    }
+}
+
+/// Swapchain presentation modes. See the Vulkan spec for `VkPresentModeKHR`
+/// for the details of each.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Present images immediately. May tear.
+    Immediate,
+    /// Wait for vertical blank, replacing queued images with newer ones.
+    Mailbox,
+    /// Wait for vertical blank, queueing images. Always supported.
+    Fifo,
+    /// Like FIFO, but presents late images immediately. May tear.
+    FifoRelaxed,
+}
+
+impl From<PresentMode> for vk::PresentModeKHR {
+    fn from(mode: PresentMode) -> Self {
+        match mode {
+            PresentMode::Immediate => Self::IMMEDIATE,
+            PresentMode::Mailbox => Self::MAILBOX,
+            PresentMode::Fifo => Self::FIFO,
+            PresentMode::FifoRelaxed => Self::FIFO_RELAXED,
+        }
+    }
+}
+
+impl FromStr for PresentMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "immediate" => Ok(Self::Immediate),
+            "mailbox" => Ok(Self::Mailbox),
+            "fifo" => Ok(Self::Fifo),
+            "fifo-relaxed" | "fifo_relaxed" => Ok(Self::FifoRelaxed),
+            _ => Err(format!(
+                "Unknown present mode {s:?}, expected one of: immediate, mailbox, fifo, fifo-relaxed"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PresentMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Immediate => "immediate",
+            Self::Mailbox => "mailbox",
+            Self::Fifo => "fifo",
+            Self::FifoRelaxed => "fifo-relaxed",
+        })
+    }
+}
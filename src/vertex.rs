@@ -1,11 +1,96 @@
 //! Vertices to be passed on to the GPU in vertex buffers and such.
 
 use std::hash::{Hash, Hasher};
-use std::mem::size_of;
+use std::mem::{offset_of, size_of};
+use std::{fmt, str::FromStr};
 
 use ash::vk;
 use nalgebra_glm as glm;
 
+use crate::bounds::Aabb;
+
+/// Describes how a `#[repr(C)]` vertex type is read from vertex buffers, so
+/// that a pipeline can be built for it.
+///
+/// A type is usually read from a single interleaved buffer, but can be split
+/// into several streams with one binding each. Pairs of layouts are read from
+/// separate streams too, like `(PositionVertex, JointWeights)` for a skinned
+/// depth-only pass.
+pub trait VertexLayout {
+    /// One description per vertex buffer, telling Vulkan the number of bytes
+    /// between entries and whether to move to the next entry after each
+    /// vertex or after each instance.
+    fn binding_descriptions() -> Vec<vk::VertexInputBindingDescription>;
+
+    /// How to read each attribute, and which binding it's read from.
+    fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription>;
+}
+
+/// The bindings and attributes of `B` come after those of `A`, so `B`'s
+/// binding and location numbers are shifted past `A`'s.
+impl<A: VertexLayout, B: VertexLayout> VertexLayout for (A, B) {
+    fn binding_descriptions() -> Vec<vk::VertexInputBindingDescription> {
+        let first = A::binding_descriptions();
+        let binding_offset = first.len() as u32;
+        let second = B::binding_descriptions().into_iter().map(|binding| {
+            vk::VertexInputBindingDescription {
+                binding: binding.binding + binding_offset,
+                ..binding
+            }
+        });
+
+        first.into_iter().chain(second).collect()
+    }
+
+    fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription> {
+        let first = A::attribute_descriptions();
+        let binding_offset = A::binding_descriptions().len() as u32;
+        let location_offset = first.iter().map(|a| a.location + 1).max().unwrap_or(0);
+        let second = B::attribute_descriptions().into_iter().map(|attribute| {
+            vk::VertexInputAttributeDescription {
+                location: attribute.location + location_offset,
+                binding: attribute.binding + binding_offset,
+                ..attribute
+            }
+        });
+
+        first.into_iter().chain(second).collect()
+    }
+}
+
+/// A binding that reads a `V` from `binding` for each vertex.
+pub const fn per_vertex_binding<V>(binding: u32) -> vk::VertexInputBindingDescription {
+    vk::VertexInputBindingDescription {
+        binding,
+        stride: size_of::<V>() as u32,
+        input_rate: vk::VertexInputRate::VERTEX,
+    }
+}
+
+/// A binding that reads a `V` from `binding` for each instance.
+pub const fn per_instance_binding<V>(binding: u32) -> vk::VertexInputBindingDescription {
+    vk::VertexInputBindingDescription {
+        binding,
+        stride: size_of::<V>() as u32,
+        input_rate: vk::VertexInputRate::INSTANCE,
+    }
+}
+
+/// An attribute at `offset` bytes into each entry of `binding`.
+pub const fn attribute(
+    location: u32,
+    binding: u32,
+    format: vk::Format,
+    offset: usize,
+) -> vk::VertexInputAttributeDescription {
+    vk::VertexInputAttributeDescription {
+        location,
+        binding,
+        format,
+        offset: offset as u32,
+    }
+}
+
 /// A vertex and an associated color to be sent to the GPU.
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
@@ -13,63 +98,67 @@ pub struct Vertex {
     pub pos: glm::Vec3,
     pub color: glm::Vec3,
     pub tex_coord: glm::Vec2,
+    /// The surface normal at this vertex. Loaders leave this at zero when the
+    /// model doesn't have normals, so that [`load_model()`](crate::model::load_model)
+    /// can generate them.
+    pub normal: glm::Vec3,
+    /// The direction of increasing u texture coordinates at this vertex, with
+    /// `w` holding the sign of the bitangent, `cross(normal, tangent.xyz) * w`.
+    /// Left at zero by loaders when the model doesn't have tangents, like
+    /// `normal`.
+    pub tangent: glm::Vec4,
 }
 
 impl Vertex {
-    /// Create a new vertex with an associated color.
+    /// Create a new vertex with an associated color. The tangent is left at
+    /// zero, to be generated later.
     ///
     /// This is marked as constant, but will only actually be usable from
     /// constant contexts once [`nalgebra_glm`] supports compile-time constructors.
-    pub const fn new(pos: glm::Vec3, color: glm::Vec3, tex_coord: glm::Vec2) -> Self {
+    pub const fn new(
+        pos: glm::Vec3,
+        color: glm::Vec3,
+        tex_coord: glm::Vec2,
+        normal: glm::Vec3,
+    ) -> Self {
         Self {
             pos,
             color,
             tex_coord,
+            normal,
+            tangent: glm::Vec4::new(0.0, 0.0, 0.0, 0.0),
         }
     }
+}
 
-    /// Return a descriptor telling Vulkan the number of bytes between data
-    /// entries and whether to move to the next data entry after each vertex
-    /// or after each instance.
-    pub const fn binding_description() -> vk::VertexInputBindingDescription {
-        vk::VertexInputBindingDescription {
-            binding: 0,
-            stride: size_of::<Self>() as u32,
-            input_rate: vk::VertexInputRate::VERTEX,
-        }
+impl VertexLayout for Vertex {
+    fn binding_descriptions() -> Vec<vk::VertexInputBindingDescription> {
+        vec![per_vertex_binding::<Self>(0)]
     }
 
-    /// Return Vulkan attribute descriptions specifying how to access each
-    /// part of a vertex.
-    pub const fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 3] {
-        let pos = vk::VertexInputAttributeDescription {
-            location: 0,
-            binding: 0,
-            format: vk::Format::R32G32B32_SFLOAT,
-            offset: 0,
-        };
-
-        let color = vk::VertexInputAttributeDescription {
-            location: 1,
-            binding: 0,
-            format: vk::Format::R32G32B32_SFLOAT,
-            offset: size_of::<glm::Vec3>() as u32,
-        };
-
-        let tex_coord = vk::VertexInputAttributeDescription {
-            location: 2,
-            binding: 0,
-            format: vk::Format::R32G32_SFLOAT,
-            offset: 2 * size_of::<glm::Vec3>() as u32,
-        };
-
-        [pos, color, tex_coord]
+    fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription> {
+        vec![
+            attribute(0, 0, vk::Format::R32G32B32_SFLOAT, offset_of!(Self, pos)),
+            attribute(1, 0, vk::Format::R32G32B32_SFLOAT, offset_of!(Self, color)),
+            attribute(2, 0, vk::Format::R32G32_SFLOAT, offset_of!(Self, tex_coord)),
+            attribute(3, 0, vk::Format::R32G32B32_SFLOAT, offset_of!(Self, normal)),
+            attribute(
+                4,
+                0,
+                vk::Format::R32G32B32A32_SFLOAT,
+                offset_of!(Self, tangent),
+            ),
+        ]
     }
 }
 
 impl PartialEq for Vertex {
     fn eq(&self, other: &Self) -> bool {
-        self.pos == other.pos && self.color == other.color && self.tex_coord == other.tex_coord
+        self.pos == other.pos
+            && self.color == other.color
+            && self.tex_coord == other.tex_coord
+            && self.normal == other.normal
+            && self.tangent == other.tangent
     }
 }
 
@@ -87,5 +176,513 @@ impl Hash for Vertex {
         self.color[2].to_bits().hash(state);
         self.tex_coord[0].to_bits().hash(state);
         self.tex_coord[1].to_bits().hash(state);
+        self.normal[0].to_bits().hash(state);
+        self.normal[1].to_bits().hash(state);
+        self.normal[2].to_bits().hash(state);
+        self.tangent[0].to_bits().hash(state);
+        self.tangent[1].to_bits().hash(state);
+        self.tangent[2].to_bits().hash(state);
+        self.tangent[3].to_bits().hash(state);
+    }
+}
+
+/// Which joints of a [`Skin`](crate::animation::Skin) move a vertex, and by
+/// how much. The vertex is moved by the weighted sum of up to four joints'
+/// transforms.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct JointWeights {
+    /// Indices into [`Skin::joints`](crate::animation::Skin::joints).
+    pub joints: [u16; 4],
+    /// Add up to 1. Vertices whose weights are all zero aren't moved by the
+    /// skeleton at all.
+    pub weights: [f32; 4],
+}
+
+impl VertexLayout for JointWeights {
+    fn binding_descriptions() -> Vec<vk::VertexInputBindingDescription> {
+        vec![per_vertex_binding::<Self>(0)]
+    }
+
+    fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription> {
+        vec![
+            attribute(
+                0,
+                0,
+                vk::Format::R16G16B16A16_UINT,
+                offset_of!(Self, joints),
+            ),
+            attribute(
+                1,
+                0,
+                vk::Format::R32G32B32A32_SFLOAT,
+                offset_of!(Self, weights),
+            ),
+        ]
+    }
+}
+
+/// Note: Like [`Vertex`], this is only valid without NaN weights.
+impl Eq for JointWeights {}
+
+impl Hash for JointWeights {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.joints.hash(state);
+        for weight in self.weights {
+            weight.to_bits().hash(state);
+        }
+    }
+}
+
+/// A [`Vertex`] of a skinned mesh, which also says which joints move it.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SkinnedVertex {
+    pub vertex: Vertex,
+    pub joint_weights: JointWeights,
+}
+
+/// The same attributes as [`Vertex`], followed by the joint indices and
+/// weights, all in one interleaved stream.
+impl VertexLayout for SkinnedVertex {
+    fn binding_descriptions() -> Vec<vk::VertexInputBindingDescription> {
+        vec![per_vertex_binding::<Self>(0)]
+    }
+
+    fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription> {
+        let joint_weights = JointWeights::attribute_descriptions()
+            .into_iter()
+            .map(|attribute| vk::VertexInputAttributeDescription {
+                location: attribute.location + 5,
+                offset: attribute.offset + offset_of!(Self, joint_weights) as u32,
+                ..attribute
+            });
+
+        Vertex::attribute_descriptions()
+            .into_iter()
+            .chain(joint_weights)
+            .collect()
+    }
+}
+
+/// Just a position, for passes that only need depth, like shadow maps.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PositionVertex {
+    pub pos: glm::Vec3,
+}
+
+impl VertexLayout for PositionVertex {
+    fn binding_descriptions() -> Vec<vk::VertexInputBindingDescription> {
+        vec![per_vertex_binding::<Self>(0)]
+    }
+
+    fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription> {
+        vec![attribute(
+            0,
+            0,
+            vk::Format::R32G32B32_SFLOAT,
+            offset_of!(Self, pos),
+        )]
+    }
+}
+
+/// A position with a color, for lines and other untextured debug geometry.
+/// The attributes are at the same locations as in [`Vertex`].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColoredVertex {
+    pub pos: glm::Vec3,
+    pub color: glm::Vec3,
+}
+
+impl VertexLayout for ColoredVertex {
+    fn binding_descriptions() -> Vec<vk::VertexInputBindingDescription> {
+        vec![per_vertex_binding::<Self>(0)]
+    }
+
+    fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription> {
+        vec![
+            attribute(0, 0, vk::Format::R32G32B32_SFLOAT, offset_of!(Self, pos)),
+            attribute(1, 0, vk::Format::R32G32B32_SFLOAT, offset_of!(Self, color)),
+        ]
+    }
+}
+
+/// Where and how to draw one of the instances of a mesh. Instances are read
+/// from their own instance-rate vertex buffer, after the mesh's vertices, so
+/// that every object drawn with the same mesh can be drawn at once.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InstanceData {
+    /// Places the instance in the world.
+    pub model: glm::Mat4,
+    /// Multiplied with the instance's color, with its opacity in `w`.
+    pub tint: glm::Vec4,
+    /// Where the instance's joint matrices start in the joint buffer, if its
+    /// mesh is skinned.
+    pub first_joint: u32,
+}
+
+impl VertexLayout for InstanceData {
+    fn binding_descriptions() -> Vec<vk::VertexInputBindingDescription> {
+        vec![per_instance_binding::<Self>(0)]
+    }
+
+    /// The model matrix takes up one location per column.
+    fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription> {
+        let model = offset_of!(Self, model);
+        let column = size_of::<glm::Vec4>();
+        vec![
+            attribute(0, 0, vk::Format::R32G32B32A32_SFLOAT, model),
+            attribute(1, 0, vk::Format::R32G32B32A32_SFLOAT, model + column),
+            attribute(2, 0, vk::Format::R32G32B32A32_SFLOAT, model + 2 * column),
+            attribute(3, 0, vk::Format::R32G32B32A32_SFLOAT, model + 3 * column),
+            attribute(
+                4,
+                0,
+                vk::Format::R32G32B32A32_SFLOAT,
+                offset_of!(Self, tint),
+            ),
+            attribute(5, 0, vk::Format::R32_UINT, offset_of!(Self, first_joint)),
+        ]
+    }
+}
+
+/// How the vertices of meshes are stored on the GPU.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VertexFormat {
+    /// Full precision [`Vertex`]es.
+    #[default]
+    Full,
+    /// [`QuantizedVertex`]es, which take less than half the memory. Skinned
+    /// meshes and point clouds are still stored at full precision.
+    Compact,
+}
+
+impl FromStr for VertexFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "full" => Ok(Self::Full),
+            "compact" => Ok(Self::Compact),
+            _ => Err(format!(
+                "Unknown vertex format {s:?}, expected one of: full, compact"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for VertexFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Full => "full",
+            Self::Compact => "compact",
+        })
+    }
+}
+
+/// A compact encoding of a [`Vertex`], at 24 bytes instead of 60.
+///
+/// Positions are stored relative to the mesh's bounding box, so they have to
+/// be scaled back out of it with [`dequantize_matrix()`] before the model
+/// transform.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct QuantizedVertex {
+    /// The position within the bounding box, from 0 to 1 on each axis. The
+    /// fourth component is padding, since hardly any GPUs can read
+    /// three-component 16-bit attributes.
+    pub pos: [u16; 4],
+    /// The color and an opaque alpha, from 0 to 1.
+    pub color: [u8; 4],
+    /// Half floats rather than normalized integers, so that repeating
+    /// textures still work.
+    pub tex_coord: [u16; 2],
+    /// The octahedral encoding of the normal.
+    pub normal: [i16; 2],
+    /// The octahedral encoding of the tangent's direction, followed by the
+    /// sign of the bitangent and padding.
+    pub tangent: [i8; 4],
+}
+
+impl QuantizedVertex {
+    /// Encode a vertex of a mesh with the bounding box `aabb`.
+    pub fn new(vertex: &Vertex, aabb: &Aabb) -> Self {
+        let size = aabb.size();
+        let pos: [u16; 3] = std::array::from_fn(|axis| {
+            if size[axis] > 0.0 {
+                unorm16((vertex.pos[axis] - aabb.min[axis]) / size[axis])
+            } else {
+                0
+            }
+        });
+        let normal = octahedral_encode(&vertex.normal);
+        let tangent = octahedral_encode(&vertex.tangent.xyz());
+
+        Self {
+            pos: [pos[0], pos[1], pos[2], 0],
+            color: [
+                unorm8(vertex.color.x),
+                unorm8(vertex.color.y),
+                unorm8(vertex.color.z),
+                u8::MAX,
+            ],
+            tex_coord: [f16(vertex.tex_coord.x), f16(vertex.tex_coord.y)],
+            normal: [snorm16(normal.x), snorm16(normal.y)],
+            tangent: [
+                snorm8(tangent.x),
+                snorm8(tangent.y),
+                snorm8(vertex.tangent.w.signum()),
+                0,
+            ],
+        }
+    }
+}
+
+/// The same locations as [`Vertex`], but a quantized vertex shader has to
+/// decode the normal and tangent.
+impl VertexLayout for QuantizedVertex {
+    fn binding_descriptions() -> Vec<vk::VertexInputBindingDescription> {
+        vec![per_vertex_binding::<Self>(0)]
+    }
+
+    fn attribute_descriptions() -> Vec<vk::VertexInputAttributeDescription> {
+        vec![
+            attribute(0, 0, vk::Format::R16G16B16A16_UNORM, offset_of!(Self, pos)),
+            attribute(1, 0, vk::Format::R8G8B8A8_UNORM, offset_of!(Self, color)),
+            attribute(2, 0, vk::Format::R16G16_SFLOAT, offset_of!(Self, tex_coord)),
+            attribute(3, 0, vk::Format::R16G16_SNORM, offset_of!(Self, normal)),
+            attribute(4, 0, vk::Format::R8G8B8A8_SNORM, offset_of!(Self, tangent)),
+        ]
+    }
+}
+
+/// The transform from the positions of [`QuantizedVertex`]es encoded with
+/// `aabb` back to model space.
+pub fn dequantize_matrix(aabb: &Aabb) -> glm::Mat4 {
+    glm::translation(&aabb.min) * glm::scaling(&aabb.size())
+}
+
+fn unorm8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * f32::from(u8::MAX)).round() as u8
+}
+
+fn unorm16(value: f32) -> u16 {
+    (value.clamp(0.0, 1.0) * f32::from(u16::MAX)).round() as u16
+}
+
+fn snorm8(value: f32) -> i8 {
+    (value.clamp(-1.0, 1.0) * f32::from(i8::MAX)).round() as i8
+}
+
+fn snorm16(value: f32) -> i16 {
+    (value.clamp(-1.0, 1.0) * f32::from(i16::MAX)).round() as i16
+}
+
+/// Map a direction onto an octahedron, then unfold the octahedron into a
+/// square from -1 to 1. A zero vector is encoded as the origin.
+fn octahedral_encode(dir: &glm::Vec3) -> glm::Vec2 {
+    let length = dir.x.abs() + dir.y.abs() + dir.z.abs();
+    if length == 0.0 {
+        return glm::Vec2::zeros();
+    }
+    let dir = dir / length;
+
+    if dir.z >= 0.0 {
+        dir.xy()
+    } else {
+        // Fold the lower half of the octahedron over the upper half
+        let sign = |v: f32| if v >= 0.0 { 1.0 } else { -1.0 };
+        glm::vec2(
+            (1.0 - dir.y.abs()) * sign(dir.x),
+            (1.0 - dir.x.abs()) * sign(dir.y),
+        )
+    }
+}
+
+/// Convert to the bits of the nearest half float, rounding ties to even.
+/// Values too large for a half float become infinity.
+fn f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    // Infinity and NaN
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa == 0 { 0 } else { 0x200 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // Rounding can carry into the exponent, which is still the right answer
+    let round = |value: u32, shift: u32| {
+        let remainder = value & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let value = value >> shift;
+        if remainder > halfway || (remainder == halfway && value & 1 == 1) {
+            value + 1
+        } else {
+            value
+        }
+    };
+
+    if exponent <= 0 {
+        // Too small for a normal half float, so make a subnormal one
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        return sign | round(mantissa, (14 - exponent) as u32) as u16;
+    }
+
+    sign | round(((exponent as u32) << 23) | mantissa, 13) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skinned_vertices_extend_the_regular_layout() {
+        let attributes = SkinnedVertex::attribute_descriptions();
+        let locations: Vec<_> = attributes.iter().map(|a| a.location).collect();
+        assert_eq!(locations, vec![0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(attributes[4].offset, 44);
+        assert_eq!(attributes[5].offset, size_of::<Vertex>() as u32);
+        assert_eq!(
+            SkinnedVertex::binding_descriptions()[0].stride,
+            size_of::<SkinnedVertex>() as u32
+        );
+    }
+
+    #[test]
+    fn position_vertices_only_have_a_position() {
+        let attributes = PositionVertex::attribute_descriptions();
+        let placement: Vec<_> = attributes
+            .iter()
+            .map(|a| (a.location, a.binding, a.offset, a.format))
+            .collect();
+        assert_eq!(placement, vec![(0, 0, 0, vk::Format::R32G32B32_SFLOAT)]);
+        assert_eq!(PositionVertex::binding_descriptions()[0].stride, 12);
+    }
+
+    #[test]
+    fn colored_vertices_match_the_regular_locations() {
+        let attributes = ColoredVertex::attribute_descriptions();
+        let placement: Vec<_> = attributes
+            .iter()
+            .map(|a| (a.location, a.binding, a.offset))
+            .collect();
+        assert_eq!(placement, vec![(0, 0, 0), (1, 0, 12)]);
+        assert_eq!(ColoredVertex::binding_descriptions()[0].stride, 24);
+
+        // Instances come straight after the color
+        let attributes = <(ColoredVertex, InstanceData)>::attribute_descriptions();
+        assert_eq!((attributes[2].location, attributes[2].binding), (2, 1));
+    }
+
+    #[test]
+    fn pairs_are_read_from_separate_streams() {
+        let bindings = <(PositionVertex, JointWeights)>::binding_descriptions();
+        assert_eq!(bindings.len(), 2);
+        assert_eq!(bindings[0].binding, 0);
+        assert_eq!(bindings[0].stride, 12);
+        assert_eq!(bindings[1].binding, 1);
+        assert_eq!(bindings[1].stride, size_of::<JointWeights>() as u32);
+
+        let attributes = <(PositionVertex, JointWeights)>::attribute_descriptions();
+        let placement: Vec<_> = attributes
+            .iter()
+            .map(|a| (a.location, a.binding, a.offset))
+            .collect();
+        assert_eq!(placement, vec![(0, 0, 0), (1, 1, 0), (2, 1, 8)]);
+    }
+
+    #[test]
+    fn instances_follow_the_vertices() {
+        let bindings = <(Vertex, InstanceData)>::binding_descriptions();
+        assert_eq!(bindings[1].binding, 1);
+        assert_eq!(bindings[1].input_rate, vk::VertexInputRate::INSTANCE);
+        assert_eq!(bindings[1].stride, 84);
+
+        let attributes = <(SkinnedVertex, InstanceData)>::attribute_descriptions();
+        let instance: Vec<_> = attributes[7..]
+            .iter()
+            .map(|a| (a.location, a.binding, a.offset))
+            .collect();
+        assert_eq!(
+            instance,
+            vec![
+                (7, 1, 0),
+                (8, 1, 16),
+                (9, 1, 32),
+                (10, 1, 48),
+                (11, 1, 64),
+                (12, 1, 80)
+            ]
+        );
+    }
+
+    #[test]
+    fn half_floats_round_to_nearest() {
+        assert_eq!(f16(1.0), 0x3c00);
+        assert_eq!(f16(-2.0), 0xc000);
+        assert_eq!(f16(0.5), 0x3800);
+        assert_eq!(f16(65504.0), 0x7bff);
+        assert_eq!(f16(1e6), 0x7c00);
+        // The smallest subnormal is 2^-24
+        assert_eq!(f16(2f32.powi(-24)), 0x0001);
+        // Halfway between 1 and the next half float rounds down to even
+        assert_eq!(f16(1.0 + 2f32.powi(-11)), 0x3c00);
+    }
+
+    #[test]
+    fn quantized_vertices_decode_close_to_the_originals() {
+        let aabb = Aabb {
+            min: glm::vec3(-1.0, 0.0, 2.0),
+            max: glm::vec3(3.0, 2.0, 2.0),
+        };
+        let mut vertex = Vertex::new(
+            glm::vec3(1.0, 0.5, 2.0),
+            glm::vec3(1.0, 0.5, 0.0),
+            glm::vec2(2.5, -1.0),
+            glm::vec3(0.0, -0.6, -0.8),
+        );
+        vertex.tangent = glm::vec4(1.0, 0.0, 0.0, -1.0);
+        let quantized = QuantizedVertex::new(&vertex, &aabb);
+
+        let pos = glm::vec3(
+            f32::from(quantized.pos[0]),
+            f32::from(quantized.pos[1]),
+            f32::from(quantized.pos[2]),
+        ) / f32::from(u16::MAX);
+        let pos = dequantize_matrix(&aabb) * pos.push(1.0);
+        assert!((pos.xyz() - vertex.pos).norm() < 1e-4);
+
+        assert_eq!(quantized.color, [255, 128, 0, 255]);
+        assert_eq!(quantized.tex_coord, [f16(2.5), f16(-1.0)]);
+        assert_eq!(quantized.tangent, [127, 0, -127, 0]);
+
+        // Decode the octahedral normal like the vertex shader does
+        let encoded = glm::vec2(
+            f32::from(quantized.normal[0]),
+            f32::from(quantized.normal[1]),
+        ) / f32::from(i16::MAX);
+        let mut normal = glm::vec3(
+            encoded.x,
+            encoded.y,
+            1.0 - encoded.x.abs() - encoded.y.abs(),
+        );
+        if normal.z < 0.0 {
+            normal.x = (1.0 - encoded.y.abs()) * encoded.x.signum();
+            normal.y = (1.0 - encoded.x.abs()) * encoded.y.signum();
+        }
+        assert!((normal.normalize() - vertex.normal).norm() < 1e-3);
     }
 }
@@ -0,0 +1,168 @@
+//! Watching the files of loaded assets, so that they can be reloaded when
+//! they change on disk.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+};
+
+use ahash::{AHashMap, AHashSet};
+use color_eyre::{eyre::eyre, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tracing::{debug, warn};
+
+use crate::loader::{Asset, Job};
+
+/// Watches the files of a scene's assets for changes.
+///
+/// The directories the files are in are watched, rather than the files
+/// themselves, because many editors save by replacing the file, which would
+/// stop a watch on the file itself from seeing any more changes.
+pub struct AssetWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    /// The jobs that reload the assets loaded from each watched file, which
+    /// may be loaded from another file that refers to it. Paths are canonical,
+    /// to match the paths of the events.
+    jobs: AHashMap<PathBuf, Vec<Job>>,
+    watched_dirs: AHashSet<PathBuf>,
+}
+
+impl AssetWatcher {
+    pub fn new() -> Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let watcher = notify::recommended_watcher(sender)?;
+
+        Ok(Self {
+            watcher,
+            events,
+            jobs: AHashMap::new(),
+            watched_dirs: AHashSet::new(),
+        })
+    }
+
+    /// Start watching the file an asset was loaded from.
+    pub fn watch(&mut self, asset: Asset, path: &Path) -> Result<()> {
+        self.watch_dependency(asset, path, path)
+    }
+
+    /// Start watching another file that an asset loaded from `path` depends
+    /// on, such as one of a model's textures, so that the asset is reloaded
+    /// from `path` when it changes.
+    pub fn watch_dependency(&mut self, asset: Asset, path: &Path, dependency: &Path) -> Result<()> {
+        let path = path.canonicalize()?;
+        let dependency = dependency.canonicalize()?;
+        let dir = dependency
+            .parent()
+            .ok_or_else(|| eyre!("{dependency:?} isn't in a directory"))?
+            .to_owned();
+
+        if !self.watched_dirs.contains(&dir) {
+            self.watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+            debug!(?dir, "Watching directory for asset changes");
+            self.watched_dirs.insert(dir);
+        }
+
+        let jobs = self.jobs.entry(dependency).or_default();
+        if !jobs.iter().any(|job| job.asset == asset) {
+            jobs.push(Job { asset, path });
+        }
+
+        Ok(())
+    }
+
+    /// Jobs to reload every asset whose files have changed since the last
+    /// call. Each asset is only reloaded once, however many times its files
+    /// changed.
+    pub fn changed(&self) -> Vec<Job> {
+        let mut changed = AHashSet::new();
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    changed.extend(event.paths);
+                }
+                Ok(_) => (),
+                Err(e) => warn!(error = %e, "Error watching asset files"),
+            }
+        }
+
+        let mut assets = AHashSet::new();
+        changed
+            .iter()
+            .filter_map(|path| self.jobs.get(path))
+            .flatten()
+            .filter(|job| assets.insert(job.asset))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs, thread,
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+
+    #[test]
+    fn reloads_changed_assets() {
+        let dir = std::env::temp_dir().join(format!("vk-tut-watcher-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let changed_path = dir.join("changed.png");
+        let unchanged_path = dir.join("unchanged.png");
+        fs::write(&changed_path, b"old").unwrap();
+        fs::write(&unchanged_path, b"old").unwrap();
+        let changed_path = changed_path.canonicalize().unwrap();
+
+        let mut watcher = AssetWatcher::new().unwrap();
+        watcher.watch(Asset::Texture(0), &changed_path).unwrap();
+        watcher.watch(Asset::Texture(1), &unchanged_path).unwrap();
+        fs::write(&changed_path, b"new").unwrap();
+
+        // Events take a moment to arrive
+        let start = Instant::now();
+        let mut jobs = Vec::new();
+        while jobs.is_empty() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(20));
+            jobs = watcher.changed();
+        }
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].asset, Asset::Texture(0));
+        assert_eq!(jobs[0].path, changed_path);
+    }
+
+    #[test]
+    fn reloads_assets_when_their_dependencies_change() {
+        let dir = std::env::temp_dir().join(format!("vk-tut-watcher-deps-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let model_path = dir.join("model.obj");
+        let texture_path = dir.join("texture.png");
+        fs::write(&model_path, b"old").unwrap();
+        fs::write(&texture_path, b"old").unwrap();
+        let model_path = model_path.canonicalize().unwrap();
+
+        let mut watcher = AssetWatcher::new().unwrap();
+        watcher.watch(Asset::Mesh(0), &model_path).unwrap();
+        watcher
+            .watch_dependency(Asset::Mesh(0), &model_path, &texture_path)
+            .unwrap();
+        fs::write(&texture_path, b"new").unwrap();
+
+        let start = Instant::now();
+        let mut jobs = Vec::new();
+        while jobs.is_empty() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(20));
+            jobs = watcher.changed();
+        }
+        fs::remove_dir_all(&dir).unwrap();
+
+        // The model is reloaded from its own file
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].asset, Asset::Mesh(0));
+        assert_eq!(jobs[0].path, model_path);
+    }
+}
@@ -0,0 +1,229 @@
+//! A harness for golden-image tests: render a scene offscreen, and compare the
+//! result against a reference PNG checked into `tests/golden/`.
+//!
+//! - Set `VK_TUT_BLESS=1` to (re-)write the reference images from the current
+//!   renderer output instead of comparing against them.
+//! - The tests fail if no Vulkan implementation is available, or if their
+//!   reference image is missing, so they're `#[ignore]`d by default. Run them
+//!   with `cargo test --test golden -- --ignored`.
+//! - When a comparison fails, the rendered image and a diff image are written
+//!   to `target/golden-failures/`.
+
+#![allow(dead_code)]
+
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use vk_tut::{app::App, camera::Camera, screenshot::Screenshot, settings::AppSettings};
+
+/// Describes a scene to render for a golden-image test.
+pub struct GoldenScene {
+    /// Name of the reference image in `tests/golden/`, without extension.
+    pub name: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub camera: Camera,
+    pub num_models: usize,
+    /// How many frames to render before capturing one. Animations don't move
+    /// because the frame clock is frozen, but this exercises frame pacing.
+    pub warmup_frames: usize,
+}
+
+/// How different a rendered image may be from its reference image.
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    /// The maximum absolute difference allowed in any color channel of a pixel
+    /// before the pixel counts as mismatched.
+    pub per_channel: u8,
+    /// The fraction of pixels (from 0 to 1) that may mismatch before the whole
+    /// comparison fails. Allows for small rasterization differences between
+    /// drivers.
+    pub max_mismatched_fraction: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            per_channel: 2,
+            max_mismatched_fraction: 0.001,
+        }
+    }
+}
+
+/// The result of comparing a rendered image against a reference image.
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    pub mismatched_pixels: usize,
+    pub total_pixels: usize,
+    pub max_channel_difference: u8,
+    /// Mismatched pixels are bright red, matching pixels are a dimmed
+    /// grayscale copy of the reference.
+    pub diff: Screenshot,
+}
+
+impl Comparison {
+    pub fn passes(&self, tolerance: Tolerance) -> bool {
+        self.mismatched_pixels as f64
+            <= tolerance.max_mismatched_fraction * self.total_pixels as f64
+    }
+}
+
+/// Compare two images of the same size pixel by pixel.
+pub fn compare_images(
+    actual: &Screenshot,
+    expected: &Screenshot,
+    tolerance: Tolerance,
+) -> Comparison {
+    assert_eq!(
+        (actual.width, actual.height),
+        (expected.width, expected.height),
+        "Can only compare images of the same size"
+    );
+
+    let mut mismatched_pixels = 0;
+    let mut max_channel_difference = 0;
+    let mut diff_pixels = Vec::with_capacity(expected.pixels.len());
+
+    for (a, e) in actual
+        .pixels
+        .chunks_exact(4)
+        .zip(expected.pixels.chunks_exact(4))
+    {
+        let difference = a
+            .iter()
+            .zip(e)
+            .map(|(a, e)| a.abs_diff(*e))
+            .max()
+            .unwrap_or(0);
+        max_channel_difference = max_channel_difference.max(difference);
+
+        if difference > tolerance.per_channel {
+            mismatched_pixels += 1;
+            diff_pixels.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 3 / 4) as u8;
+            diff_pixels.extend_from_slice(&[luma, luma, luma, 255]);
+        }
+    }
+
+    Comparison {
+        mismatched_pixels,
+        total_pixels: (expected.width * expected.height) as usize,
+        max_channel_difference,
+        diff: Screenshot {
+            width: expected.width,
+            height: expected.height,
+            pixels: diff_pixels,
+        },
+    }
+}
+
+/// Render `scene` offscreen and compare it against its reference image.
+///
+/// Panics if the comparison fails.
+pub fn assert_matches_golden(scene: &GoldenScene, tolerance: Tolerance) {
+    let actual = render_scene(scene)
+        .unwrap_or_else(|e| panic!("Failed to render golden scene {:?}: {e:?}", scene.name));
+
+    let reference_path = golden_dir().join(format!("{}.png", scene.name));
+
+    if env_flag("VK_TUT_BLESS") {
+        fs::create_dir_all(golden_dir()).unwrap();
+        actual.save_png(&reference_path).unwrap();
+        eprintln!("Blessed golden image {reference_path:?}");
+        return;
+    }
+
+    let expected = load_png(&reference_path).unwrap_or_else(|e| {
+        panic!("Failed to load reference image {reference_path:?} (run with VK_TUT_BLESS=1 to create it): {e}")
+    });
+
+    if (actual.width, actual.height) != (expected.width, expected.height) {
+        let actual_path = write_failure_image(scene.name, "actual", &actual);
+        panic!(
+            "Rendered image is {}x{}, but reference {reference_path:?} is {}x{}. Rendered image written to {actual_path:?}",
+            actual.width, actual.height, expected.width, expected.height
+        );
+    }
+
+    let comparison = compare_images(&actual, &expected, tolerance);
+    if !comparison.passes(tolerance) {
+        let actual_path = write_failure_image(scene.name, "actual", &actual);
+        let diff_path = write_failure_image(scene.name, "diff", &comparison.diff);
+        panic!(
+            "Golden image {:?} mismatched: {} of {} pixels differ by more than {} (max difference {}). \
+             Rendered image written to {actual_path:?}, diff written to {diff_path:?}",
+            scene.name,
+            comparison.mismatched_pixels,
+            comparison.total_pixels,
+            tolerance.per_channel,
+            comparison.max_channel_difference,
+        );
+    }
+}
+
+/// Render a single frame of `scene` with a headless app.
+fn render_scene(scene: &GoldenScene) -> color_eyre::Result<Screenshot> {
+    unsafe {
+        let settings = AppSettings {
+            width: scene.width,
+            height: scene.height,
+            ..Default::default()
+        };
+
+        let mut app = App::create_headless(&settings)?;
+        app.wait_for_assets()?;
+        app.set_fixed_frame_delta(Some(0.0));
+        app.set_camera(scene.camera);
+        app.num_models = scene.num_models;
+
+        let result = (0..=scene.warmup_frames)
+            .try_for_each(|_| app.render_offscreen().map(|_| ()))
+            .and_then(|_| app.capture_screenshot());
+
+        app.wait_for_device_idle()?;
+        app.destroy();
+
+        result
+    }
+}
+
+/// Load an 8-bit RGBA PNG image.
+pub fn load_png(path: &Path) -> Result<Screenshot, png::DecodingError> {
+    let decoder = png::Decoder::new(File::open(path)?);
+    let mut reader = decoder.read_info()?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels)?;
+
+    assert_eq!(
+        (info.color_type, info.bit_depth),
+        (png::ColorType::Rgba, png::BitDepth::Eight),
+        "Reference images must be 8-bit RGBA"
+    );
+    pixels.truncate(info.buffer_size());
+
+    Ok(Screenshot {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+fn write_failure_image(name: &str, kind: &str, image: &Screenshot) -> PathBuf {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/golden-failures");
+    fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(format!("{name}.{kind}.png"));
+    image.save_png(&path).unwrap();
+    path
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn env_flag(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|v| v == "1")
+}
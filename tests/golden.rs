@@ -0,0 +1,83 @@
+//! Golden-image regression tests for rendered frames. See `tests/common` for
+//! how the harness works and how to update the reference images.
+
+mod common;
+
+use common::{assert_matches_golden, compare_images, GoldenScene, Tolerance};
+use nalgebra_glm as glm;
+use vk_tut::{camera::Camera, screenshot::Screenshot};
+
+#[test]
+#[ignore = "needs Vulkan, run with --ignored"]
+fn viking_room() {
+    assert_matches_golden(
+        &GoldenScene {
+            name: "viking_room",
+            width: 320,
+            height: 240,
+            camera: Camera::default(),
+            num_models: 1,
+            warmup_frames: 2,
+        },
+        Tolerance::default(),
+    );
+}
+
+#[test]
+#[ignore = "needs Vulkan, run with --ignored"]
+fn viking_room_four_copies_from_above() {
+    assert_matches_golden(
+        &GoldenScene {
+            name: "viking_room_four_copies_from_above",
+            width: 320,
+            height: 240,
+            camera: Camera {
+                eye: glm::vec3(7.0, 0.0, 6.0),
+                target: glm::vec3(0.0, 0.0, 0.0),
+                far: 20.0,
+                ..Camera::default()
+            },
+            num_models: 4,
+            warmup_frames: 2,
+        },
+        Tolerance::default(),
+    );
+}
+
+fn solid_image(width: u32, height: u32, rgba: [u8; 4]) -> Screenshot {
+    Screenshot {
+        width,
+        height,
+        pixels: rgba.repeat((width * height) as usize),
+    }
+}
+
+#[test]
+fn comparison_allows_differences_within_tolerance() {
+    let expected = solid_image(4, 4, [100, 100, 100, 255]);
+    let actual = solid_image(4, 4, [102, 98, 100, 255]);
+
+    let comparison = compare_images(&actual, &expected, Tolerance::default());
+
+    assert_eq!(comparison.mismatched_pixels, 0);
+    assert_eq!(comparison.max_channel_difference, 2);
+    assert!(comparison.passes(Tolerance::default()));
+}
+
+#[test]
+fn comparison_flags_mismatched_pixels_in_diff_image() {
+    let expected = solid_image(2, 2, [100, 100, 100, 255]);
+    let mut actual = expected.clone();
+    actual.pixels[4..8].copy_from_slice(&[0, 0, 0, 255]);
+
+    let tolerance = Tolerance {
+        per_channel: 2,
+        max_mismatched_fraction: 0.0,
+    };
+    let comparison = compare_images(&actual, &expected, tolerance);
+
+    assert_eq!(comparison.mismatched_pixels, 1);
+    assert!(!comparison.passes(tolerance));
+    assert_eq!(&comparison.diff.pixels[4..8], &[255, 0, 0, 255]);
+    assert_ne!(&comparison.diff.pixels[0..4], &[255, 0, 0, 255]);
+}